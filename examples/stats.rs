@@ -14,5 +14,5 @@ fn main() {
 
     let bitmap = values.into_iter().collect::<Roaring>();
 
-    println!("{:#?}", bitmap.stats());
+    println!("{}", bitmap.stats());
 }
@@ -0,0 +1,121 @@
+use crate::{Roaring, RoaringLazy, RoaringTreeMap, RoaringTwoLevels};
+use alloc::{vec, vec::Vec};
+
+/// Bitmap implementation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Representation {
+    /// `Roaring`, for 32-bit integers.
+    Roaring,
+    /// `RoaringTwoLevels`, for 64-bit integers.
+    RoaringTwoLevels,
+    /// `RoaringTreeMap`, for 64-bit integers.
+    RoaringTreeMap,
+    /// `RoaringLazy`, for 64-bit integers.
+    RoaringLazy,
+}
+
+/// Report on the best bitmap representation for a set of values.
+#[derive(Clone, Debug)]
+pub struct Analysis {
+    /// Approximate in-memory size of every implementation able to hold the
+    /// values, smallest first.
+    pub sizes: Vec<(Representation, usize)>,
+    /// Whether calling `run_optimize` would make the bitmap smaller.
+    ///
+    /// `None` when the values don't fit in a `Roaring` bitmap, the only one
+    /// to support explicit run compression.
+    pub run_optimize: Option<bool>,
+}
+
+impl Analysis {
+    /// Returns the representation using the least memory.
+    ///
+    /// On ties, `Roaring` is preferred as it uses a single index level on
+    /// 16-bit keys and is thus the fastest one.
+    pub fn recommended(&self) -> Option<Representation> {
+        self.sizes
+            .first()
+            .map(|&(representation, _)| representation)
+    }
+}
+
+/// Analyzes a set of values (or a representative sample) by building every
+/// bitmap implementation from them and measuring their memory usage.
+pub fn analyze<I: IntoIterator<Item = u64>>(values: I) -> Analysis {
+    let mut values = values.into_iter().collect::<Vec<_>>();
+    values.sort_unstable();
+    values.dedup();
+
+    let mut sizes = vec![
+        (
+            Representation::RoaringTwoLevels,
+            values
+                .iter()
+                .copied()
+                .collect::<RoaringTwoLevels>()
+                .mem_size(),
+        ),
+        (
+            Representation::RoaringTreeMap,
+            values
+                .iter()
+                .copied()
+                .collect::<RoaringTreeMap>()
+                .mem_size(),
+        ),
+        (
+            Representation::RoaringLazy,
+            values.iter().copied().collect::<RoaringLazy>().mem_size(),
+        ),
+    ];
+    let run_optimize = values
+        .iter()
+        .map(|&value| u32::try_from(value))
+        .collect::<Result<Roaring, _>>()
+        .ok()
+        .map(|mut bitmap| {
+            let size = bitmap.mem_size();
+            bitmap.run_optimize();
+            let optimized_size = bitmap.mem_size();
+
+            sizes
+                .insert(0, (Representation::Roaring, size.min(optimized_size)));
+            optimized_size < size
+        });
+    // Stable sort: keeps `Roaring` first on ties.
+    sizes.sort_by_key(|&(_, size)| size);
+
+    Analysis {
+        sizes,
+        run_optimize,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn analyze_32bit() {
+        let analysis = analyze(0..1_000);
+
+        assert_eq!(analysis.sizes.len(), 4);
+        assert_eq!(analysis.recommended(), Some(Representation::Roaring));
+        assert_eq!(analysis.run_optimize, Some(true), "consecutive values");
+
+        let analysis = analyze((0..1_000).map(|value| value * 3));
+        assert_eq!(analysis.run_optimize, Some(false), "no runs");
+    }
+
+    #[test]
+    fn analyze_64bit() {
+        let analysis = analyze([1, 1 << 40, 1 << 50]);
+
+        assert_eq!(analysis.sizes.len(), 3);
+        assert!(!analysis.sizes.iter().any(|&(representation, _)| {
+            representation == Representation::Roaring
+        }));
+        assert!(analysis.run_optimize.is_none());
+        assert!(analysis.sizes.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+    }
+}
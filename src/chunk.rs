@@ -1,11 +1,41 @@
-use crate::containers::{self, Container};
-use std::mem;
-
+use crate::{
+    containers::{self, Container, Pool},
+    ChunkInfo,
+};
+use core::mem;
+
+// Maximum number of elements in a chunk.
+const CHUNK_CAPACITY: usize = 65_536;
 // Number of elements that defines the limit between a sparse and dense chunk.
 const SPARSE_CHUNK_THRESHOLD: usize = 4_096;
 
+// Payload size of a value in an array container, in bytes.
+const ARRAY_VALUE_SIZE: usize = 2;
+// Payload size of a bitmap container, in bytes.
+const BITMAP_SIZE: usize = 8_192;
+// Payload size of a run (start and length) in a run container, in bytes.
+const RUN_SIZE: usize = 4;
+
+/// Settings driving the choice of the containers.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct Tuning {
+    /// Cardinality up to which a chunk uses an array container.
+    pub(super) sparse_threshold: usize,
+    /// Whether run containers are used when they are the most compact.
+    pub(super) use_runs: bool,
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Self {
+            sparse_threshold: SPARSE_CHUNK_THRESHOLD,
+            use_runs: true,
+        }
+    }
+}
+
 /// A chunk header, providing key and cardinality handling.
-pub(super) trait Header {
+pub trait Header {
     type Key;
 
     /// Returns the chunk's key.
@@ -14,9 +44,13 @@ pub(super) trait Header {
     fn cardinality(&self) -> usize;
 
     /// Increases by 1 the chunk's cardinality.
+    ///
+    /// Never called on a full chunk (65536 values), as no value can be added.
     fn increase_cardinality(&mut self);
     /// Decreases by 1 the chunk's cardinality.
     fn decrease_cardinality(&mut self);
+    /// Sets the chunk's cardinality.
+    fn set_cardinality(&mut self, cardinality: usize);
 }
 
 /// Chunks of 2¹⁶ integers, using containers adapted to the density.
@@ -28,25 +62,106 @@ pub(super) struct Chunk<H> {
 }
 
 pub(super) type Iter<'a> = containers::Iter<'a>;
+pub(super) type IntoIter = containers::IntoIter;
+pub(super) type Ranges<'a> = containers::Ranges<'a>;
 
 impl<H: Header> Chunk<H> {
     /// Initializes a new chunk with the given value.
     pub(super) fn new(header: H, value: u16) -> Self {
+        #[cfg(feature = "metrics")]
+        crate::metrics::record_chunk_allocation();
         Self {
             header,
             container: Container::new(value),
         }
     }
 
+    /// Initializes a new chunk from an existing non-empty container.
+    pub(super) fn from_container(
+        header: H,
+        container: Container,
+        cardinality: usize,
+    ) -> Self {
+        Self::from_container_in(
+            header,
+            container,
+            cardinality,
+            &mut Pool::default(),
+            &Tuning::default(),
+        )
+    }
+
+    /// Initializes a new chunk from an existing non-empty container, adapted
+    /// to the settings of a bitmap and recycling allocations through `pool`.
+    pub(super) fn from_container_in(
+        mut header: H,
+        container: Container,
+        cardinality: usize,
+        pool: &mut Pool,
+        tuning: &Tuning,
+    ) -> Self {
+        header.set_cardinality(cardinality);
+        #[cfg(feature = "metrics")]
+        crate::metrics::record_chunk_allocation();
+
+        let mut chunk = Self { header, container };
+        chunk.optimize_container_in(pool, tuning);
+        chunk
+    }
+
     /// Adds a value to the chunk.
     ///
     /// If the chunk did not have this value present, true is returned.
     /// If the chunk did have this value present, false is returned.
     pub(super) fn insert(&mut self, value: u16) -> bool {
+        let added = self.insert_deferred(value);
+        if added {
+            self.optimize_container();
+        }
+        added
+    }
+
+    /// Adds a value to the chunk, recycling allocations through `pool`.
+    ///
+    /// Returns whether the value was added or not.
+    pub(super) fn insert_in(
+        &mut self,
+        value: u16,
+        pool: &mut Pool,
+        tuning: &Tuning,
+    ) -> bool {
+        let added = self.insert_deferred(value);
+        if added {
+            self.optimize_container_in(pool, tuning);
+        }
+        added
+    }
+
+    /// Adds a value to the chunk, keeping the current container.
+    ///
+    /// `optimize_container` must be called once the updates are done.
+    pub(super) fn insert_deferred(&mut self, value: u16) -> bool {
         let added = self.container.insert(value);
         if added {
             self.header.increase_cardinality();
-            self.optimize_container();
+        }
+        added
+    }
+
+    /// Adds a slice of values, sorted in ascending order, to the chunk,
+    /// recycling allocations through `pool`.
+    ///
+    /// Returns the number of values that were not already present.
+    pub(super) fn insert_sorted_slice_in(
+        &mut self,
+        values: &[u16],
+        pool: &mut Pool,
+        tuning: &Tuning,
+    ) -> usize {
+        let added = self.container.insert_sorted_slice(values);
+        if added != 0 {
+            self.header.set_cardinality(self.cardinality() + added);
+            self.optimize_container_in(pool, tuning);
         }
         added
     }
@@ -55,10 +170,36 @@ impl<H: Header> Chunk<H> {
     ///
     /// Returns whether the value was present or not.
     pub(super) fn remove(&mut self, value: u16) -> bool {
+        let removed = self.remove_deferred(value);
+        if removed {
+            self.optimize_container();
+        }
+        removed
+    }
+
+    /// Removes a value from the chunk, recycling allocations through `pool`.
+    ///
+    /// Returns whether the value was present or not.
+    pub(super) fn remove_in(
+        &mut self,
+        value: u16,
+        pool: &mut Pool,
+        tuning: &Tuning,
+    ) -> bool {
+        let removed = self.remove_deferred(value);
+        if removed {
+            self.optimize_container_in(pool, tuning);
+        }
+        removed
+    }
+
+    /// Removes a value from the chunk, keeping the current container.
+    ///
+    /// `optimize_container` must be called once the updates are done.
+    pub(super) fn remove_deferred(&mut self, value: u16) -> bool {
         let removed = self.container.remove(value);
         if removed {
             self.header.decrease_cardinality();
-            self.optimize_container();
         }
         removed
     }
@@ -73,6 +214,11 @@ impl<H: Header> Chunk<H> {
         self.header.key()
     }
 
+    /// Consumes the chunk, returning its container.
+    pub(super) fn into_container(self) -> Container {
+        self.container
+    }
+
     /// Returns the chunk container.
     pub(super) fn container(&self) -> &Container {
         &self.container
@@ -99,31 +245,207 @@ impl<H: Header> Chunk<H> {
         self.container.iter()
     }
 
+    /// Gets an iterator that visits the runs of consecutive values in the
+    /// chunk in ascending order.
+    pub(super) fn ranges(&self) -> Ranges<'_> {
+        self.container.ranges()
+    }
+
     /// Returns the approximate in-memory size of the chunk, in bytes.
     pub(super) fn mem_size(&self) -> usize {
-        mem::size_of_val(&self.header) + self.container.mem_size()
+        size_of_val(&self.header) + self.container.mem_size()
+    }
+
+    /// Estimates by how many bytes the chunk would grow if `value`, absent
+    /// from it, was added by [`Self::insert_in`].
+    ///
+    /// Conversions are accounted for pessimistically, as if the allocation of
+    /// the previous container was handed over to the pool with as much spare
+    /// capacity.
+    pub(super) fn insert_mem_delta(
+        &self,
+        value: u16,
+        tuning: &Tuning,
+    ) -> usize {
+        let cardinality = self.cardinality() + 1;
+        let is_sparse = cardinality <= tuning.sparse_threshold;
+        // Payload of a run container after the insertion, if the best one.
+        let run_size = || {
+            let neighbors = [value.checked_sub(1), value.checked_add(1)]
+                .into_iter()
+                .flatten()
+                .filter(|&neighbor| self.contains(neighbor))
+                .count();
+            let size = (self.container.count_runs() + 1 - neighbors) * RUN_SIZE;
+            (tuning.use_runs
+                && size < (cardinality * ARRAY_VALUE_SIZE).min(BITMAP_SIZE))
+            .then_some(size)
+        };
+        let array_size = cardinality * ARRAY_VALUE_SIZE;
+
+        // Mirrors `optimize_container_in`.
+        let converted = match self.container {
+            Container::Full => None,
+            _ if cardinality == CHUNK_CAPACITY => return 0,
+            Container::Array(_) if !is_sparse => {
+                Some(run_size().unwrap_or(BITMAP_SIZE))
+            },
+            Container::Bitmap(_) if is_sparse => {
+                Some(run_size().unwrap_or(array_size))
+            },
+            Container::Run(_) if run_size().is_none() => {
+                Some(if is_sparse { array_size } else { BITMAP_SIZE })
+            },
+            _ => None,
+        };
+
+        converted.map_or_else(
+            || self.container.insert_mem_delta(value),
+            |payload| {
+                payload + size_of::<Container>() + self.container.mem_size()
+            },
+        )
+    }
+
+    /// Releases the unused capacity of the container's buffers.
+    pub(super) fn shrink_to_fit(&mut self) {
+        self.container.shrink_to_fit();
+    }
+
+    /// Returns the layout of the chunk.
+    pub(super) fn info(&self) -> ChunkInfo<H::Key> {
+        ChunkInfo {
+            key: self.key(),
+            kind: self.container.kind(),
+            cardinality: self.cardinality(),
+            mem_size: self.mem_size(),
+        }
+    }
+
+    /// Converts the container to its most compact representation, including
+    /// run containers.
+    ///
+    /// Returns true if the chunk uses a run container.
+    pub(super) fn run_optimize(&mut self) -> bool {
+        let run_container = match self.container {
+            Container::Array(ref array) if self.is_run_smaller() => {
+                Some(Container::Run(array.into()))
+            },
+            Container::Bitmap(ref bitmap) if self.is_run_smaller() => {
+                Some(Container::Run(bitmap.into()))
+            },
+            _ => None,
+        };
+
+        if let Some(container) = run_container {
+            #[cfg(feature = "metrics")]
+            crate::metrics::record_conversion();
+            self.container = container;
+        }
+        matches!(self.container, Container::Run(_))
+    }
+
+    /// Converts a run container back into an array or bitmap container.
+    ///
+    /// Returns true if the container was converted.
+    pub(super) fn remove_run_compression(&mut self, tuning: &Tuning) -> bool {
+        let is_sparse = self.cardinality() <= tuning.sparse_threshold;
+        let container = match self.container {
+            Container::Run(ref run) if is_sparse => {
+                Container::Array(run.into())
+            },
+            Container::Run(ref run) => Container::Bitmap(run.into()),
+            _ => return false,
+        };
+
+        #[cfg(feature = "metrics")]
+        crate::metrics::record_conversion();
+        self.container = container;
+        true
     }
 
     /// Ensures that the container is adapted to the chunk's cardinality.
-    fn optimize_container(&mut self) {
-        let better_container = match (&self.container, self.cardinality()) {
-            (&Container::Array(ref array), cardinality)
-                if cardinality > SPARSE_CHUNK_THRESHOLD =>
-            {
-                Some(Container::Bitmap(array.into()))
+    ///
+    /// Switching to a run container is only considered when crossing the
+    /// sparse/dense threshold, as counting runs isn't free.
+    pub(super) fn optimize_container(&mut self) {
+        self.optimize_container_in(&mut Pool::default(), &Tuning::default());
+    }
+
+    /// Ensures that the container is adapted to the chunk's cardinality,
+    /// recycling allocations through `pool`.
+    pub(super) fn optimize_container_in(
+        &mut self,
+        pool: &mut Pool,
+        tuning: &Tuning,
+    ) {
+        let is_sparse = self.cardinality() <= tuning.sparse_threshold;
+        let use_run = || tuning.use_runs && self.is_run_smaller();
+        let better_container = match self.container {
+            Container::Full => None,
+            _ if self.cardinality() == CHUNK_CAPACITY => Some(Container::Full),
+            Container::Array(ref array) if !is_sparse => {
+                Some(if use_run() {
+                    Container::Run(array.into())
+                } else {
+                    pool.bitmap(self.container.iter())
+                })
             },
-            (&Container::Bitmap(ref bitmap), cardinality)
-                if cardinality <= SPARSE_CHUNK_THRESHOLD =>
-            {
-                Some(Container::Array(bitmap.into()))
+            Container::Bitmap(ref bitmap) if is_sparse => {
+                Some(if use_run() {
+                    Container::Run(bitmap.into())
+                } else {
+                    pool.array(self.container.iter())
+                })
+            },
+            Container::Run(_) if !use_run() => {
+                Some(if is_sparse {
+                    pool.array(self.container.iter())
+                } else {
+                    pool.bitmap(self.container.iter())
+                })
             },
             _ => None,
         };
 
         if let Some(container) = better_container {
-            self.container = container;
+            #[cfg(feature = "metrics")]
+            crate::metrics::record_conversion();
+            pool.recycle(mem::replace(&mut self.container, container));
         }
     }
+
+    /// Returns true if a run container is smaller than both an array and a
+    /// bitmap container for the chunk's values.
+    fn is_run_smaller(&self) -> bool {
+        let run_size = self.container.count_runs() * RUN_SIZE;
+        let array_size = self.cardinality() * ARRAY_VALUE_SIZE;
+
+        run_size < array_size.min(BITMAP_SIZE)
+    }
+}
+
+impl<H: Clone> Clone for Chunk<H> {
+    fn clone(&self) -> Self {
+        Self {
+            header: self.header.clone(),
+            container: self.container.clone(),
+        }
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        self.header.clone_from(&source.header);
+        self.container.clone_from(&source.container);
+    }
+}
+
+impl<H> IntoIterator for Chunk<H> {
+    type Item = u16;
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> IntoIter {
+        self.container.into_iter()
+    }
 }
 
 #[cfg(test)]
@@ -142,13 +464,13 @@ mod tests {
 
         // They keep using an array until they hit the density threshold.
         for value in 1..SPARSE_CHUNK_THRESHOLD {
-            chunk.insert(value as u16);
+            chunk.insert(u16::try_from(value * 2).expect("valid u16"));
             assert!(chunk.cardinality() <= SPARSE_CHUNK_THRESHOLD);
             assert!(matches!(chunk.container, Container::Array(_)));
         }
 
         // From there, they migrate the values into a bitmap container.
-        chunk.insert(4242);
+        chunk.insert(4243);
         chunk.insert(8888);
         assert!(chunk.cardinality() > SPARSE_CHUNK_THRESHOLD);
         assert!(matches!(chunk.container, Container::Bitmap(_)));
@@ -156,7 +478,7 @@ mod tests {
         // Original data (min) and new ones (max) are both here.
         assert_eq!(chunk.min(), Some(0));
         assert_eq!(chunk.max(), Some(8888));
-        assert!(chunk.contains(4242));
+        assert!(chunk.contains(4243));
 
         // Move values back into an array when the density is below the
         // threshold.
@@ -166,17 +488,200 @@ mod tests {
         assert!(matches!(chunk.container, Container::Array(_)));
     }
 
+    #[test]
+    fn run_container() {
+        let header = Header::new(0);
+        let mut chunk = Chunk::new(header, 0);
+
+        // Long runs use a run container once out of the sparse range…
+        for value in 1..30_000 {
+            chunk.insert(value);
+        }
+        assert!(matches!(chunk.container, Container::Run(_)));
+        assert_eq!(chunk.cardinality(), 30_000);
+
+        // … and keep it while it's the most compact representation.
+        for value in (0..2_000).step_by(2) {
+            chunk.remove(value);
+        }
+        assert!(matches!(chunk.container, Container::Run(_)));
+
+        // Until there are too many runs.
+        for value in (2_000..6_000).step_by(2) {
+            chunk.remove(value);
+        }
+        assert!(matches!(chunk.container, Container::Bitmap(_)));
+        assert_eq!(chunk.cardinality(), 27_000);
+        assert!(chunk.contains(1_999));
+        assert!(!chunk.contains(2_000));
+        assert!(chunk.contains(6_000));
+    }
+
+    #[test]
+    fn full_container() {
+        let header = Header::new(0);
+        let mut chunk = Chunk::new(header, 0);
+
+        // Saturated chunks use a payload-free container.
+        for value in 1..=u16::MAX {
+            chunk.insert(value);
+        }
+        assert!(matches!(chunk.container, Container::Full));
+        assert_eq!(chunk.cardinality(), 65_536);
+        assert_eq!(chunk.container.mem_size(), 0);
+        assert_eq!(chunk.min(), Some(0));
+        assert_eq!(chunk.max(), Some(u16::MAX));
+        assert!(chunk.contains(42));
+        assert!(!chunk.insert(42), "already exists");
+        assert_eq!(chunk.iter().count(), 65_536);
+
+        // Removing a value falls back to a run container.
+        assert!(chunk.remove(42), "found");
+        assert!(matches!(chunk.container, Container::Run(_)));
+        assert_eq!(chunk.cardinality(), 65_535);
+        assert!(!chunk.contains(42));
+
+        // Back to full.
+        chunk.insert(42);
+        assert!(matches!(chunk.container, Container::Full));
+    }
+
+    #[test]
+    fn run_optimize() {
+        let header = Header::new(0);
+        let mut chunk = Chunk::new(header, 0);
+        for value in 1..100 {
+            chunk.insert(value);
+        }
+        assert!(matches!(chunk.container, Container::Array(_)));
+
+        assert!(chunk.run_optimize(), "runs are smaller");
+        assert!(matches!(chunk.container, Container::Run(_)));
+        assert_eq!(
+            chunk.iter().collect::<Vec<_>>(),
+            (0..100).collect::<Vec<_>>()
+        );
+
+        assert!(
+            chunk.remove_run_compression(&Tuning::default()),
+            "converted back"
+        );
+        assert!(matches!(chunk.container, Container::Array(_)));
+        assert!(
+            !chunk.remove_run_compression(&Tuning::default()),
+            "nothing to convert"
+        );
+        assert_eq!(
+            chunk.iter().collect::<Vec<_>>(),
+            (0..100).collect::<Vec<_>>()
+        );
+
+        // Runs are not used when they aren't the most compact representation.
+        let header = Header::new(0);
+        let mut chunk = Chunk::new(header, 0);
+        chunk.insert(10);
+        chunk.insert(20);
+        assert!(!chunk.run_optimize(), "array is smaller");
+        assert!(matches!(chunk.container, Container::Array(_)));
+    }
+
+    #[test]
+    fn insert_sorted_slice() {
+        let mut chunk = Chunk::new(Header::new(0), 1);
+        let mut pool = Pool::default();
+        let tuning = Tuning::default();
+
+        let evens = (0..10_000).map(|value| value * 2).collect::<Vec<_>>();
+        assert_eq!(
+            chunk.insert_sorted_slice_in(&evens, &mut pool, &tuning),
+            10_000
+        );
+        assert_eq!(chunk.cardinality(), 10_001);
+        assert!(matches!(chunk.container, Container::Bitmap(_)));
+
+        assert_eq!(
+            chunk.insert_sorted_slice_in(&evens[..10], &mut pool, &tuning),
+            0
+        );
+        assert_eq!(chunk.cardinality(), 10_001);
+    }
+
+    #[test]
+    fn insert_mem_delta() {
+        for use_runs in [true, false] {
+            let mut pool = Pool::default();
+            let tuning = Tuning {
+                use_runs,
+                ..Tuning::default()
+            };
+            let mut chunk = Chunk::new(Header::new(0), 0);
+
+            // Array, then bitmap, then run (or full) containers.
+            let values = (2..10_000).step_by(2).chain((1..10_000).step_by(2));
+            for value in values.chain(10_000..=u16::MAX) {
+                let size = chunk.mem_size() + pool.mem_size();
+                let delta = chunk.insert_mem_delta(value, &tuning);
+                chunk.insert_in(value, &mut pool, &tuning);
+                assert!(chunk.mem_size() + pool.mem_size() <= size + delta);
+            }
+            assert!(matches!(chunk.container, Container::Full));
+        }
+    }
+
+    #[test]
+    fn tuning() {
+        let mut pool = Pool::default();
+        let tuning = Tuning {
+            sparse_threshold: 8_192,
+            use_runs: false,
+        };
+        let mut chunk = Chunk::new(Header::new(0), 0);
+
+        // Arrays are used up to the custom threshold, even for long runs.
+        for value in 1..8_192 {
+            chunk.insert_in(value, &mut pool, &tuning);
+        }
+        assert!(matches!(chunk.container, Container::Array(_)));
+        chunk.insert_in(8_192, &mut pool, &tuning);
+        assert!(matches!(chunk.container, Container::Bitmap(_)));
+
+        // Explicit run containers are dropped by the next update.
+        assert!(chunk.run_optimize());
+        chunk.remove_in(42, &mut pool, &tuning);
+        assert!(matches!(chunk.container, Container::Array(_)));
+        assert_eq!(chunk.cardinality(), 8_192);
+    }
+
+    #[test]
+    fn from_container() {
+        let mut evens = Chunk::new(Header::new(0), 0);
+        let mut odds = Chunk::new(Header::new(0), 1);
+        for value in 1..2_500 {
+            evens.insert(value * 2);
+            odds.insert(value * 2 + 1);
+        }
+        let (container, cardinality) =
+            evens.container().or(odds.container()).expect("non-empty");
+
+        // The container is adapted to the cardinality.
+        let chunk =
+            Chunk::from_container(Header::new(0), container, cardinality);
+        assert!(matches!(chunk.container, Container::Run(_)));
+        assert_eq!(chunk.cardinality(), 5_000);
+        assert_eq!(chunk.max(), Some(4_999));
+    }
+
     #[test]
     fn contains() {
         let header = Header::new(0);
         let mut chunk = Chunk::new(header, 42);
-        assert_eq!(chunk.contains(11), false);
+        assert!(!chunk.contains(11));
 
         chunk.insert(11);
-        assert_eq!(chunk.contains(11), true);
+        assert!(chunk.contains(11));
 
         chunk.remove(11);
-        assert_eq!(chunk.contains(11), false);
+        assert!(!chunk.contains(11));
     }
 
     #[test]
@@ -184,10 +689,10 @@ mod tests {
         let header = Header::new(0);
         let mut chunk = Chunk::new(header, 42);
 
-        assert_eq!(chunk.insert(42), false, "already exists");
+        assert!(!chunk.insert(42), "already exists");
         assert_eq!(chunk.cardinality(), 1);
 
-        assert_eq!(chunk.insert(11), true, "new entry");
+        assert!(chunk.insert(11), "new entry");
         assert_eq!(chunk.cardinality(), 2);
     }
 
@@ -196,8 +701,8 @@ mod tests {
         let header = Header::new(0);
         let mut chunk = Chunk::new(header, 42);
 
-        assert_eq!(chunk.remove(42), true, "found");
-        assert_eq!(chunk.remove(11), false, "missing entry");
+        assert!(chunk.remove(42), "found");
+        assert!(!chunk.remove(11), "missing entry");
     }
 
     #[test]
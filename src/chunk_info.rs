@@ -0,0 +1,27 @@
+/// Kind of container used by a chunk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ContainerKind {
+    /// Sorted array of values, for sparse chunks.
+    Array,
+    /// Bitmap of 2¹⁶ bits, for dense chunks.
+    Bitmap,
+    /// Runs of consecutive values.
+    Run,
+    /// Payload-free container, for chunks holding every possible value.
+    Full,
+}
+
+/// Layout of a bitmap chunk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ChunkInfo<K> {
+    /// Chunk's key (i.e. the most significant bits of its values).
+    pub key: K,
+    /// Kind of container used by the chunk.
+    pub kind: ContainerKind,
+    /// Number of values in the chunk.
+    pub cardinality: usize,
+    /// Number of allocated bytes (approximated).
+    pub mem_size: usize,
+}
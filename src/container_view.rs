@@ -0,0 +1,83 @@
+use crate::{containers, Container, ContainerKind};
+
+/// Read-only view over the container of a bitmap chunk.
+///
+/// Values are the 16 least significant bits of the chunk's values: combine
+/// them with the chunk's key to get back the full values.
+#[derive(Clone, Copy)]
+pub struct ContainerView<'a> {
+    /// Viewed container.
+    container: &'a Container,
+    /// Number of values in the container.
+    cardinality: usize,
+}
+
+impl<'a> ContainerView<'a> {
+    /// Initializes a view over a container holding `cardinality` values.
+    pub(crate) fn new(container: &'a Container, cardinality: usize) -> Self {
+        Self {
+            container,
+            cardinality,
+        }
+    }
+
+    /// Returns true if the container contains the value.
+    pub fn contains(self, value: u16) -> bool {
+        self.container.contains(value)
+    }
+
+    /// Returns the container cardinality.
+    pub fn cardinality(self) -> usize {
+        self.cardinality
+    }
+
+    /// Counts the runs of consecutive values in the container.
+    pub fn count_runs(self) -> usize {
+        self.container.count_runs()
+    }
+
+    /// Returns the kind of the container.
+    pub fn kind(self) -> ContainerKind {
+        self.container.kind()
+    }
+
+    /// Finds the smallest value in the container.
+    pub fn min(self) -> Option<u16> {
+        self.container.min()
+    }
+
+    /// Finds the largest value in the container.
+    pub fn max(self) -> Option<u16> {
+        self.container.max()
+    }
+
+    /// Gets an iterator that visits the values in the container in ascending
+    /// order.
+    pub fn iter(self) -> ContainerViewIter<'a> {
+        ContainerViewIter(self.container.iter())
+    }
+}
+
+impl<'a> IntoIterator for ContainerView<'a> {
+    type Item = u16;
+    type IntoIter = ContainerViewIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the values of a container view.
+pub struct ContainerViewIter<'a>(containers::Iter<'a>);
+
+impl Iterator for ContainerViewIter<'_> {
+    type Item = u16;
+
+    fn next(&mut self) -> Option<u16> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
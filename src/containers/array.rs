@@ -1,13 +1,39 @@
-use super::bitmap::Bitmap;
-use std::{iter::FromIterator, mem};
+#[cfg(feature = "simd")]
+use super::simd;
+use super::{bitmap::Bitmap, run::Run, Operation};
+use alloc::{vec, vec::Vec};
+use core::{
+    cmp::Ordering,
+    iter::FromIterator,
+    ops::{Deref, RangeInclusive},
+};
+
+/// Number of values stored inline, before spilling on the heap.
+const INLINE_CAPACITY: usize = 8;
 
 /// A sorted array of packed 16-bit integers.
-pub(crate) struct Array(Vec<u16>);
+pub(crate) struct Array(Values);
 
 impl Array {
     /// Initializes a new array with the given value.
     pub(super) fn new(value: u16) -> Self {
-        Self(vec![value])
+        let mut values = [0; INLINE_CAPACITY];
+        values[0] = value;
+
+        Self(Values::Inline(1, values))
+    }
+
+    /// Initializes a new array from values sorted in ascending order.
+    pub(super) fn from_vec(values: Vec<u16>) -> Self {
+        Self(values.into())
+    }
+
+    /// Consumes the array, returning its heap buffer if it has one.
+    pub(super) fn into_vec(self) -> Option<Vec<u16>> {
+        match self.0 {
+            Values::Inline(..) => None,
+            Values::Heap(values) => Some(values),
+        }
     }
 
     /// Adds a value to the array.
@@ -46,14 +72,155 @@ impl Array {
         self.0.last().copied()
     }
 
+    /// Returns the `n`-th smallest value in the array, if any.
+    pub(super) fn select(&self, n: usize) -> Option<u16> {
+        self.0.get(n).copied()
+    }
+
+    /// Returns the number of values in the array lower than or equal to
+    /// `value`.
+    pub(super) fn rank(&self, value: u16) -> usize {
+        self.0.partition_point(|&current| current <= value)
+    }
+
+    /// Returns the number of values in the array.
+    pub(super) fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Merges two arrays according to the set operation.
+    pub(super) fn merge(&self, other: &Self, op: Operation) -> Self {
+        #[cfg(feature = "simd")]
+        if let Operation::And = op {
+            return Self(simd::intersect(&self.0, &other.0).into());
+        }
+
+        Self(merge(&self.0, &other.0, op).into())
+    }
+
+    /// Counts the values shared with another array, without building their
+    /// intersection.
+    pub(super) fn and_len(&self, other: &Self) -> usize {
+        let (mut i, mut j, mut count) = (0, 0, 0);
+
+        while let (Some(&lhs), Some(&rhs)) = (self.0.get(i), other.0.get(j)) {
+            match lhs.cmp(&rhs) {
+                Ordering::Less => i += 1,
+                Ordering::Equal => {
+                    count += 1;
+                    i += 1;
+                    j += 1;
+                },
+                Ordering::Greater => j += 1,
+            }
+        }
+
+        count
+    }
+
+    /// Appends at most `limit` values shared with another array to `values`,
+    /// in ascending order.
+    pub(super) fn and_limited(
+        &self,
+        other: &Self,
+        limit: usize,
+        values: &mut Vec<u16>,
+    ) {
+        let (mut i, mut j, mut count) = (0, 0, 0);
+
+        while let (Some(&lhs), Some(&rhs)) = (self.0.get(i), other.0.get(j)) {
+            if count == limit {
+                break;
+            }
+            match lhs.cmp(&rhs) {
+                Ordering::Less => i += 1,
+                Ordering::Equal => {
+                    values.push(lhs);
+                    count += 1;
+                    i += 1;
+                    j += 1;
+                },
+                Ordering::Greater => j += 1,
+            }
+        }
+    }
+
+    /// Adds a slice of values, sorted in ascending order, to the array.
+    ///
+    /// Returns the number of values that were not already present.
+    pub(super) fn insert_sorted_slice(&mut self, values: &[u16]) -> usize {
+        debug_assert!(
+            values.windows(2).all(|pair| pair[0] < pair[1]),
+            "unsorted values"
+        );
+        let old_len = self.len();
+
+        self.0 = merge(&self.0, values, Operation::Or).into();
+
+        self.len() - old_len
+    }
+
+    /// Returns a new array containing only the values matching the predicate.
+    pub(super) fn filter(&self, predicate: impl Fn(u16) -> bool) -> Self {
+        self.iter().filter(|&value| predicate(value)).collect()
+    }
+
+    /// Counts the number of runs of consecutive values in the array.
+    pub(super) fn count_runs(&self) -> usize {
+        let breaks = self.0.windows(2).filter(|pair| pair[0] + 1 != pair[1]);
+
+        usize::from(!self.0.is_empty()) + breaks.count()
+    }
+
     /// Gets an iterator that visits the values in the array in ascending order.
     pub(super) fn iter(&self) -> Iter<'_> {
-        Iter(self.0.iter().copied())
+        Iter(self.0.iter())
+    }
+
+    /// Gets an iterator that visits the runs of consecutive values in the
+    /// array in ascending order.
+    pub(super) fn ranges(&self) -> Ranges<'_> {
+        Ranges(&self.0)
+    }
+
+    /// Calls `f` on each value of the array within `range` in ascending
+    /// order, stopping at the first error.
+    pub(super) fn try_for_each_in<E>(
+        &self,
+        range: RangeInclusive<u16>,
+        f: impl FnMut(u16) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let start = self.0.partition_point(|&value| value < *range.start());
+        let end = self.0.partition_point(|&value| value <= *range.end());
+
+        self.0[start..end].iter().copied().try_for_each(f)
     }
 
     /// Returns the approximate in-memory size of the array, in bytes.
     pub(super) fn mem_size(&self) -> usize {
-        mem::size_of_val(self) + self.0.len() * mem::size_of::<u16>()
+        let heap_size = match self.0 {
+            Values::Inline(..) => 0,
+            Values::Heap(ref values) => values.len() * size_of::<u16>(),
+        };
+
+        size_of_val(self) + heap_size
+    }
+
+    /// Returns by how many bytes the array would grow if a value was added.
+    pub(super) fn insert_mem_delta(&self) -> usize {
+        match self.0 {
+            Values::Inline(len, _) if usize::from(len) < INLINE_CAPACITY => 0,
+            // The values spill on the heap.
+            Values::Inline(len, _) => (usize::from(len) + 1) * size_of::<u16>(),
+            Values::Heap(_) => size_of::<u16>(),
+        }
+    }
+
+    /// Releases the unused capacity of the heap buffer, if any.
+    pub(super) fn shrink_to_fit(&mut self) {
+        if let Values::Heap(ref mut values) = self.0 {
+            values.shrink_to_fit();
+        }
     }
 
     #[cfg(test)]
@@ -64,7 +231,7 @@ impl Array {
 
 impl FromIterator<u16> for Array {
     fn from_iter<I: IntoIterator<Item = u16>>(iter: I) -> Self {
-        Self(Vec::from_iter(iter))
+        Self(Vec::from_iter(iter).into())
     }
 }
 
@@ -74,13 +241,216 @@ impl From<&Bitmap> for Array {
     }
 }
 
-pub(crate) struct Iter<'a>(std::iter::Copied<std::slice::Iter<'a, u16>>);
+impl From<&Run> for Array {
+    fn from(run: &Run) -> Self {
+        run.iter().collect()
+    }
+}
+
+/// Merges two sorted slices in a single pass, keeping values according to
+/// the set operation.
+fn merge(lhs: &[u16], rhs: &[u16], op: Operation) -> Vec<u16> {
+    let mut values = Vec::new();
+    let (mut i, mut j) = (0, 0);
+
+    loop {
+        let (value, in_lhs, in_rhs) =
+            match (lhs.get(i).copied(), rhs.get(j).copied()) {
+                (Some(lhs), Some(rhs)) => {
+                    match lhs.cmp(&rhs) {
+                        Ordering::Less => (lhs, true, false),
+                        Ordering::Equal => (lhs, true, true),
+                        Ordering::Greater => (rhs, false, true),
+                    }
+                },
+                (Some(lhs), None) => (lhs, true, false),
+                (None, Some(rhs)) => (rhs, false, true),
+                (None, None) => break,
+            };
+        i += usize::from(in_lhs);
+        j += usize::from(in_rhs);
+
+        if op.keep(in_lhs, in_rhs) {
+            values.push(value);
+        }
+    }
+
+    values
+}
+
+impl Clone for Array {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        self.0.clone_from(&source.0);
+    }
+}
+
+/// Storage for the values of an array, inline for the small ones.
+enum Values {
+    /// Values stored inline (length, buffer).
+    Inline(u8, [u16; INLINE_CAPACITY]),
+    /// Values stored on the heap.
+    Heap(Vec<u16>),
+}
+
+impl Values {
+    /// Inserts a value at position `index`, shifting the ones after it.
+    fn insert(&mut self, index: usize, value: u16) {
+        match *self {
+            Self::Inline(ref mut len, ref mut values)
+                if usize::from(*len) < INLINE_CAPACITY =>
+            {
+                values.copy_within(index..usize::from(*len), index + 1);
+                values[index] = value;
+                *len += 1;
+            },
+            Self::Inline(..) => {
+                let mut values = Vec::with_capacity(INLINE_CAPACITY * 2);
+                values.extend_from_slice(self);
+                values.insert(index, value);
+                *self = Self::Heap(values);
+            },
+            Self::Heap(ref mut values) => values.insert(index, value),
+        }
+    }
+
+    /// Removes the value at position `index`, shifting the ones after it.
+    fn remove(&mut self, index: usize) {
+        match *self {
+            Self::Inline(ref mut len, ref mut values) => {
+                values.copy_within(index + 1..usize::from(*len), index);
+                *len -= 1;
+            },
+            Self::Heap(ref mut values) => {
+                values.remove(index);
+            },
+        }
+    }
+}
+
+impl Clone for Values {
+    fn clone(&self) -> Self {
+        match *self {
+            Self::Inline(len, values) => Self::Inline(len, values),
+            Self::Heap(ref values) => Self::Heap(values.clone()),
+        }
+    }
+
+    /// Reuses the heap allocation, if any.
+    fn clone_from(&mut self, source: &Self) {
+        match *self {
+            Self::Inline(..) => *self = source.clone(),
+            Self::Heap(ref mut values) => {
+                values.clear();
+                values.extend_from_slice(source);
+            },
+        }
+    }
+}
+
+impl Deref for Values {
+    type Target = [u16];
+
+    fn deref(&self) -> &[u16] {
+        match *self {
+            Self::Inline(len, ref values) => &values[..usize::from(len)],
+            Self::Heap(ref values) => values,
+        }
+    }
+}
+
+impl From<Vec<u16>> for Values {
+    // Length is checked against the inline capacity: no truncation.
+    #[allow(clippy::cast_possible_truncation)]
+    fn from(vec: Vec<u16>) -> Self {
+        if vec.len() > INLINE_CAPACITY {
+            return Self::Heap(vec);
+        }
+        let mut values = [0; INLINE_CAPACITY];
+        values[..vec.len()].copy_from_slice(&vec);
+
+        Self::Inline(vec.len() as u8, values)
+    }
+}
 
-impl<'a> Iterator for Iter<'a> {
+impl IntoIterator for Array {
+    type Item = u16;
+    type IntoIter = vec::IntoIter<u16>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        match self.0 {
+            Values::Inline(len, values) => {
+                let mut values = Vec::from(values);
+                values.truncate(usize::from(len));
+                values.into_iter()
+            },
+            Values::Heap(values) => values.into_iter(),
+        }
+    }
+}
+
+pub(crate) struct Iter<'a>(core::slice::Iter<'a, u16>);
+
+/// Iterator over the runs of consecutive values in an array.
+pub(crate) struct Ranges<'a>(&'a [u16]);
+
+impl Iterator for Ranges<'_> {
+    type Item = RangeInclusive<u16>;
+
+    fn next(&mut self) -> Option<RangeInclusive<u16>> {
+        let start = *self.0.first()?;
+        let len = 1 + self
+            .0
+            .windows(2)
+            .take_while(|pair| pair[0] + 1 == pair[1])
+            .count();
+        let end = self.0[len - 1];
+        self.0 = &self.0[len..];
+
+        Some(start..=end)
+    }
+}
+
+impl Iter<'_> {
+    /// Skips the values lower than `value`.
+    ///
+    /// Returns the number of skipped values.
+    pub(super) fn advance_to(&mut self, value: u16) -> usize {
+        let values = self.0.as_slice();
+        let skipped = values.partition_point(|&current| current < value);
+        self.0 = values[skipped..].iter();
+
+        skipped
+    }
+
+    /// Decodes the next values into `buf`, combined with `base`.
+    ///
+    /// Returns the number of decoded values.
+    pub(super) fn next_many(&mut self, buf: &mut [u32], base: u32) -> usize {
+        let values = self.0.as_slice();
+        let count = values.len().min(buf.len());
+
+        for (slot, &value) in buf.iter_mut().zip(&values[..count]) {
+            *slot = base | u32::from(value);
+        }
+        self.0 = values[count..].iter();
+
+        count
+    }
+}
+
+impl Iterator for Iter<'_> {
     type Item = u16;
 
     fn next(&mut self) -> Option<u16> {
-        self.0.next()
+        self.0.next().copied()
+    }
+
+    fn nth(&mut self, n: usize) -> Option<u16> {
+        self.0.nth(n).copied()
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
@@ -88,6 +458,12 @@ impl<'a> Iterator for Iter<'a> {
     }
 }
 
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<u16> {
+        self.0.next_back().copied()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -111,29 +487,29 @@ mod tests {
     #[test]
     fn contains() {
         let mut array = Array::new(42);
-        assert_eq!(array.contains(11), false);
+        assert!(!array.contains(11));
 
         array.insert(11);
-        assert_eq!(array.contains(11), true);
+        assert!(array.contains(11));
 
         array.remove(11);
-        assert_eq!(array.contains(11), false);
+        assert!(!array.contains(11));
     }
 
     #[test]
     fn already_exists() {
         let mut array = Array::new(42);
 
-        assert_eq!(array.insert(42), false, "already exists");
-        assert_eq!(array.insert(11), true, "new entry");
+        assert!(!array.insert(42), "already exists");
+        assert!(array.insert(11), "new entry");
     }
 
     #[test]
     fn missing() {
         let mut array = Array::new(42);
 
-        assert_eq!(array.remove(42), true, "found");
-        assert_eq!(array.remove(11), false, "missing entry");
+        assert!(array.remove(42), "found");
+        assert!(!array.remove(11), "missing entry");
     }
 
     #[test]
@@ -162,6 +538,85 @@ mod tests {
         assert_eq!(array.iter().collect::<Vec<_>>(), vec![3u16, 11, 77, 100]);
     }
 
+    #[test]
+    fn merge() {
+        let lhs = [1u16, 3, 5, 7].iter().copied().collect::<Array>();
+        let rhs = [3u16, 4, 5, 6].iter().copied().collect::<Array>();
+
+        let and = lhs.merge(&rhs, Operation::And);
+        assert_eq!(and.iter().collect::<Vec<_>>(), vec![3, 5]);
+
+        let or = lhs.merge(&rhs, Operation::Or);
+        assert_eq!(or.iter().collect::<Vec<_>>(), vec![1, 3, 4, 5, 6, 7]);
+
+        let xor = lhs.merge(&rhs, Operation::Xor);
+        assert_eq!(xor.iter().collect::<Vec<_>>(), vec![1, 4, 6, 7]);
+
+        let and_not = lhs.merge(&rhs, Operation::AndNot);
+        assert_eq!(and_not.iter().collect::<Vec<_>>(), vec![1, 7]);
+    }
+
+    #[test]
+    fn insert_sorted_slice() {
+        let mut array = [1u16, 5, 9].iter().copied().collect::<Array>();
+
+        assert_eq!(array.insert_sorted_slice(&[0, 5, 6, 10]), 3);
+        assert_eq!(array.iter().collect::<Vec<_>>(), vec![0, 1, 5, 6, 9, 10]);
+        assert_eq!(array.insert_sorted_slice(&[]), 0);
+    }
+
+    #[test]
+    fn count_runs() {
+        let mut array = Array::new(42);
+        assert_eq!(array.count_runs(), 1);
+
+        array.insert(43);
+        array.insert(41);
+        assert_eq!(array.count_runs(), 1, "consecutive values");
+
+        array.insert(100);
+        array.insert(0);
+        assert_eq!(array.count_runs(), 3, "isolated values");
+    }
+
+    #[test]
+    fn from_run() {
+        let run = [3u16, 4, 5, 100].iter().copied().collect::<Run>();
+
+        let array = Array::from(&run);
+        assert_eq!(array.iter().collect::<Vec<_>>(), vec![3u16, 4, 5, 100]);
+    }
+
+    #[test]
+    fn inline_values() {
+        let mut array = Array::new(42);
+        for value in [0, 2, 4, 6, 8, 10, 12, 14] {
+            array.insert(value);
+        }
+        assert!(matches!(array.0, Values::Heap(_)), "spilled on the heap");
+        assert!(array.is_sorted());
+        assert_eq!(array.len(), INLINE_CAPACITY + 1);
+
+        let array = (0..3).collect::<Array>();
+        assert!(matches!(array.0, Values::Inline(3, _)), "stored inline");
+        assert_eq!(array.iter().collect::<Vec<_>>(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn next_many() {
+        let array = (0..10).collect::<Array>();
+        let mut iter = array.iter();
+        let mut buf = [0; 4];
+
+        assert_eq!(iter.next_many(&mut buf, 0x1_0000), 4);
+        assert_eq!(buf, [0x1_0000, 0x1_0001, 0x1_0002, 0x1_0003]);
+        assert_eq!(iter.next(), Some(4));
+        assert_eq!(iter.next_many(&mut buf, 0), 4);
+        assert_eq!(iter.next_many(&mut buf, 0), 1);
+        assert_eq!(buf[0], 9);
+        assert_eq!(iter.next_many(&mut buf, 0), 0);
+    }
+
     #[test]
     fn mem_size() {
         let mut array = Array::new(42);
@@ -1,18 +1,64 @@
-use super::array::Array;
-use std::{iter::FromIterator, mem};
+#[cfg(feature = "simd")]
+use super::simd::{self, popcount};
+use super::{array::Array, run::Run, Operation, Pool};
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    iter::{self, FromIterator},
+    mem,
+    ops::{Deref, RangeInclusive},
+};
 
 /// Bitmap size, in 64-bit words.
-const BITMAP_WORD_COUNT: usize = 1024;
+pub(crate) const BITMAP_WORD_COUNT: usize = 1024;
 
 /// 2¹⁶-bit bitmap.
 pub(crate) struct Bitmap(Box<[u64; BITMAP_WORD_COUNT]>);
 
+impl Clone for Bitmap {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        self.0.clone_from(&source.0);
+    }
+}
+
 impl Bitmap {
     /// Initializes a new empty bitmap.
     pub(super) fn new() -> Self {
         Self(Box::new([0; BITMAP_WORD_COUNT]))
     }
 
+    /// Initializes a new empty bitmap, reusing the words of a previous one.
+    pub(super) fn from_recycled(
+        mut words: Box<[u64; BITMAP_WORD_COUNT]>,
+    ) -> Self {
+        words.fill(0);
+        Self(words)
+    }
+
+    /// Initializes a bitmap from its words, the bit `i` of the word `j`
+    /// standing for the value `j * 64 + i`.
+    pub(super) fn from_words(words: Box<[u64; BITMAP_WORD_COUNT]>) -> Self {
+        Self(words)
+    }
+
+    /// Consumes the bitmap, returning its words.
+    pub(super) fn into_words(self) -> Box<[u64; BITMAP_WORD_COUNT]> {
+        self.0
+    }
+
+    /// Returns the words of the bitmap.
+    pub(super) fn words(&self) -> &[u64; BITMAP_WORD_COUNT] {
+        &self.0
+    }
+
+    /// Initializes a new bitmap with every bit set.
+    pub(super) fn full() -> Self {
+        Self(Box::new([u64::MAX; BITMAP_WORD_COUNT]))
+    }
+
     /// Adds a value to the bitmap.
     ///
     /// If the bitmap did not have this value present, true is returned.
@@ -74,15 +120,212 @@ impl Bitmap {
             })
     }
 
+    /// Returns the `n`-th smallest value in the bitmap, if any.
+    // Max index is BITMAP_WORD_COUNT/max trailing zeros is 64: no truncation.
+    #[allow(clippy::cast_possible_truncation)]
+    pub(super) fn select(&self, mut n: usize) -> Option<u16> {
+        for (index, &word) in self.0.iter().enumerate() {
+            let count = word.count_ones() as usize;
+            if n < count {
+                // Clear the lowest set bits until the selected one.
+                let mut word = word;
+                for _ in 0..n {
+                    word &= word - 1;
+                }
+                let tail = (index as u16) * 64;
+                let head = word.trailing_zeros() as u16;
+
+                return Some(tail + head);
+            }
+            n -= count;
+        }
+        None
+    }
+
+    /// Returns the number of values in the bitmap lower than or equal to
+    /// `value`.
+    pub(super) fn rank(&self, value: u16) -> usize {
+        let index = usize::from(value / 64);
+        let last = self.0[index] & (u64::MAX >> (63 - value % 64));
+
+        self.0[..index]
+            .iter()
+            .fold(last.count_ones() as usize, |acc, word| {
+                acc + word.count_ones() as usize
+            })
+    }
+
+    /// Combines two bitmaps word by word.
+    ///
+    /// Returns the resulting bitmap and its cardinality.
+    pub(super) fn combine(
+        &self,
+        other: &Self,
+        op: Operation,
+        pool: &mut Pool,
+    ) -> (Self, usize) {
+        // Every word is overwritten: no need to clear a recycled bitmap.
+        let mut bitmap = pool.take_words().map_or_else(Self::new, Self);
+
+        #[cfg(feature = "simd")]
+        let cardinality = simd::combine(
+            &self.0[..],
+            &other.0[..],
+            &mut bitmap.0[..],
+            |lhs, rhs| op.apply(lhs, rhs),
+        );
+
+        #[cfg(not(feature = "simd"))]
+        let cardinality = {
+            let mut cardinality = 0;
+            for (word, (&lhs, &rhs)) in
+                bitmap.0.iter_mut().zip(self.0.iter().zip(other.0.iter()))
+            {
+                *word = op.apply(lhs, rhs);
+                cardinality += word.count_ones() as usize;
+            }
+            cardinality
+        };
+
+        (bitmap, cardinality)
+    }
+
+    /// Counts the values shared with another bitmap, without building their
+    /// intersection.
+    pub(super) fn and_len(&self, other: &Self) -> usize {
+        self.0
+            .iter()
+            .zip(other.0.iter())
+            .fold(0, |acc, (&lhs, &rhs)| {
+                acc + (lhs & rhs).count_ones() as usize
+            })
+    }
+
+    /// Appends at most `limit` values shared with another bitmap to `values`,
+    /// in ascending order.
+    pub(super) fn and_limited(
+        &self,
+        other: &Self,
+        mut limit: usize,
+        values: &mut Vec<u16>,
+    ) {
+        for (index, (&lhs, &rhs)) in
+            self.0.iter().zip(other.0.iter()).enumerate()
+        {
+            let mut word = lhs & rhs;
+            while word != 0 {
+                if limit == 0 {
+                    return;
+                }
+                // Max index is BITMAP_WORD_COUNT/max trailing zeros is 64: no
+                // truncation.
+                #[allow(clippy::cast_possible_truncation)]
+                values.push((index as u16) * 64 + word.trailing_zeros() as u16);
+                word &= word - 1;
+                limit -= 1;
+            }
+        }
+    }
+
+    /// Combines the bitmap with the values of an array.
+    ///
+    /// `op` is called with the word holding each value of the array and the
+    /// mask selecting its bit.
+    ///
+    /// Returns the resulting bitmap and its cardinality.
+    pub(super) fn combine_array(
+        &self,
+        array: &Array,
+        op: impl Fn(u64, u64) -> u64,
+        pool: &mut Pool,
+    ) -> (Self, usize) {
+        let mut bitmap = pool.take_words().map_or_else(
+            || self.clone(),
+            |mut words| {
+                words.copy_from_slice(&self.0[..]);
+                Self(words)
+            },
+        );
+        let mut cardinality = self.cardinality();
+
+        for value in array.iter() {
+            let index = Index::from(value);
+            let old_word = bitmap.0[index.word];
+            let new_word = op(old_word, 1 << index.bit);
+
+            cardinality += new_word.count_ones() as usize;
+            cardinality -= old_word.count_ones() as usize;
+            bitmap.0[index.word] = new_word;
+        }
+
+        (bitmap, cardinality)
+    }
+
+    /// Counts the number of values in the bitmap.
+    pub(super) fn cardinality(&self) -> usize {
+        popcount(&self.0[..])
+    }
+
+    /// Counts the number of runs of consecutive values in the bitmap.
+    pub(super) fn count_runs(&self) -> usize {
+        // A run starts on every set bit whose predecessor is unset.
+        let (count, _) = self.0.iter().fold((0, 0), |(count, carry), word| {
+            let starts = word & !((word << 1) | carry);
+            (count + starts.count_ones() as usize, word >> 63)
+        });
+
+        count
+    }
+
     /// Gets an iterator that visits the values in the bitmap in ascending
     /// order.
-    pub(super) fn iter(&self) -> Iter<'_> {
+    pub(super) fn iter(&self) -> Iter<&[u64; BITMAP_WORD_COUNT]> {
         Iter::new(&self.0)
     }
 
+    /// Gets an iterator that visits the runs of consecutive values in the
+    /// bitmap in ascending order.
+    pub(super) fn ranges(&self) -> Ranges<'_> {
+        Ranges {
+            bitmap: &self.0,
+            position: 0,
+        }
+    }
+
+    /// Calls `f` on each value of the bitmap within `range` in ascending
+    /// order, stopping at the first error.
+    // Max index is BITMAP_WORD_COUNT/max trailing zeros is 64: no truncation.
+    #[allow(clippy::cast_possible_truncation)]
+    pub(super) fn try_for_each_in<E>(
+        &self,
+        range: RangeInclusive<u16>,
+        mut f: impl FnMut(u16) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let start = usize::from(*range.start());
+        let end = usize::from(*range.end());
+
+        for index in start / 64..=end / 64 {
+            let mut word = self.0[index];
+            // Mask out the bits of the boundary words outside of the range.
+            if index == start / 64 {
+                word &= u64::MAX << (start % 64);
+            }
+            if index == end / 64 {
+                word &= u64::MAX >> (63 - end % 64);
+            }
+            let base = (index as u16) * 64;
+            while word != 0 {
+                f(base + word.trailing_zeros() as u16)?;
+                word &= word - 1;
+            }
+        }
+
+        Ok(())
+    }
+
     /// Returns the approximate in-memory size of the bitmap, in bytes.
     pub(super) fn mem_size(&self) -> usize {
-        mem::size_of_val(self) + mem::size_of::<[u64; BITMAP_WORD_COUNT]>()
+        size_of_val(self) + size_of::<[u64; BITMAP_WORD_COUNT]>()
     }
 
     /// Tests the bit at `index`.
@@ -119,6 +362,20 @@ impl From<&Array> for Bitmap {
     }
 }
 
+impl From<&Run> for Bitmap {
+    fn from(run: &Run) -> Self {
+        run.iter().collect()
+    }
+}
+
+/// Counts the number of set bits in the words.
+#[cfg(not(feature = "simd"))]
+fn popcount(words: &[u64]) -> usize {
+    words
+        .iter()
+        .fold(0, |acc, word| acc + word.count_ones() as usize)
+}
+
 /// Bitmap index
 struct Index {
     /// Selected word in the bitmap.
@@ -136,38 +393,181 @@ impl From<u16> for Index {
     }
 }
 
-pub(crate) struct Iter<'a> {
+/// Iterator over the runs of consecutive values in a bitmap.
+///
+/// Runs are detected by scanning whole words for their first set, then unset,
+/// bit.
+pub(crate) struct Ranges<'a> {
     bitmap: &'a [u64; BITMAP_WORD_COUNT],
+    /// Index of the next bit to look at.
+    position: usize,
+}
+
+impl Ranges<'_> {
+    /// Returns the index of the first bit from the current position, set in
+    /// the words transformed by `map` (if any).
+    fn first_bit(&self, map: impl Fn(u64) -> u64) -> Option<usize> {
+        let index = self.position / 64;
+        let first =
+            map(*self.bitmap.get(index)?) & (!0 << (self.position % 64));
+
+        iter::once(first)
+            .chain(self.bitmap[index + 1..].iter().map(|&word| map(word)))
+            .position(|word| word != 0)
+            .map(|offset| {
+                let word = index + offset;
+                let bit = if offset == 0 {
+                    first
+                } else {
+                    map(self.bitmap[word])
+                }
+                .trailing_zeros();
+                word * 64 + bit as usize
+            })
+    }
+}
+
+impl Iterator for Ranges<'_> {
+    type Item = RangeInclusive<u16>;
+
+    // Bit indexes are bounded by 2¹⁶: no truncation.
+    #[allow(clippy::cast_possible_truncation)]
+    fn next(&mut self) -> Option<RangeInclusive<u16>> {
+        let Some(start) = self.first_bit(|word| word) else {
+            self.position = BITMAP_WORD_COUNT * 64;
+            return None;
+        };
+        self.position = start;
+        let end = self
+            .first_bit(|word| !word)
+            .unwrap_or(BITMAP_WORD_COUNT * 64);
+        self.position = end;
+
+        Some(start as u16..=(end - 1) as u16)
+    }
+}
+
+impl IntoIterator for Bitmap {
+    type Item = u16;
+    type IntoIter = Iter<Box<[u64; BITMAP_WORD_COUNT]>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Iter::new(self.0)
+    }
+}
+
+/// Iterator over the values of a bitmap, borrowing or owning its words.
+pub(crate) struct Iter<W> {
+    bitmap: W,
     size: usize,
+    /// Index and remaining bits of the word visited from the front.
     index: usize,
     word: u64,
+    /// Index and remaining bits of the word visited from the back, unless
+    /// both ends are on the same word (only tracked by the front then).
+    back_index: usize,
+    back_word: u64,
 }
 
-impl<'a> Iter<'a> {
-    fn new(bitmap: &'a [u64; BITMAP_WORD_COUNT]) -> Self {
+impl<W: Deref<Target = [u64; BITMAP_WORD_COUNT]>> Iter<W> {
+    fn new(bitmap: W) -> Self {
         Self {
-            bitmap,
-            size: bitmap
-                .iter()
-                .fold(0_usize, |acc, word| acc + (word.count_ones() as usize)),
+            size: popcount(&bitmap[..]),
             index: 0,
             word: bitmap[0],
+            back_index: BITMAP_WORD_COUNT - 1,
+            back_word: bitmap[BITMAP_WORD_COUNT - 1],
+            bitmap,
         }
     }
+
+    /// Moves the front to the next word, up to the one visited from the back.
+    ///
+    /// Returns false if both ends are already on the same word.
+    fn next_word(&mut self) -> bool {
+        if self.index == self.back_index {
+            return false;
+        }
+        self.index += 1;
+        self.word = if self.index == self.back_index {
+            mem::take(&mut self.back_word)
+        } else {
+            self.bitmap[self.index]
+        };
+        true
+    }
+
+    /// Skips the values lower than `value`.
+    ///
+    /// Returns the number of skipped values.
+    pub(super) fn advance_to(&mut self, value: u16) -> usize {
+        let index = Index::from(value);
+        if index.word < self.index {
+            return 0;
+        }
+
+        let mut skipped = 0;
+        if index.word > self.index && self.index < self.back_index {
+            // Skip whole words, up to the one visited from the back.
+            let target = index.word.min(self.back_index);
+            skipped += self.word.count_ones() as usize
+                + popcount(&self.bitmap[self.index + 1..target]);
+            self.index = target - 1;
+            self.word = 0;
+            self.next_word();
+        }
+        let mask = if index.word > self.index {
+            0
+        } else {
+            u64::MAX << index.bit
+        };
+        let word = self.word & mask;
+        skipped += (self.word ^ word).count_ones() as usize;
+        self.word = word;
+        self.size -= skipped;
+
+        skipped
+    }
+
+    /// Decodes the next values into `buf`, combined with `base`.
+    ///
+    /// Returns the number of decoded values.
+    // Max index is BITMAP_WORD_COUNT: no truncation.
+    #[allow(clippy::cast_possible_truncation)]
+    pub(super) fn next_many(&mut self, buf: &mut [u32], base: u32) -> usize {
+        let mut count = 0;
+
+        while count < buf.len() {
+            while self.word == 0 {
+                if !self.next_word() {
+                    self.size -= count;
+                    return count;
+                }
+            }
+            // Drain the current word.
+            let word_base = base | ((self.index as u32) * 64);
+            while self.word != 0 && count < buf.len() {
+                buf[count] = word_base + self.word.trailing_zeros();
+                self.word &= self.word - 1;
+                count += 1;
+            }
+        }
+        self.size -= count;
+
+        count
+    }
 }
 
-impl<'a> Iterator for Iter<'a> {
+impl<W: Deref<Target = [u64; BITMAP_WORD_COUNT]>> Iterator for Iter<W> {
     type Item = u16;
 
     // Max index is BITMAP_WORD_COUNT/max trailing zeros is 64: no truncation.
     #[allow(clippy::cast_possible_truncation)]
     fn next(&mut self) -> Option<u16> {
         while self.word == 0 {
-            self.index += 1;
-            if self.index == self.bitmap.len() {
+            if !self.next_word() {
                 return None;
             }
-            self.word = self.bitmap[self.index];
         }
         let value = (self.index as u32) * 64 + self.word.trailing_zeros();
         self.word &= self.word - 1;
@@ -176,11 +576,60 @@ impl<'a> Iterator for Iter<'a> {
         Some(value as u16)
     }
 
+    /// Skips whole words, rather than their values one by one.
+    fn nth(&mut self, mut n: usize) -> Option<u16> {
+        loop {
+            let count = self.word.count_ones() as usize;
+            if n < count {
+                for _ in 0..n {
+                    self.word &= self.word - 1;
+                }
+                self.size -= n;
+                return self.next();
+            }
+            n -= count;
+            self.size -= count;
+            self.word = 0;
+            if !self.next_word() {
+                return None;
+            }
+        }
+    }
+
     fn size_hint(&self) -> (usize, Option<usize>) {
         (self.size, Some(self.size))
     }
 }
 
+impl<W: Deref<Target = [u64; BITMAP_WORD_COUNT]>> DoubleEndedIterator
+    for Iter<W>
+{
+    // Max index is BITMAP_WORD_COUNT/max bit position is 63: no truncation.
+    #[allow(clippy::cast_possible_truncation)]
+    fn next_back(&mut self) -> Option<u16> {
+        // Move the back to the previous non-empty word, up to the front one.
+        while self.back_word == 0 && self.back_index > self.index {
+            self.back_index -= 1;
+            if self.back_index > self.index {
+                self.back_word = self.bitmap[self.back_index];
+            }
+        }
+        let word = if self.back_index == self.index {
+            &mut self.word
+        } else {
+            &mut self.back_word
+        };
+        if *word == 0 {
+            return None;
+        }
+        let bit = 63 - word.leading_zeros();
+        *word &= !(1 << bit);
+        self.size -= 1;
+
+        Some(((self.back_index as u32) * 64 + bit) as u16)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -246,21 +695,21 @@ mod tests {
     #[test]
     fn contains() {
         let mut bitmap = Bitmap::new();
-        assert_eq!(bitmap.contains(42), false);
+        assert!(!bitmap.contains(42));
 
         bitmap.insert(42);
-        assert_eq!(bitmap.contains(42), true);
+        assert!(bitmap.contains(42));
 
         bitmap.remove(42);
-        assert_eq!(bitmap.contains(42), false);
+        assert!(!bitmap.contains(42));
     }
 
     #[test]
     fn already_exists() {
         let mut bitmap = Bitmap::new();
 
-        assert_eq!(bitmap.insert(42), true, "new entry");
-        assert_eq!(bitmap.insert(42), false, "already exists");
+        assert!(bitmap.insert(42), "new entry");
+        assert!(!bitmap.insert(42), "already exists");
     }
 
     #[test]
@@ -269,8 +718,8 @@ mod tests {
 
         bitmap.insert(11);
 
-        assert_eq!(bitmap.remove(11), true, "found");
-        assert_eq!(bitmap.remove(11), false, "missing entry");
+        assert!(bitmap.remove(11), "found");
+        assert!(!bitmap.remove(11), "missing entry");
     }
 
     #[test]
@@ -284,6 +733,88 @@ mod tests {
         assert_eq!(bitmap.iter().collect::<Vec<_>>(), vec![3u16, 11, 77, 100]);
     }
 
+    #[test]
+    fn combine() {
+        let lhs = [1u16, 3, 5, 7].iter().copied().collect::<Bitmap>();
+        let rhs = [3u16, 4, 5, 6].iter().copied().collect::<Bitmap>();
+
+        let (and, cardinality) =
+            lhs.combine(&rhs, Operation::And, &mut Pool::default());
+        assert_eq!(and.iter().collect::<Vec<_>>(), vec![3, 5]);
+        assert_eq!(cardinality, 2);
+
+        let (or, cardinality) =
+            lhs.combine(&rhs, Operation::Or, &mut Pool::default());
+        assert_eq!(or.iter().collect::<Vec<_>>(), vec![1, 3, 4, 5, 6, 7]);
+        assert_eq!(cardinality, 6);
+    }
+
+    #[test]
+    fn combine_array() {
+        let bitmap = [1u16, 3, 5, 7].iter().copied().collect::<Bitmap>();
+        let array = [3u16, 4, 5, 6].iter().copied().collect::<Array>();
+
+        let (xor, cardinality) = bitmap.combine_array(
+            &array,
+            |word, mask| word ^ mask,
+            &mut Pool::default(),
+        );
+        assert_eq!(xor.iter().collect::<Vec<_>>(), vec![1, 4, 6, 7]);
+        assert_eq!(cardinality, 4);
+
+        let (and_not, cardinality) = bitmap.combine_array(
+            &array,
+            |word, mask| word & !mask,
+            &mut Pool::default(),
+        );
+        assert_eq!(and_not.iter().collect::<Vec<_>>(), vec![1, 7]);
+        assert_eq!(cardinality, 2);
+    }
+
+    #[test]
+    fn next_many() {
+        let input = (0..1_000).map(|value| value * 7).collect::<Vec<u16>>();
+        let bitmap = input.iter().copied().collect::<Bitmap>();
+        let mut iter = bitmap.iter();
+        let mut buf = [0; 300];
+        let mut values = Vec::new();
+
+        loop {
+            let count = iter.next_many(&mut buf, 0);
+            if count == 0 {
+                break;
+            }
+            values.extend_from_slice(&buf[..count]);
+            assert_eq!(iter.size_hint().0, input.len() - values.len());
+        }
+        let expected = input.iter().copied().map(u32::from).collect::<Vec<_>>();
+        assert_eq!(values, expected);
+    }
+
+    #[test]
+    fn count_runs() {
+        let mut bitmap = Bitmap::new();
+        assert_eq!(bitmap.count_runs(), 0);
+
+        // Runs crossing word boundaries are counted once.
+        for value in 60..70 {
+            bitmap.insert(value);
+        }
+        assert_eq!(bitmap.count_runs(), 1);
+
+        bitmap.insert(0);
+        bitmap.insert(u16::MAX);
+        assert_eq!(bitmap.count_runs(), 3);
+    }
+
+    #[test]
+    fn from_run() {
+        let run = [3u16, 4, 5, 100].iter().copied().collect::<Run>();
+
+        let bitmap = Bitmap::from(&run);
+        assert_eq!(bitmap.iter().collect::<Vec<_>>(), vec![3u16, 4, 5, 100]);
+    }
+
     #[test]
     fn mem_size() {
         let mut bitmap = Bitmap::new();
@@ -0,0 +1,246 @@
+//! Read-only containers, packed into a shared buffer of 16-bit words.
+//!
+//! Each container kind is laid out as follows:
+//! - array: the sorted values.
+//! - bitmap: the 2¹⁶ bits, as 4096 little-endian 16-bit words.
+//! - run: pairs of start and length minus one.
+//! - full: nothing.
+
+use super::Container;
+use crate::ContainerKind;
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+
+/// Bitmap size, in 16-bit words.
+const BITMAP_WORD_COUNT: usize = 4096;
+
+impl Container {
+    /// Appends the frozen representation of the container to `data`.
+    pub(crate) fn freeze_into(&self, data: &mut Vec<u16>) {
+        match *self {
+            Container::Array(ref array) => data.extend(array.iter()),
+            Container::Bitmap(ref bitmap) => {
+                for &word in bitmap.words() {
+                    // Truncation is intended: splits the word in four.
+                    #[allow(clippy::cast_possible_truncation)]
+                    data.extend((0..4).map(|i| (word >> (16 * i)) as u16));
+                }
+            },
+            Container::Run(ref run) => {
+                for (start, length) in run.intervals() {
+                    data.extend([start, length]);
+                }
+            },
+            Container::Full => {},
+        }
+    }
+}
+
+/// Read-only view over a frozen container.
+#[derive(Clone, Copy)]
+pub(crate) enum View<'a> {
+    /// Sorted values.
+    Array(&'a [u16]),
+    /// 2¹⁶-bit bitmap.
+    Bitmap(&'a [u16]),
+    /// Runs, as pairs of start and length minus one.
+    Run(&'a [[u16; 2]]),
+    /// Every possible value.
+    Full,
+}
+
+impl<'a> View<'a> {
+    /// Initializes a view over the frozen representation of a container.
+    pub(crate) fn new(kind: ContainerKind, data: &'a [u16]) -> Self {
+        match kind {
+            ContainerKind::Array => Self::Array(data),
+            ContainerKind::Bitmap => {
+                debug_assert_eq!(data.len(), BITMAP_WORD_COUNT);
+                Self::Bitmap(data)
+            },
+            ContainerKind::Run => {
+                let (runs, rest) = data.as_chunks();
+                debug_assert!(rest.is_empty(), "truncated run");
+                Self::Run(runs)
+            },
+            ContainerKind::Full => Self::Full,
+        }
+    }
+
+    /// Returns true if the container contains the value.
+    pub(crate) fn contains(self, value: u16) -> bool {
+        match self {
+            Self::Array(values) => values.binary_search(&value).is_ok(),
+            Self::Bitmap(words) => {
+                words[usize::from(value >> 4)] & (1 << (value & 15)) != 0
+            },
+            Self::Run(runs) => {
+                // Index of the first run starting after the value.
+                let index = runs.partition_point(|run| run[0] <= value);
+                index.checked_sub(1).is_some_and(|index| {
+                    value - runs[index][0] <= runs[index][1]
+                })
+            },
+            Self::Full => true,
+        }
+    }
+
+    /// Finds the smallest value in the container.
+    pub(crate) fn min(self) -> Option<u16> {
+        self.iter().next()
+    }
+
+    /// Finds the largest value in the container.
+    pub(crate) fn max(self) -> Option<u16> {
+        match self {
+            Self::Array(values) => values.last().copied(),
+            Self::Bitmap(words) => {
+                words
+                    .iter()
+                    .enumerate()
+                    .rev()
+                    .find(|&(_, &word)| word != 0)
+                    .map(|(index, word)| {
+                        bit_value(index, 15 - word.leading_zeros())
+                    })
+            },
+            Self::Run(runs) => runs.last().map(|run| run[0] + run[1]),
+            Self::Full => Some(u16::MAX),
+        }
+    }
+
+    /// Gets an iterator that visits the values in the container in ascending
+    /// order.
+    pub(crate) fn iter(self) -> ViewIter<'a> {
+        match self {
+            Self::Array(values) => ViewIter::Array(values.iter()),
+            Self::Bitmap(words) => {
+                ViewIter::Bitmap {
+                    words,
+                    index: 0,
+                    word: words.first().copied().unwrap_or_default(),
+                }
+            },
+            Self::Run(runs) => {
+                ViewIter::Run {
+                    runs: runs.iter(),
+                    current: None,
+                }
+            },
+            Self::Full => ViewIter::Full(u16::MIN..=u16::MAX),
+        }
+    }
+}
+
+/// Returns the value of the `bit`-th bit in the `index`-th 16-bit word.
+fn bit_value(index: usize, bit: u32) -> u16 {
+    // Bitmaps hold 4096 words: index fits on 12 bits.
+    #[allow(clippy::cast_possible_truncation)]
+    let index = index as u16;
+    // Bit is in [0, 16).
+    #[allow(clippy::cast_possible_truncation)]
+    let bit = bit as u16;
+    (index << 4) | bit
+}
+
+/// Frozen container iterator.
+pub(crate) enum ViewIter<'a> {
+    /// Array container iterator.
+    Array(core::slice::Iter<'a, u16>),
+    /// Bitmap container iterator.
+    Bitmap {
+        /// Bitmap words.
+        words: &'a [u16],
+        /// Index of the current word.
+        index: usize,
+        /// Remaining bits of the current word.
+        word: u16,
+    },
+    /// Run container iterator.
+    Run {
+        /// Remaining runs.
+        runs: core::slice::Iter<'a, [u16; 2]>,
+        /// Values left in the current run.
+        current: Option<RangeInclusive<u16>>,
+    },
+    /// Full container iterator.
+    Full(RangeInclusive<u16>),
+}
+
+impl Iterator for ViewIter<'_> {
+    type Item = u16;
+
+    fn next(&mut self) -> Option<u16> {
+        match *self {
+            Self::Array(ref mut values) => values.next().copied(),
+            Self::Bitmap {
+                words,
+                ref mut index,
+                ref mut word,
+            } => {
+                while *word == 0 {
+                    *index += 1;
+                    *word = *words.get(*index)?;
+                }
+                let bit = word.trailing_zeros();
+                *word &= *word - 1;
+                Some(bit_value(*index, bit))
+            },
+            Self::Run {
+                ref mut runs,
+                ref mut current,
+            } => {
+                loop {
+                    if let Some(value) =
+                        current.as_mut().and_then(Iterator::next)
+                    {
+                        return Some(value);
+                    }
+                    let run = runs.next()?;
+                    *current = Some(run[0]..=run[0] + run[1]);
+                }
+            },
+            Self::Full(ref mut range) => range.next(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::containers::{array::Array, bitmap::Bitmap, run::Run};
+
+    #[test]
+    fn round_trip() {
+        let input = [0, 1, 2, 3, 100, 4_095, 4_096, 40_000, 65_534, 65_535];
+        let containers = [
+            Container::Array(input.iter().copied().collect::<Array>()),
+            Container::Bitmap(input.iter().copied().collect::<Bitmap>()),
+            Container::Run(input.iter().copied().collect::<Run>()),
+        ];
+
+        for container in &containers {
+            let mut data = Vec::new();
+            container.freeze_into(&mut data);
+            let view = View::new(container.kind(), &data);
+
+            assert_eq!(view.iter().collect::<Vec<_>>(), input);
+            assert_eq!(view.min(), Some(0));
+            assert_eq!(view.max(), Some(65_535));
+            for value in [0, 3, 4, 99, 100, 4_096, 40_001, 65_535] {
+                assert_eq!(view.contains(value), container.contains(value));
+            }
+        }
+    }
+
+    #[test]
+    fn full() {
+        let mut data = Vec::new();
+        Container::Full.freeze_into(&mut data);
+        assert!(data.is_empty(), "no payload");
+
+        let view = View::new(ContainerKind::Full, &data);
+        assert!(view.contains(42));
+        assert_eq!(view.iter().count(), 65_536);
+    }
+}
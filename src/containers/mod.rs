@@ -1,8 +1,20 @@
 mod array;
 mod bitmap;
+mod frozen;
+mod pool;
+mod run;
+#[cfg(feature = "simd")]
+mod simd;
 
+use crate::ContainerKind;
+use alloc::{borrow::Cow, boxed::Box, vec, vec::Vec};
 use array::Array;
 use bitmap::Bitmap;
+pub(crate) use bitmap::BITMAP_WORD_COUNT;
+use core::ops::{BitAnd, BitOr, BitXor, Not, RangeInclusive};
+pub(crate) use frozen::{View, ViewIter};
+pub(crate) use pool::Pool;
+use run::Run;
 
 /// Integers container for chunks, bounded to 8 kB at most.
 pub(crate) enum Container {
@@ -10,6 +22,10 @@ pub(crate) enum Container {
     Array(Array),
     /// Bitmap container for dense chunks.
     Bitmap(Bitmap),
+    /// Run container for chunks made of long runs of consecutive values.
+    Run(Run),
+    /// Payload-free container for chunks holding every possible value.
+    Full,
 }
 
 impl Container {
@@ -18,6 +34,28 @@ impl Container {
         Container::Array(Array::new(value))
     }
 
+    /// Initializes a container from bitmap words, the bit `i` of the word `j`
+    /// standing for the value `j * 64 + i`.
+    ///
+    /// Returns the container and its cardinality, or `None` if no bit is set.
+    pub(crate) fn from_words(
+        words: Box<[u64; BITMAP_WORD_COUNT]>,
+    ) -> Option<(Self, usize)> {
+        let bitmap = Bitmap::from_words(words);
+        let cardinality = bitmap.cardinality();
+
+        (cardinality != 0).then_some((Container::Bitmap(bitmap), cardinality))
+    }
+
+    /// Initializes a run container from disjoint, non-adjacent, runs sorted
+    /// in ascending order.
+    pub(crate) fn from_ranges<I>(ranges: I) -> Self
+    where
+        I: IntoIterator<Item = RangeInclusive<u16>>,
+    {
+        Container::Run(Run::from_ranges(ranges))
+    }
+
     /// Adds a value to the container.
     ///
     /// If the container did not have this value present, true is returned.
@@ -26,6 +64,26 @@ impl Container {
         match *self {
             Container::Array(ref mut array) => array.insert(value),
             Container::Bitmap(ref mut bitmap) => bitmap.insert(value),
+            Container::Run(ref mut run) => run.insert(value),
+            Container::Full => false,
+        }
+    }
+
+    /// Adds a slice of values, sorted in ascending order, to the container.
+    ///
+    /// Returns the number of values that were not already present.
+    pub(crate) fn insert_sorted_slice(&mut self, values: &[u16]) -> usize {
+        match *self {
+            Container::Array(ref mut array) => {
+                array.insert_sorted_slice(values)
+            },
+            Container::Bitmap(ref mut bitmap) => {
+                values.iter().filter(|&&value| bitmap.insert(value)).count()
+            },
+            Container::Run(ref mut run) => {
+                values.iter().filter(|&&value| run.insert(value)).count()
+            },
+            Container::Full => 0,
         }
     }
 
@@ -36,6 +94,15 @@ impl Container {
         match *self {
             Container::Array(ref mut array) => array.remove(value),
             Container::Bitmap(ref mut bitmap) => bitmap.remove(value),
+            Container::Run(ref mut run) => run.remove(value),
+            Container::Full => {
+                let mut run = Run::full();
+                run.remove(value);
+                #[cfg(feature = "metrics")]
+                crate::metrics::record_conversion();
+                *self = Container::Run(run);
+                true
+            },
         }
     }
 
@@ -44,6 +111,8 @@ impl Container {
         match *self {
             Container::Array(ref array) => array.contains(value),
             Container::Bitmap(ref bitmap) => bitmap.contains(value),
+            Container::Run(ref run) => run.contains(value),
+            Container::Full => true,
         }
     }
 
@@ -52,6 +121,8 @@ impl Container {
         match *self {
             Container::Array(ref array) => array.min(),
             Container::Bitmap(ref bitmap) => bitmap.min(),
+            Container::Run(ref run) => run.min(),
+            Container::Full => Some(u16::MIN),
         }
     }
 
@@ -60,6 +131,29 @@ impl Container {
         match *self {
             Container::Array(ref array) => array.max(),
             Container::Bitmap(ref bitmap) => bitmap.max(),
+            Container::Run(ref run) => run.max(),
+            Container::Full => Some(u16::MAX),
+        }
+    }
+
+    /// Returns the `n`-th smallest value in the container, if any.
+    pub(crate) fn select(&self, n: usize) -> Option<u16> {
+        match *self {
+            Container::Array(ref array) => array.select(n),
+            Container::Bitmap(ref bitmap) => bitmap.select(n),
+            Container::Run(ref run) => run.select(n),
+            Container::Full => u16::try_from(n).ok(),
+        }
+    }
+
+    /// Returns the number of values in the container lower than or equal to
+    /// `value`.
+    pub(crate) fn rank(&self, value: u16) -> usize {
+        match *self {
+            Container::Array(ref array) => array.rank(value),
+            Container::Bitmap(ref bitmap) => bitmap.rank(value),
+            Container::Run(ref run) => run.rank(value),
+            Container::Full => usize::from(value) + 1,
         }
     }
 
@@ -69,11 +163,331 @@ impl Container {
         Iter::new(self)
     }
 
+    /// Sets the bits of the container's values in `words`, the bit `i` of
+    /// the word `j` standing for the value `j * 64 + i`.
+    pub(crate) fn write_words(&self, words: &mut [u64; BITMAP_WORD_COUNT]) {
+        match *self {
+            Container::Bitmap(ref bitmap) => {
+                words.copy_from_slice(bitmap.words());
+            },
+            Container::Full => words.fill(u64::MAX),
+            Container::Array(_) | Container::Run(_) => {
+                for value in self.iter() {
+                    words[usize::from(value / 64)] |= 1 << (value % 64);
+                }
+            },
+        }
+    }
+
+    /// Gets an iterator that visits the runs of consecutive values in the
+    /// container in ascending order.
+    pub(crate) fn ranges(&self) -> Ranges<'_> {
+        match *self {
+            Container::Array(ref array) => Ranges::Array(array.ranges()),
+            Container::Bitmap(ref bitmap) => Ranges::Bitmap(bitmap.ranges()),
+            Container::Run(ref run) => Ranges::Run(run.ranges()),
+            Container::Full => Ranges::Full(Some(u16::MIN..=u16::MAX)),
+        }
+    }
+
+    /// Returns the kind of the container.
+    pub(crate) fn kind(&self) -> ContainerKind {
+        match *self {
+            Container::Array(_) => ContainerKind::Array,
+            Container::Bitmap(_) => ContainerKind::Bitmap,
+            Container::Run(_) => ContainerKind::Run,
+            Container::Full => ContainerKind::Full,
+        }
+    }
+
+    /// Counts the number of runs of consecutive values in the container.
+    pub(crate) fn count_runs(&self) -> usize {
+        match *self {
+            Container::Array(ref array) => array.count_runs(),
+            Container::Bitmap(ref bitmap) => bitmap.count_runs(),
+            Container::Run(ref run) => run.count_runs(),
+            Container::Full => 1,
+        }
+    }
+
+    /// Calls `f` on each value of the container in ascending order, stopping
+    /// at the first error.
+    pub(crate) fn try_for_each<E>(
+        &self,
+        f: impl FnMut(u16) -> Result<(), E>,
+    ) -> Result<(), E> {
+        self.try_for_each_in(0..=u16::MAX, f)
+    }
+
+    /// Calls `f` on each value of the container within `range` in ascending
+    /// order, stopping at the first error.
+    pub(crate) fn try_for_each_in<E>(
+        &self,
+        mut range: RangeInclusive<u16>,
+        f: impl FnMut(u16) -> Result<(), E>,
+    ) -> Result<(), E> {
+        match *self {
+            Container::Array(ref array) => array.try_for_each_in(range, f),
+            Container::Bitmap(ref bitmap) => bitmap.try_for_each_in(range, f),
+            Container::Run(ref run) => run.try_for_each_in(range, f),
+            Container::Full => range.try_for_each(f),
+        }
+    }
+
     /// Returns the approximate in-memory size of the container, in bytes.
     pub(crate) fn mem_size(&self) -> usize {
         match *self {
             Container::Array(ref array) => array.mem_size(),
             Container::Bitmap(ref bitmap) => bitmap.mem_size(),
+            Container::Run(ref run) => run.mem_size(),
+            Container::Full => 0,
+        }
+    }
+
+    /// Returns by how many bytes the container would grow if `value`, absent
+    /// from it, was added.
+    pub(crate) fn insert_mem_delta(&self, value: u16) -> usize {
+        match *self {
+            Container::Array(ref array) => array.insert_mem_delta(),
+            Container::Run(ref run) => run.insert_mem_delta(value),
+            Container::Bitmap(_) | Container::Full => 0,
+        }
+    }
+
+    /// Releases the unused capacity of the container's buffers.
+    pub(crate) fn shrink_to_fit(&mut self) {
+        match *self {
+            Container::Array(ref mut array) => array.shrink_to_fit(),
+            Container::Run(ref mut run) => run.shrink_to_fit(),
+            Container::Bitmap(_) | Container::Full => (),
+        }
+    }
+
+    /// Computes the intersection of two containers.
+    ///
+    /// Returns the resulting container and its cardinality, or `None` if the
+    /// result is empty.
+    pub(crate) fn and(&self, other: &Self) -> Option<(Self, usize)> {
+        self.combine(other, Operation::And, &mut Pool::default())
+    }
+
+    /// Counts the values shared by two containers, without building their
+    /// intersection.
+    pub(crate) fn and_len(&self, other: &Self) -> usize {
+        match (self.operand(), other.operand()) {
+            (Operand::Array(lhs), Operand::Array(rhs)) => lhs.and_len(rhs),
+            (Operand::Bitmap(lhs), Operand::Bitmap(rhs)) => lhs.and_len(&rhs),
+            (Operand::Array(array), Operand::Bitmap(bitmap))
+            | (Operand::Bitmap(bitmap), Operand::Array(array)) => {
+                array.iter().filter(|&value| bitmap.contains(value)).count()
+            },
+        }
+    }
+
+    /// Appends at most `limit` values shared by two containers to `values`, in
+    /// ascending order, without building their whole intersection.
+    pub(crate) fn and_limited(
+        &self,
+        other: &Self,
+        limit: usize,
+        values: &mut Vec<u16>,
+    ) {
+        match (self.operand(), other.operand()) {
+            (Operand::Array(lhs), Operand::Array(rhs)) => {
+                lhs.and_limited(rhs, limit, values);
+            },
+            (Operand::Bitmap(lhs), Operand::Bitmap(rhs)) => {
+                lhs.and_limited(&rhs, limit, values);
+            },
+            (Operand::Array(array), Operand::Bitmap(bitmap))
+            | (Operand::Bitmap(bitmap), Operand::Array(array)) => {
+                values.extend(
+                    array
+                        .iter()
+                        .filter(|&value| bitmap.contains(value))
+                        .take(limit),
+                );
+            },
+        }
+    }
+
+    /// Computes the union of two containers.
+    ///
+    /// Returns the resulting container and its cardinality, or `None` if the
+    /// result is empty.
+    pub(crate) fn or(&self, other: &Self) -> Option<(Self, usize)> {
+        self.combine(other, Operation::Or, &mut Pool::default())
+    }
+
+    /// Computes the symmetric difference of two containers.
+    ///
+    /// Returns the resulting container and its cardinality, or `None` if the
+    /// result is empty.
+    pub(crate) fn xor(&self, other: &Self) -> Option<(Self, usize)> {
+        self.combine(other, Operation::Xor, &mut Pool::default())
+    }
+
+    /// Computes the difference of two containers.
+    ///
+    /// Returns the resulting container and its cardinality, or `None` if the
+    /// result is empty.
+    pub(crate) fn and_not(&self, other: &Self) -> Option<(Self, usize)> {
+        self.combine(other, Operation::AndNot, &mut Pool::default())
+    }
+
+    /// Applies a set operation between two containers, reusing the recycled
+    /// allocations of `pool` if possible.
+    ///
+    /// The result is either an array or a bitmap container, which may not be
+    /// the best fit for its cardinality.
+    pub(crate) fn combine(
+        &self,
+        other: &Self,
+        op: Operation,
+        pool: &mut Pool,
+    ) -> Option<(Self, usize)> {
+        let (container, cardinality) = match (self.operand(), other.operand()) {
+            (Operand::Array(lhs), Operand::Array(rhs)) => {
+                let array = lhs.merge(rhs, op);
+                let cardinality = array.len();
+                (Container::Array(array), cardinality)
+            },
+            (Operand::Bitmap(lhs), Operand::Bitmap(rhs)) => {
+                let (bitmap, cardinality) = lhs.combine(&rhs, op, pool);
+                (Container::Bitmap(bitmap), cardinality)
+            },
+            // Result is a subset of the array: probe the bitmap.
+            (Operand::Array(lhs), Operand::Bitmap(rhs))
+                if !op.keep(false, true) =>
+            {
+                let array =
+                    lhs.filter(|value| op.keep(true, rhs.contains(value)));
+                let cardinality = array.len();
+                (Container::Array(array), cardinality)
+            },
+            (Operand::Bitmap(lhs), Operand::Array(rhs))
+                if !op.keep(true, false) =>
+            {
+                let array =
+                    rhs.filter(|value| op.keep(lhs.contains(value), true));
+                let cardinality = array.len();
+                (Container::Array(array), cardinality)
+            },
+            // Otherwise, update the bits of the bitmap.
+            (Operand::Array(lhs), Operand::Bitmap(rhs)) => {
+                let (bitmap, cardinality) = rhs.combine_array(
+                    lhs,
+                    |word, mask| op.apply(mask, word),
+                    pool,
+                );
+                (Container::Bitmap(bitmap), cardinality)
+            },
+            (Operand::Bitmap(lhs), Operand::Array(rhs)) => {
+                let (bitmap, cardinality) = lhs.combine_array(
+                    rhs,
+                    |word, mask| op.apply(word, mask),
+                    pool,
+                );
+                (Container::Bitmap(bitmap), cardinality)
+            },
+        };
+
+        (cardinality != 0).then_some((container, cardinality))
+    }
+
+    /// Returns the container as an operand for set operations, expanding run
+    /// and full containers into bitmaps.
+    fn operand(&self) -> Operand<'_> {
+        match *self {
+            Container::Array(ref array) => Operand::Array(array),
+            Container::Bitmap(ref bitmap) => {
+                Operand::Bitmap(Cow::Borrowed(bitmap))
+            },
+            Container::Run(ref run) => Operand::Bitmap(Cow::Owned(run.into())),
+            Container::Full => Operand::Bitmap(Cow::Owned(Bitmap::full())),
+        }
+    }
+}
+
+impl Clone for Container {
+    fn clone(&self) -> Self {
+        match *self {
+            Container::Array(ref array) => Container::Array(array.clone()),
+            Container::Bitmap(ref bitmap) => Container::Bitmap(bitmap.clone()),
+            Container::Run(ref run) => Container::Run(run.clone()),
+            Container::Full => Container::Full,
+        }
+    }
+
+    /// Reuses the allocation of the container when both are of the same kind.
+    // Matching through references, as required by `pattern_type_mismatch`.
+    #[allow(clippy::needless_borrowed_reference)]
+    fn clone_from(&mut self, source: &Self) {
+        match (&mut *self, source) {
+            (
+                &mut Container::Array(ref mut dst),
+                &Container::Array(ref src),
+            ) => {
+                dst.clone_from(src);
+            },
+            (
+                &mut Container::Bitmap(ref mut dst),
+                &Container::Bitmap(ref src),
+            ) => dst.clone_from(src),
+            (&mut Container::Run(ref mut dst), &Container::Run(ref src)) => {
+                dst.clone_from(src);
+            },
+            _ => *self = source.clone(),
+        }
+    }
+}
+
+/// Container operand of a set operation.
+enum Operand<'a> {
+    /// Array operand.
+    Array(&'a Array),
+    /// Bitmap operand.
+    Bitmap(Cow<'a, Bitmap>),
+}
+
+/// Set operation between two containers.
+#[derive(Clone, Copy)]
+pub(crate) enum Operation {
+    /// Intersection.
+    And,
+    /// Union.
+    Or,
+    /// Symmetric difference.
+    Xor,
+    /// Difference.
+    AndNot,
+}
+
+impl Operation {
+    /// Returns true if a value is part of the result, given its presence in
+    /// the left and right operands.
+    pub(crate) fn keep(self, in_lhs: bool, in_rhs: bool) -> bool {
+        match self {
+            Self::And => in_lhs && in_rhs,
+            Self::Or => in_lhs || in_rhs,
+            Self::Xor => in_lhs != in_rhs,
+            Self::AndNot => in_lhs && !in_rhs,
+        }
+    }
+
+    /// Applies the operation on two bitmap words (or vectors of words).
+    fn apply<T>(self, lhs: T, rhs: T) -> T
+    where
+        T: BitAnd<Output = T>
+            + BitOr<Output = T>
+            + BitXor<Output = T>
+            + Not<Output = T>,
+    {
+        match self {
+            Self::And => lhs & rhs,
+            Self::Or => lhs | rhs,
+            Self::Xor => lhs ^ rhs,
+            Self::AndNot => lhs & !rhs,
         }
     }
 }
@@ -82,7 +496,11 @@ pub(crate) enum Iter<'a> {
     /// Array container iterator.
     Array(array::Iter<'a>),
     /// Bitmap container iterator.
-    Bitmap(bitmap::Iter<'a>),
+    Bitmap(bitmap::Iter<&'a [u64; BITMAP_WORD_COUNT]>),
+    /// Run container iterator.
+    Run(run::Iter<&'a Run>),
+    /// Full container iterator.
+    Full(RangeInclusive<u16>),
 }
 
 impl<'a> Iter<'a> {
@@ -90,17 +508,469 @@ impl<'a> Iter<'a> {
         match *container {
             Container::Array(ref array) => Self::Array(array.iter()),
             Container::Bitmap(ref bitmap) => Self::Bitmap(bitmap.iter()),
+            Container::Run(ref run) => Self::Run(run.iter()),
+            Container::Full => Self::Full(u16::MIN..=u16::MAX),
         }
     }
 }
 
-impl<'a> Iterator for Iter<'a> {
+impl Iter<'_> {
+    /// Decodes the next values into `buf`, combined with `base` (i.e. the
+    /// high bits of the values).
+    ///
+    /// Returns the number of decoded values: less than the buffer length
+    /// only when the iterator is exhausted.
+    pub(crate) fn next_many(&mut self, buf: &mut [u32], base: u32) -> usize {
+        match *self {
+            Self::Array(ref mut array) => array.next_many(buf, base),
+            Self::Bitmap(ref mut bitmap) => bitmap.next_many(buf, base),
+            Self::Run(_) | Self::Full(_) => {
+                buf.iter_mut()
+                    .zip(self)
+                    .map(|(slot, value)| *slot = base | u32::from(value))
+                    .count()
+            },
+        }
+    }
+}
+
+impl Iter<'_> {
+    /// Skips the values lower than `value`.
+    ///
+    /// Returns the number of skipped values.
+    pub(crate) fn advance_to(&mut self, value: u16) -> usize {
+        match *self {
+            Self::Array(ref mut array) => array.advance_to(value),
+            Self::Bitmap(ref mut bitmap) => bitmap.advance_to(value),
+            Self::Run(ref mut run) => run.advance_to(value),
+            Self::Full(ref mut range) => advance_range(range, value),
+        }
+    }
+}
+
+impl Iterator for Iter<'_> {
     type Item = u16;
 
     fn next(&mut self) -> Option<u16> {
         match *self {
             Self::Array(ref mut array) => array.next(),
             Self::Bitmap(ref mut bitmap) => bitmap.next(),
+            Self::Run(ref mut run) => run.next(),
+            Self::Full(ref mut range) => range.next(),
+        }
+    }
+
+    fn nth(&mut self, n: usize) -> Option<u16> {
+        match *self {
+            Self::Array(ref mut array) => array.nth(n),
+            Self::Bitmap(ref mut bitmap) => bitmap.nth(n),
+            Self::Run(ref mut run) => run.nth(n),
+            Self::Full(ref mut range) => range.nth(n),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match *self {
+            Self::Array(ref array) => array.size_hint(),
+            Self::Bitmap(ref bitmap) => bitmap.size_hint(),
+            Self::Run(ref run) => run.size_hint(),
+            Self::Full(ref range) => range.size_hint(),
+        }
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<u16> {
+        match *self {
+            Self::Array(ref mut array) => array.next_back(),
+            Self::Bitmap(ref mut bitmap) => bitmap.next_back(),
+            Self::Run(ref mut run) => run.next_back(),
+            Self::Full(ref mut range) => range.next_back(),
+        }
+    }
+}
+
+impl IntoIterator for Container {
+    type Item = u16;
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> IntoIter {
+        match self {
+            Container::Array(array) => IntoIter::Array(array.into_iter()),
+            Container::Bitmap(bitmap) => IntoIter::Bitmap(bitmap.into_iter()),
+            Container::Run(run) => IntoIter::Run(run.into_iter()),
+            Container::Full => IntoIter::Full(u16::MIN..=u16::MAX),
+        }
+    }
+}
+
+/// Owning iterator over the values of a container.
+pub(crate) enum IntoIter {
+    /// Array container iterator.
+    Array(vec::IntoIter<u16>),
+    /// Bitmap container iterator.
+    Bitmap(bitmap::Iter<Box<[u64; BITMAP_WORD_COUNT]>>),
+    /// Run container iterator.
+    Run(run::Iter<Run>),
+    /// Full container iterator.
+    Full(RangeInclusive<u16>),
+}
+
+impl Iterator for IntoIter {
+    type Item = u16;
+
+    fn next(&mut self) -> Option<u16> {
+        match *self {
+            Self::Array(ref mut array) => array.next(),
+            Self::Bitmap(ref mut bitmap) => bitmap.next(),
+            Self::Run(ref mut run) => run.next(),
+            Self::Full(ref mut range) => range.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match *self {
+            Self::Array(ref array) => array.size_hint(),
+            Self::Bitmap(ref bitmap) => bitmap.size_hint(),
+            Self::Run(ref run) => run.size_hint(),
+            Self::Full(ref range) => range.size_hint(),
+        }
+    }
+}
+
+impl DoubleEndedIterator for IntoIter {
+    fn next_back(&mut self) -> Option<u16> {
+        match *self {
+            Self::Array(ref mut array) => array.next_back(),
+            Self::Bitmap(ref mut bitmap) => bitmap.next_back(),
+            Self::Run(ref mut run) => run.next_back(),
+            Self::Full(ref mut range) => range.next_back(),
+        }
+    }
+}
+
+/// Skips the values of the range lower than `value`.
+///
+/// Returns the number of skipped values.
+fn advance_range(range: &mut RangeInclusive<u16>, value: u16) -> usize {
+    if RangeInclusive::is_empty(range) || *range.start() >= value {
+        return 0;
+    }
+    if *range.end() < value {
+        let skipped = range.len();
+        range.nth(skipped);
+        return skipped;
+    }
+
+    let skipped = usize::from(value - range.start());
+    *range = value..=*range.end();
+    skipped
+}
+
+/// Iterator over the runs of consecutive values in a container.
+pub(crate) enum Ranges<'a> {
+    /// Array container runs.
+    Array(array::Ranges<'a>),
+    /// Bitmap container runs.
+    Bitmap(bitmap::Ranges<'a>),
+    /// Run container runs.
+    Run(run::Ranges<'a>),
+    /// Full container run, until visited.
+    Full(Option<RangeInclusive<u16>>),
+}
+
+impl Iterator for Ranges<'_> {
+    type Item = RangeInclusive<u16>;
+
+    fn next(&mut self) -> Option<RangeInclusive<u16>> {
+        match *self {
+            Self::Array(ref mut array) => array.next(),
+            Self::Bitmap(ref mut bitmap) => bitmap.next(),
+            Self::Run(ref mut run) => run.next(),
+            Self::Full(ref mut range) => range.take(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(result: Option<(Container, usize)>) -> Vec<u16> {
+        result.map_or_else(Vec::new, |(container, cardinality)| {
+            let values = container.iter().collect::<Vec<_>>();
+            assert_eq!(values.len(), cardinality, "cardinality mismatch");
+            values
+        })
+    }
+
+    fn containers(values: &[u16]) -> Vec<Container> {
+        let array = values.iter().copied().collect::<Array>();
+        let bitmap = values.iter().copied().collect::<Bitmap>();
+        let run = values.iter().copied().collect::<Run>();
+
+        vec![
+            Container::Array(array),
+            Container::Bitmap(bitmap),
+            Container::Run(run),
+        ]
+    }
+
+    #[test]
+    fn operations() {
+        for lhs in &containers(&[1, 2, 3, 10, 20, 30]) {
+            for rhs in &containers(&[3, 4, 5, 20, 40]) {
+                assert_eq!(values(lhs.and(rhs)), vec![3, 20]);
+                assert_eq!(lhs.and_len(rhs), 2);
+                assert_eq!(
+                    values(lhs.or(rhs)),
+                    vec![1, 2, 3, 4, 5, 10, 20, 30, 40]
+                );
+                assert_eq!(values(lhs.xor(rhs)), vec![1, 2, 4, 5, 10, 30, 40]);
+                assert_eq!(values(lhs.and_not(rhs)), vec![1, 2, 10, 30]);
+                assert_eq!(values(rhs.and_not(lhs)), vec![4, 5, 40]);
+            }
+        }
+    }
+
+    #[test]
+    fn try_for_each() {
+        let input = (0..5_000).step_by(3).chain(60_000..=u16::MAX);
+        let expected = input.clone().collect::<Vec<_>>();
+        let mut containers = containers(&expected);
+        containers.push(Container::Full);
+
+        for container in &containers {
+            let mut values = Vec::new();
+            let result = container.try_for_each(|value| {
+                values.push(value);
+                Ok::<_, ()>(())
+            });
+            assert_eq!(result, Ok(()));
+            assert!(values.into_iter().eq(container.iter()));
+
+            // Stops at the first error.
+            let mut count = 0;
+            let result = container.try_for_each(|value| {
+                count += 1;
+                if value >= 4_000 {
+                    return Err(value);
+                }
+                Ok(())
+            });
+            assert_eq!(
+                result,
+                Err(container.iter().find(|&v| v >= 4_000).expect("value"))
+            );
+            assert_eq!(
+                count,
+                container.iter().take_while(|&v| v < 4_000).count() + 1
+            );
+        }
+    }
+
+    #[test]
+    fn try_for_each_in() {
+        let input = (0..5_000).step_by(3).chain(60_000..=u16::MAX);
+        let mut containers = containers(&input.collect::<Vec<_>>());
+        containers.push(Container::Full);
+
+        for container in &containers {
+            for range in [
+                0..=u16::MAX,
+                0..=0,
+                63..=64,
+                100..=4_999,
+                4_000..=60_010,
+                65_535..=65_535,
+            ] {
+                let mut values = Vec::new();
+                let result =
+                    container.try_for_each_in(range.clone(), |value| {
+                        values.push(value);
+                        Ok::<_, ()>(())
+                    });
+                assert_eq!(result, Ok(()));
+                let expected =
+                    container.iter().filter(|value| range.contains(value));
+                assert!(values.into_iter().eq(expected), "{range:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn and_limited() {
+        let lhs = (0..5_000).step_by(3).collect::<Vec<_>>();
+        let rhs = (0..5_000).step_by(5).collect::<Vec<_>>();
+        for lhs in &containers(&lhs) {
+            for rhs in &containers(&rhs) {
+                for limit in [0, 1, 7, 334, 1_000] {
+                    let mut shared = vec![42];
+                    lhs.and_limited(rhs, limit, &mut shared);
+                    let expected = (0..5_000).step_by(15).take(limit);
+                    assert!(shared[1..].iter().copied().eq(expected));
+                    assert_eq!(shared[0], 42, "values are appended");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ranges() {
+        // Runs at both ends, across and on word boundaries, and single values.
+        let values = (0..3)
+            .chain(60..130)
+            .chain([192, 255])
+            .chain(256..320)
+            .chain(65_530..=65_535)
+            .collect::<Vec<u16>>();
+        let expected = [0..=2, 60..=129, 192..=192, 255..=319, 65_530..=65_535];
+
+        for container in &containers(&values) {
+            assert_eq!(container.ranges().collect::<Vec<_>>(), expected);
+        }
+        assert!(Container::Full.ranges().eq([0..=u16::MAX]));
+    }
+
+    #[test]
+    fn select() {
+        let values = (0..3)
+            .chain(60..130)
+            .chain([192, 255])
+            .chain(65_530..=65_535)
+            .collect::<Vec<u16>>();
+
+        for container in &containers(&values) {
+            for (n, &value) in values.iter().enumerate() {
+                assert_eq!(container.select(n), Some(value));
+            }
+            assert_eq!(container.select(values.len()), None);
+        }
+        assert_eq!(Container::Full.select(42), Some(42));
+        assert_eq!(Container::Full.select(65_536), None);
+    }
+
+    #[test]
+    fn rank() {
+        let values = (0..3)
+            .chain(60..130)
+            .chain([192, 255])
+            .chain(65_530..=65_535)
+            .collect::<Vec<u16>>();
+
+        for container in &containers(&values) {
+            for (n, &value) in values.iter().enumerate() {
+                assert_eq!(container.rank(value), n + 1);
+            }
+            assert_eq!(container.rank(59), 3);
+            assert_eq!(container.rank(200), 74);
+        }
+        assert_eq!(Container::Full.rank(0), 1);
+        assert_eq!(Container::Full.rank(u16::MAX), 65_536);
+    }
+
+    #[test]
+    fn double_ended() {
+        let values = (0..3)
+            .chain(60..130)
+            .chain([192, 255])
+            .chain(65_530..=65_535)
+            .collect::<Vec<u16>>();
+
+        for container in containers(&values) {
+            assert!(container.iter().rev().eq(values.iter().rev().copied()));
+
+            // Both ends meet without missing or repeating a value.
+            let mut iter = container.iter();
+            let (mut head, mut tail) = (Vec::new(), Vec::new());
+            while let Some(value) = iter.next() {
+                head.push(value);
+                tail.extend(iter.next_back());
+                assert_eq!(
+                    iter.size_hint().0,
+                    values.len() - head.len() - tail.len()
+                );
+            }
+            head.extend(tail.iter().rev());
+            assert_eq!(head, values);
+
+            // Seeking from the front stops at the values taken from the back.
+            let mut iter = container.iter();
+            assert_eq!(iter.next_back(), Some(65_535));
+            assert_eq!(iter.advance_to(65_000), 75);
+            assert!(iter.eq(65_530..65_535));
+            let mut iter = container.iter();
+            assert_eq!(iter.next_back(), Some(65_535));
+            assert_eq!(iter.advance_to(65_533), values.len() - 3);
+            assert!(iter.eq([65_533, 65_534]));
+            let mut iter = container.iter();
+            assert_eq!(iter.nth_back(2), Some(65_533));
+            assert_eq!(iter.advance_to(65_534), values.len() - 3);
+            assert_eq!(iter.size_hint(), (0, Some(0)));
+            assert_eq!(iter.next(), None);
+
+            let mut iter = container.into_iter();
+            assert_eq!(iter.next_back(), Some(65_535));
+            assert_eq!(iter.next(), Some(0));
+            assert!(iter
+                .rev()
+                .eq(values[1..values.len() - 1].iter().rev().copied()));
+        }
+        assert!(Container::Full.into_iter().rev().eq((0..=u16::MAX).rev()));
+    }
+
+    #[test]
+    fn clone_from() {
+        let values = (0..3).chain(60..130).collect::<Vec<u16>>();
+
+        for (dst, src) in
+            containers(&[1, 2, 3]).iter_mut().zip(containers(&values))
+        {
+            dst.clone_from(&src);
+            assert!(dst.iter().eq(values.iter().copied()));
+        }
+
+        // Bitmap words are copied in place.
+        let mut dst = Container::Bitmap([1].into_iter().collect());
+        let src = Container::Bitmap(values.iter().copied().collect());
+        let words = |container: &Container| {
+            match *container {
+                Container::Bitmap(ref bitmap) => bitmap.words().as_ptr(),
+                _ => unreachable!("bitmap container"),
+            }
+        };
+        let before = words(&dst);
+        dst.clone_from(&src);
+        assert_eq!(words(&dst), before);
+        assert!(dst.iter().eq(values.iter().copied()));
+
+        // Different kinds are replaced.
+        let mut dst = Container::Full;
+        dst.clone_from(&src);
+        assert_eq!(dst.kind(), ContainerKind::Bitmap);
+    }
+
+    #[test]
+    fn empty_result() {
+        for lhs in &containers(&[1, 2, 3]) {
+            for rhs in &containers(&[4, 5, 6]) {
+                assert!(lhs.and(rhs).is_none());
+                assert!(lhs.xor(lhs).is_none());
+                assert!(lhs.and_not(lhs).is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn full_container() {
+        let full = Container::Full;
+
+        for other in &containers(&[1, 2, 3]) {
+            assert_eq!(values(full.and(other)), vec![1, 2, 3]);
+            assert_eq!(full.and_len(other), 3);
+            assert_eq!(full.or(other).map(|(_, len)| len), Some(65_536));
+            assert_eq!(full.xor(other).map(|(_, len)| len), Some(65_533));
+            assert!(other.and_not(&full).is_none());
         }
     }
 }
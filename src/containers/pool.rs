@@ -0,0 +1,127 @@
+use super::{
+    array::Array,
+    bitmap::{Bitmap, BITMAP_WORD_COUNT},
+    Container,
+};
+use alloc::{boxed::Box, vec::Vec};
+
+/// Maximum number of allocations of each kind kept by a pool.
+const POOL_CAPACITY: usize = 8;
+
+/// Allocations of discarded containers, recycled by container conversions.
+///
+/// Avoids hammering the allocator when chunks keep switching between array and
+/// bitmap containers, or keep being deleted and recreated.
+#[derive(Default)]
+pub(crate) struct Pool {
+    /// Bitmap words (8 kB each).
+    bitmaps: Vec<Box<[u64; BITMAP_WORD_COUNT]>>,
+    /// Array buffers, always empty.
+    arrays: Vec<Vec<u16>>,
+}
+
+impl Pool {
+    /// Keeps the allocation of a discarded container, if any and if the pool
+    /// isn't full yet.
+    pub(crate) fn recycle(&mut self, container: Container) {
+        match container {
+            Container::Bitmap(bitmap) if self.bitmaps.len() < POOL_CAPACITY => {
+                self.bitmaps.push(bitmap.into_words());
+            },
+            Container::Array(array) if self.arrays.len() < POOL_CAPACITY => {
+                if let Some(mut values) = array.into_vec() {
+                    values.clear();
+                    self.arrays.push(values);
+                }
+            },
+            _ => (),
+        }
+    }
+
+    /// Builds a bitmap container from values, reusing a recycled allocation
+    /// if possible.
+    pub(crate) fn bitmap<I>(&mut self, values: I) -> Container
+    where
+        I: IntoIterator<Item = u16>,
+    {
+        let mut bitmap = self
+            .bitmaps
+            .pop()
+            .map_or_else(Bitmap::new, Bitmap::from_recycled);
+        for value in values {
+            bitmap.insert(value);
+        }
+
+        Container::Bitmap(bitmap)
+    }
+
+    /// Builds an array container from values sorted in ascending order,
+    /// reusing a recycled allocation if possible.
+    pub(crate) fn array<I>(&mut self, values: I) -> Container
+    where
+        I: IntoIterator<Item = u16>,
+    {
+        let mut buffer = self.arrays.pop().unwrap_or_default();
+        buffer.extend(values);
+
+        Container::Array(Array::from_vec(buffer))
+    }
+
+    /// Takes the words of a recycled bitmap, if any.
+    ///
+    /// Words are left as is: the caller must overwrite them.
+    pub(super) fn take_words(
+        &mut self,
+    ) -> Option<Box<[u64; BITMAP_WORD_COUNT]>> {
+        self.bitmaps.pop()
+    }
+
+    /// Returns the number of bytes held by the pool.
+    pub(crate) fn mem_size(&self) -> usize {
+        self.bitmaps.len() * size_of::<[u64; BITMAP_WORD_COUNT]>()
+            + self.arrays.iter().fold(0, |acc, values| {
+                acc + values.capacity() * size_of::<u16>()
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recycle() {
+        let mut pool = Pool::default();
+        assert_eq!(pool.mem_size(), 0);
+
+        // Allocations are kept...
+        let bitmap = pool.bitmap(0..5_000);
+        pool.recycle(bitmap);
+        let empty = pool.array([]);
+        pool.recycle(empty);
+        let array = pool.array((0..100).map(|value| value * 3));
+        pool.recycle(array);
+        pool.recycle(Container::new(42));
+        assert_eq!(pool.bitmaps.len(), 1);
+        assert_eq!(pool.arrays.len(), 1, "inline and empty arrays");
+        assert!(pool.mem_size() >= 8_192 + 200);
+
+        // ...and reused, without leaking previous values.
+        let bitmap = pool.bitmap([1, 2, 3]);
+        assert_eq!(bitmap.iter().collect::<Vec<_>>(), vec![1, 2, 3]);
+        let array = pool.array(10..20);
+        assert_eq!(
+            array.iter().collect::<Vec<_>>(),
+            (10..20).collect::<Vec<_>>()
+        );
+        assert_eq!(pool.mem_size(), 0);
+
+        // Up to a limit.
+        for _ in 0..2 * POOL_CAPACITY {
+            let bitmap = pool.bitmap([1]);
+            pool.recycle(bitmap);
+            pool.recycle(Container::Bitmap(Bitmap::new()));
+        }
+        assert_eq!(pool.bitmaps.len(), POOL_CAPACITY);
+    }
+}
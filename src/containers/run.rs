@@ -0,0 +1,556 @@
+use super::{advance_range, array::Array, bitmap::Bitmap};
+use alloc::{vec, vec::Vec};
+use core::{
+    borrow::Borrow,
+    iter::FromIterator,
+    ops::{Range, RangeInclusive},
+};
+
+/// A sorted list of runs of consecutive 16-bit integers.
+pub(crate) struct Run(Vec<Interval>);
+
+impl Clone for Run {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        self.0.clone_from(&source.0);
+    }
+}
+
+impl Run {
+    /// Initializes a new run container with the given value.
+    #[cfg(test)]
+    pub(super) fn new(value: u16) -> Self {
+        Self(vec![Interval::new(value, value)])
+    }
+
+    /// Initializes a run container from disjoint, non-adjacent, runs sorted
+    /// in ascending order.
+    pub(super) fn from_ranges<I>(ranges: I) -> Self
+    where
+        I: IntoIterator<Item = RangeInclusive<u16>>,
+    {
+        Self(
+            ranges
+                .into_iter()
+                .map(|range| Interval::new(*range.start(), *range.end()))
+                .collect(),
+        )
+    }
+
+    /// Initializes a new run container holding every 16-bit integer.
+    pub(super) fn full() -> Self {
+        Self(vec![Interval::new(u16::MIN, u16::MAX)])
+    }
+
+    /// Adds a value to the run container.
+    ///
+    /// If the container did not have this value present, true is returned.
+    /// If the container did have this value present, false is returned.
+    pub(super) fn insert(&mut self, value: u16) -> bool {
+        // Index of the first run starting after the value.
+        let index = self.0.partition_point(|run| run.start <= value);
+
+        if index > 0 && value <= self.0[index - 1].end() {
+            return false;
+        }
+        // No overflow: the value is after the end of the previous run and
+        // before the start of the next one.
+        let extends_prev = index > 0 && self.0[index - 1].end() + 1 == value;
+        let extends_next =
+            index < self.0.len() && value + 1 == self.0[index].start;
+
+        match (extends_prev, extends_next) {
+            // The value fills the gap between two runs: merge them.
+            (true, true) => {
+                let next = self.0.remove(index);
+                let prev = &mut self.0[index - 1];
+                *prev = Interval::new(prev.start, next.end());
+            },
+            (true, false) => self.0[index - 1].length += 1,
+            (false, true) => {
+                let next = &mut self.0[index];
+                next.start -= 1;
+                next.length += 1;
+            },
+            (false, false) => self.0.insert(index, Interval::new(value, value)),
+        }
+
+        true
+    }
+
+    /// Removes a value from the run container.
+    ///
+    /// Returns whether the value was present or not.
+    pub(super) fn remove(&mut self, value: u16) -> bool {
+        let Some(index) = self.find(value) else {
+            return false;
+        };
+        let (start, end) = (self.0[index].start, self.0[index].end());
+
+        if start == end {
+            self.0.remove(index);
+        } else if value == start {
+            self.0[index] = Interval::new(value + 1, end);
+        } else if value == end {
+            self.0[index] = Interval::new(start, value - 1);
+        } else {
+            // Removing from the middle of a run splits it in two.
+            self.0[index] = Interval::new(start, value - 1);
+            self.0.insert(index + 1, Interval::new(value + 1, end));
+        }
+
+        true
+    }
+
+    /// Returns true if the run container contains the value.
+    pub(super) fn contains(&self, value: u16) -> bool {
+        self.find(value).is_some()
+    }
+
+    /// Finds the smallest value in the run container.
+    pub(super) fn min(&self) -> Option<u16> {
+        self.0.first().map(|run| run.start)
+    }
+
+    /// Finds the largest value in the run container.
+    pub(super) fn max(&self) -> Option<u16> {
+        self.0.last().map(Interval::end)
+    }
+
+    /// Returns the `n`-th smallest value in the run container, if any.
+    pub(super) fn select(&self, mut n: usize) -> Option<u16> {
+        for run in &self.0 {
+            let length = usize::from(run.length);
+            if n <= length {
+                // n is bounded by the run length: no truncation.
+                #[allow(clippy::cast_possible_truncation)]
+                return Some(run.start + n as u16);
+            }
+            n -= length + 1;
+        }
+        None
+    }
+
+    /// Returns the number of values in the run container lower than or equal
+    /// to `value`.
+    pub(super) fn rank(&self, value: u16) -> usize {
+        self.0
+            .iter()
+            .take_while(|run| run.start <= value)
+            .fold(0, |acc, run| {
+                acc + usize::from(run.end().min(value) - run.start) + 1
+            })
+    }
+
+    /// Returns the number of runs in the container.
+    pub(super) fn count_runs(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Gets an iterator that visits the runs of the container, as pairs of
+    /// start and length minus one.
+    pub(super) fn intervals(&self) -> impl Iterator<Item = (u16, u16)> + '_ {
+        self.0.iter().map(|run| (run.start, run.length))
+    }
+
+    /// Gets an iterator that visits the values in the run container in
+    /// ascending order.
+    pub(super) fn iter(&self) -> Iter<&Self> {
+        Iter::new(self)
+    }
+
+    /// Gets an iterator that visits the runs of the container in ascending
+    /// order.
+    pub(super) fn ranges(&self) -> Ranges<'_> {
+        Ranges(self.0.iter())
+    }
+
+    /// Calls `f` on each value of the run container within `range` in
+    /// ascending order, stopping at the first error.
+    pub(super) fn try_for_each_in<E>(
+        &self,
+        range: RangeInclusive<u16>,
+        mut f: impl FnMut(u16) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let first = self.0.partition_point(|run| run.end() < *range.start());
+        for run in &self.0[first..] {
+            if run.start > *range.end() {
+                break;
+            }
+            let start = run.start.max(*range.start());
+            for value in start..=run.end().min(*range.end()) {
+                f(value)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the approximate in-memory size of the run container, in bytes.
+    pub(super) fn mem_size(&self) -> usize {
+        size_of_val(self) + self.0.len() * size_of::<Interval>()
+    }
+
+    /// Returns by how many bytes the run container would grow if `value`,
+    /// absent from it, was added.
+    pub(super) fn insert_mem_delta(&self, value: u16) -> usize {
+        let index = self.0.partition_point(|run| run.start <= value);
+        let extends_prev = index > 0 && self.0[index - 1].end() + 1 == value;
+        let extends_next =
+            index < self.0.len() && value + 1 == self.0[index].start;
+
+        if extends_prev || extends_next {
+            0
+        } else {
+            size_of::<Interval>()
+        }
+    }
+
+    /// Releases the unused capacity of the run list.
+    pub(super) fn shrink_to_fit(&mut self) {
+        self.0.shrink_to_fit();
+    }
+
+    /// Returns the index of the run containing the value, if any.
+    fn find(&self, value: u16) -> Option<usize> {
+        let index = self.0.partition_point(|run| run.start <= value);
+
+        (index > 0 && value <= self.0[index - 1].end()).then(|| index - 1)
+    }
+
+    #[cfg(test)]
+    fn runs(&self) -> Vec<(u16, u16)> {
+        self.0.iter().map(|run| (run.start, run.end())).collect()
+    }
+}
+
+impl FromIterator<u16> for Run {
+    /// Builds a run container from values sorted in ascending order.
+    fn from_iter<I: IntoIterator<Item = u16>>(iter: I) -> Self {
+        let mut runs: Vec<Interval> = Vec::new();
+
+        for value in iter {
+            match runs.last_mut() {
+                Some(run) if u32::from(run.end()) + 1 == u32::from(value) => {
+                    run.length += 1;
+                },
+                _ => runs.push(Interval::new(value, value)),
+            }
+        }
+
+        Self(runs)
+    }
+}
+
+impl From<&Array> for Run {
+    fn from(array: &Array) -> Self {
+        array.iter().collect()
+    }
+}
+
+impl From<&Bitmap> for Run {
+    fn from(bitmap: &Bitmap) -> Self {
+        bitmap.iter().collect()
+    }
+}
+
+/// A run of consecutive values.
+#[derive(Clone)]
+struct Interval {
+    /// First value of the run.
+    start: u16,
+    /// Run's length minus one.
+    ///
+    /// -1 allows to represent a run spanning the whole 16-bit space, and it's
+    /// safe because the minimum length is 1 (empty runs are deleted).
+    length: u16,
+}
+
+impl Interval {
+    /// Initializes a new interval covering `start..=end`.
+    fn new(start: u16, end: u16) -> Self {
+        debug_assert!(start <= end, "invalid interval");
+        Self {
+            start,
+            length: end - start,
+        }
+    }
+
+    /// Returns the last value of the run.
+    fn end(&self) -> u16 {
+        self.start + self.length
+    }
+
+    /// Returns the values covered by the run.
+    fn values(&self) -> RangeInclusive<u16> {
+        self.start..=self.end()
+    }
+}
+
+/// Iterator over the runs of a run container.
+pub(crate) struct Ranges<'a>(core::slice::Iter<'a, Interval>);
+
+impl Iterator for Ranges<'_> {
+    type Item = RangeInclusive<u16>;
+
+    fn next(&mut self) -> Option<RangeInclusive<u16>> {
+        self.0.next().map(Interval::values)
+    }
+}
+
+impl IntoIterator for Run {
+    type Item = u16;
+    type IntoIter = Iter<Run>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Iter::new(self)
+    }
+}
+
+/// Iterator over the values of a run container, borrowed or owned.
+pub(crate) struct Iter<R> {
+    run: R,
+    /// Indexes of the runs not visited yet.
+    pending: Range<usize>,
+    /// Values left in the run visited from the front.
+    current: Option<RangeInclusive<u16>>,
+    /// Values left in the run visited from the back.
+    back: Option<RangeInclusive<u16>>,
+    size: usize,
+}
+
+impl<R: Borrow<Run>> Iter<R> {
+    fn new(run: R) -> Self {
+        let runs = &run.borrow().0;
+        Self {
+            pending: 0..runs.len(),
+            current: None,
+            back: None,
+            size: runs
+                .iter()
+                .fold(0, |acc, run| acc + usize::from(run.length) + 1),
+            run,
+        }
+    }
+
+    /// Returns the values of the next run, taking the one visited from the
+    /// back once every other run is visited.
+    fn next_run(&mut self) -> Option<RangeInclusive<u16>> {
+        match self.pending.next() {
+            Some(index) => Some(self.run.borrow().0[index].values()),
+            None => self.back.take(),
+        }
+    }
+
+    /// Returns the values of the previous run, taking the one visited from
+    /// the front once every other run is visited.
+    fn next_back_run(&mut self) -> Option<RangeInclusive<u16>> {
+        match self.pending.next_back() {
+            Some(index) => Some(self.run.borrow().0[index].values()),
+            None => self.current.take(),
+        }
+    }
+
+    /// Skips the values lower than `value`.
+    ///
+    /// Returns the number of skipped values.
+    pub(super) fn advance_to(&mut self, value: u16) -> usize {
+        let mut skipped = 0;
+        let ahead = self.current.as_ref().is_some_and(|current| {
+            !current.is_empty() && *current.end() >= value
+        });
+        if !ahead {
+            // Skip the current run, and the whole runs before the value.
+            skipped += self.current.as_ref().map_or(0, ExactSizeIterator::len);
+            let runs = &self.run.borrow().0[self.pending.clone()];
+            let count = runs.partition_point(|run| run.end() < value);
+            skipped += runs[..count]
+                .iter()
+                .fold(0, |acc, run| acc + usize::from(run.length) + 1);
+            self.pending.start += count;
+            self.current = self.next_run();
+        }
+        if let Some(ref mut current) = self.current {
+            skipped += advance_range(current, value);
+        }
+        self.size -= skipped;
+
+        skipped
+    }
+}
+
+impl<R: Borrow<Run>> Iterator for Iter<R> {
+    type Item = u16;
+
+    fn next(&mut self) -> Option<u16> {
+        loop {
+            if let Some(value) = self.current.as_mut().and_then(Iterator::next)
+            {
+                self.size -= 1;
+                return Some(value);
+            }
+            self.current = Some(self.next_run()?);
+        }
+    }
+
+    /// Skips whole runs, rather than their values one by one.
+    fn nth(&mut self, mut n: usize) -> Option<u16> {
+        loop {
+            if let Some(ref mut current) = self.current {
+                let len = current.len();
+                if n < len {
+                    self.size -= n + 1;
+                    return current.nth(n);
+                }
+                n -= len;
+                self.size -= len;
+            }
+            self.current = Some(self.next_run()?);
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.size, Some(self.size))
+    }
+}
+
+impl<R: Borrow<Run>> DoubleEndedIterator for Iter<R> {
+    fn next_back(&mut self) -> Option<u16> {
+        loop {
+            if let Some(value) =
+                self.back.as_mut().and_then(DoubleEndedIterator::next_back)
+            {
+                self.size -= 1;
+                return Some(value);
+            }
+            self.back = Some(self.next_back_run()?);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_merge() {
+        let mut run = Run::new(10);
+
+        // Extends the run on both sides.
+        run.insert(11);
+        run.insert(9);
+        assert_eq!(run.runs(), vec![(9, 11)]);
+
+        // Creates new runs.
+        run.insert(15);
+        run.insert(0);
+        assert_eq!(run.runs(), vec![(0, 0), (9, 11), (15, 15)]);
+
+        // Fills the gaps.
+        run.insert(13);
+        run.insert(12);
+        run.insert(14);
+        assert_eq!(run.runs(), vec![(0, 0), (9, 15)]);
+    }
+
+    #[test]
+    fn remove_split() {
+        let mut run = (0..10).collect::<Run>();
+
+        // Shrinks the run on both sides.
+        run.remove(0);
+        run.remove(9);
+        assert_eq!(run.runs(), vec![(1, 8)]);
+
+        // Splits the run in two.
+        run.remove(5);
+        assert_eq!(run.runs(), vec![(1, 4), (6, 8)]);
+
+        // Deletes single-value runs.
+        run.remove(7);
+        run.remove(6);
+        run.remove(8);
+        assert_eq!(run.runs(), vec![(1, 4)]);
+    }
+
+    #[test]
+    fn contains() {
+        let mut run = Run::new(42);
+        assert!(!run.contains(11));
+
+        run.insert(11);
+        assert!(run.contains(11));
+
+        run.remove(11);
+        assert!(!run.contains(11));
+    }
+
+    #[test]
+    fn already_exists() {
+        let mut run = Run::new(42);
+
+        assert!(!run.insert(42), "already exists");
+        assert!(run.insert(11), "new entry");
+    }
+
+    #[test]
+    fn missing() {
+        let mut run = Run::new(42);
+
+        assert!(run.remove(42), "found");
+        assert!(!run.remove(11), "missing entry");
+    }
+
+    #[test]
+    fn min_max() {
+        let mut run = Run::new(42);
+        assert_eq!(run.min(), Some(42));
+        assert_eq!(run.max(), Some(42));
+
+        run.insert(11);
+        run.insert(100);
+        run.insert(77);
+        run.insert(3);
+        assert_eq!(run.min(), Some(3));
+        assert_eq!(run.max(), Some(100));
+    }
+
+    #[test]
+    fn boundaries() {
+        let run = (0..=u16::MAX).collect::<Run>();
+        assert_eq!(run.runs(), vec![(0, u16::MAX)]);
+        assert_eq!(run.iter().size_hint(), (65_536, Some(65_536)));
+
+        let mut run = Run::new(u16::MAX);
+        run.insert(0);
+        assert!(run.contains(u16::MAX));
+        assert_eq!(run.runs(), vec![(0, 0), (u16::MAX, u16::MAX)]);
+    }
+
+    #[test]
+    fn iterator() {
+        let input = vec![3u16, 4, 5, 11, 77, 78, 100];
+        let run = input.iter().copied().collect::<Run>();
+
+        assert_eq!(run.count_runs(), 4);
+        assert_eq!(run.iter().collect::<Vec<_>>(), input);
+    }
+
+    #[test]
+    fn mem_size() {
+        let mut run = Run::new(0);
+        let size = run.mem_size();
+
+        // Extending a run doesn't change its size.
+        run.insert(1);
+        run.insert(2);
+        assert_eq!(size, run.mem_size());
+
+        // Creating new runs does.
+        run.insert(42);
+        assert!(size < run.mem_size());
+    }
+}
@@ -0,0 +1,161 @@
+//! Vectorized kernels for the containers, using portable SIMD.
+
+use alloc::vec::Vec;
+use core::simd::{cmp::SimdPartialEq, num::SimdUint, u16x8, u64x8};
+
+/// Number of lanes of the vectors.
+const LANES: usize = 8;
+
+/// Counts the number of set bits in the words.
+pub(super) fn popcount(words: &[u64]) -> usize {
+    let chunks = words.chunks_exact(LANES);
+    let tail = chunks
+        .remainder()
+        .iter()
+        .fold(0, |acc, word| acc + word.count_ones() as usize);
+    let counts = chunks.fold(u64x8::splat(0), |acc, chunk| {
+        acc + u64x8::from_slice(chunk).count_ones()
+    });
+
+    usize::try_from(counts.reduce_sum()).expect("popcount fits in usize") + tail
+}
+
+/// Combines two slices of words into `dst`, using `op` on each vector.
+///
+/// Returns the number of set bits in `dst`.
+///
+/// The slices must have the same length, multiple of the number of lanes.
+pub(super) fn combine(
+    lhs: &[u64],
+    rhs: &[u64],
+    dst: &mut [u64],
+    op: impl Fn(u64x8, u64x8) -> u64x8,
+) -> usize {
+    debug_assert!(lhs.len() == rhs.len() && lhs.len() == dst.len());
+    debug_assert!(lhs.len().is_multiple_of(LANES));
+
+    let mut counts = u64x8::splat(0);
+    for (out, (lhs, rhs)) in dst
+        .chunks_exact_mut(LANES)
+        .zip(lhs.chunks_exact(LANES).zip(rhs.chunks_exact(LANES)))
+    {
+        let words = op(u64x8::from_slice(lhs), u64x8::from_slice(rhs));
+        counts += words.count_ones();
+        words.copy_to_slice(out);
+    }
+
+    usize::try_from(counts.reduce_sum()).expect("popcount fits in usize")
+}
+
+/// Intersects two sorted arrays of unique values.
+///
+/// Blocks of values are compared all-against-all, then the block with the
+/// smallest maximum is skipped, as its values can't match the next blocks.
+pub(super) fn intersect(lhs: &[u16], rhs: &[u16]) -> Vec<u16> {
+    let mut values = Vec::with_capacity(lhs.len().min(rhs.len()));
+    let (mut i, mut j) = (0, 0);
+
+    while i + LANES <= lhs.len() && j + LANES <= rhs.len() {
+        let block = u16x8::from_slice(&lhs[i..i + LANES]);
+        let other = u16x8::from_slice(&rhs[j..j + LANES]);
+
+        // Compare each value against every lane, by rotating the other block.
+        let mut found = block.simd_eq(other);
+        found |= block.simd_eq(other.rotate_elements_left::<1>());
+        found |= block.simd_eq(other.rotate_elements_left::<2>());
+        found |= block.simd_eq(other.rotate_elements_left::<3>());
+        found |= block.simd_eq(other.rotate_elements_left::<4>());
+        found |= block.simd_eq(other.rotate_elements_left::<5>());
+        found |= block.simd_eq(other.rotate_elements_left::<6>());
+        found |= block.simd_eq(other.rotate_elements_left::<7>());
+
+        values.extend(
+            lhs[i..i + LANES]
+                .iter()
+                .zip(found.to_array())
+                .filter_map(|(&value, found)| found.then_some(value)),
+        );
+
+        let (lhs_max, rhs_max) = (lhs[i + LANES - 1], rhs[j + LANES - 1]);
+        if lhs_max <= rhs_max {
+            i += LANES;
+        }
+        if rhs_max <= lhs_max {
+            j += LANES;
+        }
+    }
+
+    // Scalar merge for the remaining values.
+    while let (Some(&lhs_value), Some(&rhs_value)) = (lhs.get(i), rhs.get(j)) {
+        if lhs_value <= rhs_value {
+            i += 1;
+        }
+        if rhs_value <= lhs_value {
+            j += 1;
+        }
+        if lhs_value == rhs_value {
+            values.push(lhs_value);
+        }
+    }
+
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reference implementation of the population count.
+    fn scalar_popcount(words: &[u64]) -> usize {
+        words
+            .iter()
+            .fold(0, |acc, word| acc + word.count_ones() as usize)
+    }
+
+    #[test]
+    fn popcount_words() {
+        let words = (0..1024_u64)
+            .map(|i| i.wrapping_mul(0x9E37_79B9_7F4A_7C15))
+            .collect::<Vec<_>>();
+
+        assert_eq!(popcount(&words), scalar_popcount(&words));
+        // Not a multiple of the number of lanes.
+        assert_eq!(popcount(&words[..13]), scalar_popcount(&words[..13]));
+        assert_eq!(popcount(&[]), 0);
+    }
+
+    #[test]
+    fn combine_words() {
+        let lhs = (0..1024_u64)
+            .map(|i| i.wrapping_mul(0x9E37_79B9_7F4A_7C15))
+            .collect::<Vec<_>>();
+        let rhs = lhs
+            .iter()
+            .map(|word| word.rotate_left(7))
+            .collect::<Vec<_>>();
+        let mut dst = vec![0; lhs.len()];
+
+        let cardinality = combine(&lhs, &rhs, &mut dst, |lhs, rhs| lhs & rhs);
+
+        let expected = lhs
+            .iter()
+            .zip(rhs.iter())
+            .map(|(lhs, rhs)| lhs & rhs)
+            .collect::<Vec<_>>();
+        assert_eq!(dst, expected);
+        assert_eq!(cardinality, scalar_popcount(&expected));
+    }
+
+    #[test]
+    fn intersect_arrays() {
+        let lhs = (0..1000_u16).filter(|x| x % 3 == 0).collect::<Vec<_>>();
+        let rhs = (0..1000_u16).filter(|x| x % 5 == 0).collect::<Vec<_>>();
+        let expected =
+            (0..1000_u16).filter(|x| x % 15 == 0).collect::<Vec<_>>();
+
+        assert_eq!(intersect(&lhs, &rhs), expected);
+        assert_eq!(intersect(&rhs, &lhs), expected);
+        assert_eq!(intersect(&lhs, &[]), Vec::<u16>::new());
+        assert_eq!(intersect(&lhs[..5], &rhs[..3]), vec![0]);
+    }
+}
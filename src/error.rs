@@ -0,0 +1,118 @@
+use core::fmt;
+
+/// Error returned when a bitmap conversion fails because some values don't
+/// fit in the target's value type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TryFromBitmapError(());
+
+impl TryFromBitmapError {
+    /// Initializes a new conversion error.
+    pub(crate) fn new() -> Self {
+        Self(())
+    }
+}
+
+impl fmt::Display for TryFromBitmapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("out of range values in bitmap conversion")
+    }
+}
+
+impl core::error::Error for TryFromBitmapError {}
+
+/// Error returned when building a bitmap from values expected to be sorted in
+/// ascending order, and they are not.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NotSortedError(());
+
+impl NotSortedError {
+    /// Initializes a new ordering error.
+    pub(crate) fn new() -> Self {
+        Self(())
+    }
+}
+
+impl fmt::Display for NotSortedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("values are not sorted in ascending order")
+    }
+}
+
+impl core::error::Error for NotSortedError {}
+
+/// Error returned when adding a value to a bitmap would exceed its quota.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QuotaExceededError(());
+
+impl QuotaExceededError {
+    /// Initializes a new quota error.
+    pub(crate) fn new() -> Self {
+        Self(())
+    }
+}
+
+impl fmt::Display for QuotaExceededError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("bitmap quota exceeded")
+    }
+}
+
+impl core::error::Error for QuotaExceededError {}
+
+/// Error returned when replaying a mutation log containing an invalid or
+/// truncated record.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InvalidLogError {
+    /// Length of the log prefix made of valid records.
+    valid_up_to: usize,
+}
+
+impl InvalidLogError {
+    /// Initializes a new log error, after `valid_up_to` bytes of valid
+    /// records.
+    pub(crate) fn new(valid_up_to: usize) -> Self {
+        Self { valid_up_to }
+    }
+
+    /// Returns the length, in bytes, of the log prefix made of valid records.
+    ///
+    /// A log torn by a crash can be truncated to that length.
+    pub fn valid_up_to(&self) -> usize {
+        self.valid_up_to
+    }
+}
+
+impl fmt::Display for InvalidLogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid mutation log record at byte {}",
+            self.valid_up_to
+        )
+    }
+}
+
+impl core::error::Error for InvalidLogError {}
+
+/// Error returned when deserializing a malformed bitmap patch.
+#[cfg(feature = "serde")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InvalidPatchError(());
+
+#[cfg(feature = "serde")]
+impl InvalidPatchError {
+    /// Initializes a new patch error.
+    pub(crate) fn new() -> Self {
+        Self(())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl fmt::Display for InvalidPatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unsorted chunks or malformed runs in bitmap patch")
+    }
+}
+
+#[cfg(feature = "serde")]
+impl core::error::Error for InvalidPatchError {}
@@ -1,7 +1,9 @@
 //! Baziot — a Roaring bitmap implementation, with a more compact serialization
 
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+// The `simd` feature requires a nightly toolchain.
+#![cfg_attr(feature = "simd", feature(portable_simd))]
 // Lints {{{
-
 #![deny(
     nonstandard_style,
     rust_2018_idioms,
@@ -48,19 +50,63 @@
 
 // }}}
 
+extern crate alloc;
+
+mod advisor;
 mod chunk;
+mod chunk_info;
+mod container_view;
 mod containers;
+mod error;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod mutation_log;
+mod ops;
+mod range_index;
 mod roaring;
+mod roaring128;
+#[cfg(feature = "std")]
+mod roaring_concurrent;
+mod roaring_core;
+mod roaring_frozen;
 mod roaring_lazy;
+mod roaring_persistent;
 mod roaring_tree_map;
 mod roaring_two_levels;
+mod set16;
 mod stats;
 
-pub use roaring::Roaring;
-pub use roaring_lazy::RoaringLazy;
-pub use roaring_tree_map::RoaringTreeMap;
-pub use roaring_two_levels::RoaringTwoLevels;
+pub use advisor::{analyze, Analysis, Representation};
+pub use chunk_info::{ChunkInfo, ContainerKind};
+pub use container_view::{ContainerView, ContainerViewIter};
+#[cfg(feature = "serde")]
+pub use error::InvalidPatchError;
+pub use error::{
+    InvalidLogError, NotSortedError, QuotaExceededError, TryFromBitmapError,
+};
+#[cfg(feature = "metrics")]
+pub use metrics::{metrics, Metrics};
+pub use mutation_log::MutationLog;
+pub use ops::BitmapOps;
+pub use range_index::RangeIndex;
+pub use roaring::{
+    Roaring, RoaringBuilder, RoaringIntoIter, RoaringIter, RoaringPatch,
+};
+pub use roaring128::{Roaring128, Roaring128Iter};
+#[cfg(feature = "std")]
+pub use roaring_concurrent::ConcurrentRoaring;
+pub use roaring_core::{ChunkKey, RoaringCore};
+pub use roaring_frozen::{FrozenRoaring, FrozenRoaringIter};
+pub use roaring_lazy::{RoaringLazy, RoaringLazyIntoIter, RoaringLazyIter};
+pub use roaring_persistent::{PersistentRoaring, PersistentRoaringIter};
+pub use roaring_tree_map::{
+    RoaringTreeMap, RoaringTreeMapIntoIter, RoaringTreeMapIter,
+};
+pub use roaring_two_levels::{
+    RoaringTwoLevels, RoaringTwoLevelsIntoIter, RoaringTwoLevelsIter,
+};
+pub use set16::{Set16, Set16Iter};
 pub use stats::Stats;
 
-use chunk::Chunk;
+use chunk::{Chunk, Tuning};
 use containers::Container;
@@ -0,0 +1,59 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Number of container conversions, across every bitmap.
+static NB_CONVERSIONS: AtomicUsize = AtomicUsize::new(0);
+/// Number of chunks created, across every bitmap.
+static NB_CHUNK_ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// Counters of the bitmaps internals, shared by every bitmap of the process.
+///
+/// The counters only increase: compare two snapshots to observe a workload.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Metrics {
+    /// Number of containers converted to another representation (e.g. from
+    /// array to bitmap).
+    pub nb_conversions: usize,
+    /// Number of chunks created.
+    pub nb_chunk_allocations: usize,
+}
+
+/// Takes a snapshot of the counters.
+pub fn metrics() -> Metrics {
+    Metrics {
+        nb_conversions: NB_CONVERSIONS.load(Ordering::Relaxed),
+        nb_chunk_allocations: NB_CHUNK_ALLOCATIONS.load(Ordering::Relaxed),
+    }
+}
+
+/// Counts a container conversion.
+pub(crate) fn record_conversion() {
+    NB_CONVERSIONS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Counts a chunk creation.
+pub(crate) fn record_chunk_allocation() {
+    NB_CHUNK_ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Roaring;
+
+    #[test]
+    fn counters() {
+        let before = metrics();
+
+        // Other tests run concurrently: only lower bounds hold.
+        let mut bitmap = (0..5_000).step_by(2).collect::<Roaring>();
+        bitmap.insert(1_000_000);
+        for value in (1..5_000).step_by(2) {
+            bitmap.insert(value);
+        }
+        let after = metrics();
+
+        assert!(after.nb_chunk_allocations >= before.nb_chunk_allocations + 2);
+        assert!(after.nb_conversions > before.nb_conversions);
+    }
+}
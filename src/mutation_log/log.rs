@@ -0,0 +1,264 @@
+use crate::{InvalidLogError, Roaring};
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+
+/// Record tag of a value insertion.
+const INSERT: u8 = 0;
+/// Record tag of a value removal.
+const REMOVE: u8 = 1;
+/// Record tag of a range insertion.
+const INSERT_RANGE: u8 = 2;
+/// Record tag of a range removal.
+const REMOVE_RANGE: u8 = 3;
+
+/// Append-only log of the updates of a Roaring bitmap.
+///
+/// Each update is encoded as a record made of a tag byte followed by its
+/// operands as LEB128 varints (a range being encoded as its start and its
+/// length minus one): replaying the records on the last snapshot of a bitmap
+/// brings it up to date.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Log {
+    /// Encoded records.
+    bytes: Vec<u8>,
+}
+
+impl Log {
+    /// Creates an empty log.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Logs the insertion of a value.
+    pub fn insert(&mut self, value: u32) {
+        self.bytes.push(INSERT);
+        write_varint(&mut self.bytes, value);
+    }
+
+    /// Logs the removal of a value.
+    pub fn remove(&mut self, value: u32) {
+        self.bytes.push(REMOVE);
+        write_varint(&mut self.bytes, value);
+    }
+
+    /// Logs the insertion of a range of values.
+    ///
+    /// Empty ranges aren't logged.
+    pub fn insert_range(&mut self, range: RangeInclusive<u32>) {
+        self.push_range(INSERT_RANGE, &range);
+    }
+
+    /// Logs the removal of a range of values.
+    ///
+    /// Empty ranges aren't logged.
+    pub fn remove_range(&mut self, range: RangeInclusive<u32>) {
+        self.push_range(REMOVE_RANGE, &range);
+    }
+
+    /// Returns the encoded records, to append to durable storage.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Returns the size of the encoded records, in bytes.
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Returns true if no update is logged.
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Clears the log, once its updates are part of a snapshot.
+    pub fn clear(&mut self) {
+        self.bytes.clear();
+    }
+
+    /// Rebuilds a bitmap from the encoded records of a log.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on the first invalid or truncated record.
+    pub fn replay(bytes: &[u8]) -> Result<Roaring, InvalidLogError> {
+        let mut bitmap = Roaring::new();
+        Self::replay_into(bytes, &mut bitmap)?;
+        Ok(bitmap)
+    }
+
+    /// Applies the encoded records of a log onto a bitmap.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on the first invalid or truncated record, the
+    /// previous records being applied.
+    pub fn replay_into(
+        bytes: &[u8],
+        bitmap: &mut Roaring,
+    ) -> Result<(), InvalidLogError> {
+        let mut offset = 0;
+
+        while offset < bytes.len() {
+            let error = InvalidLogError::new(offset);
+            let mut cursor = &bytes[offset + 1..];
+            let value = read_varint(&mut cursor).ok_or(error)?;
+            match bytes[offset] {
+                INSERT => {
+                    bitmap.insert(value);
+                },
+                REMOVE => {
+                    bitmap.remove(value);
+                },
+                tag @ (INSERT_RANGE | REMOVE_RANGE) => {
+                    let end = read_varint(&mut cursor)
+                        .and_then(|length| value.checked_add(length))
+                        .ok_or(error)?;
+                    // Merged as a whole, in place to keep the bitmap's
+                    // settings.
+                    let range = Roaring::from_range(value..=end);
+                    let current = bitmap.clone();
+                    if tag == INSERT_RANGE {
+                        bitmap.assign_union(&current, &range);
+                    } else {
+                        bitmap.assign_difference(&current, &range);
+                    }
+                },
+                _ => return Err(error),
+            }
+            offset = bytes.len() - cursor.len();
+        }
+
+        Ok(())
+    }
+
+    /// Appends a range record, unless the range is empty.
+    fn push_range(&mut self, tag: u8, range: &RangeInclusive<u32>) {
+        if range.is_empty() {
+            return;
+        }
+        self.bytes.push(tag);
+        write_varint(&mut self.bytes, *range.start());
+        write_varint(&mut self.bytes, range.end() - range.start());
+    }
+}
+
+/// Appends a value encoded as a LEB128 varint.
+fn write_varint(bytes: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        // Masked to the 7 least significant bits: no truncation.
+        #[allow(clippy::cast_possible_truncation)]
+        bytes.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    // Below 0x80: no truncation.
+    #[allow(clippy::cast_possible_truncation)]
+    bytes.push(value as u8);
+}
+
+/// Reads a LEB128 varint, advancing the cursor past it.
+///
+/// Returns `None` if the varint is truncated or overflows a `u32`.
+fn read_varint(cursor: &mut &[u8]) -> Option<u32> {
+    let mut value = 0_u32;
+
+    for shift in (0..35).step_by(7) {
+        let (&byte, rest) = cursor.split_first()?;
+        *cursor = rest;
+        let bits = u32::from(byte & 0x7f);
+        if shift == 28 && bits > 0x0f {
+            return None;
+        }
+        value |= bits << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replay() {
+        let mut log = Log::new();
+        log.insert(42);
+        log.insert(u32::MAX);
+        log.insert_range(100_000..=200_000);
+        log.remove(150_000);
+        log.remove_range(180_000..=250_000);
+        log.insert_range(RangeInclusive::new(10, 9));
+        log.remove(7);
+
+        let bitmap = Log::replay(log.as_bytes()).expect("valid log");
+        let expected = [42]
+            .into_iter()
+            .chain((100_000..180_000).filter(|&value| value != 150_000))
+            .chain([u32::MAX])
+            .collect::<Vec<_>>();
+        assert_eq!(bitmap.to_vec(), expected);
+
+        // Onto an existing snapshot.
+        let mut bitmap = (0..10).collect::<Roaring>();
+        Log::replay_into(log.as_bytes(), &mut bitmap).expect("valid log");
+        assert_eq!(bitmap.cardinality(), expected.len() + 9, "7 removed");
+
+        log.clear();
+        assert!(log.is_empty());
+        assert!(Log::replay(log.as_bytes()).expect("empty").is_empty());
+    }
+
+    #[test]
+    fn encoding() {
+        let mut log = Log::new();
+        log.insert(1);
+        assert_eq!(log.len(), 2, "tag and single-byte varint");
+        log.remove(u32::MAX);
+        assert_eq!(log.len(), 8, "tag and five-byte varint");
+        log.insert_range(0..=u32::MAX);
+        assert_eq!(log.len(), 15);
+    }
+
+    #[test]
+    fn replay_large_ranges() {
+        let mut log = Log::new();
+        log.insert_range(0..=u32::MAX);
+        let bitmap = Log::replay(log.as_bytes()).expect("valid log");
+        assert_eq!(bitmap.chunks().count(), 65_536);
+        assert!(bitmap.contains(0) && bitmap.contains(u32::MAX));
+
+        log.remove_range(1..=u32::MAX - 1);
+        let bitmap = Log::replay(log.as_bytes()).expect("valid log");
+        assert_eq!(bitmap.to_vec(), vec![0, u32::MAX]);
+    }
+
+    #[test]
+    fn invalid() {
+        let mut log = Log::new();
+        log.insert(1);
+        log.insert(300);
+        let bytes = log.as_bytes();
+
+        // Torn write: the valid prefix is replayed up to the last record.
+        let mut bitmap = Roaring::new();
+        let error = Log::replay_into(&bytes[..bytes.len() - 1], &mut bitmap)
+            .expect_err("truncated");
+        assert_eq!(error.valid_up_to(), 2);
+        assert!(bitmap.contains(1));
+
+        // Unknown tag.
+        assert_eq!(Log::replay(&[7, 1]).err(), Some(InvalidLogError::new(0)));
+        // Overflowing varint.
+        assert_eq!(
+            Log::replay(&[INSERT, 0xff, 0xff, 0xff, 0xff, 0x7f]).err(),
+            Some(InvalidLogError::new(0))
+        );
+        // Overflowing range.
+        assert_eq!(
+            Log::replay(&[INSERT_RANGE, 0xff, 0xff, 0xff, 0xff, 0x0f, 1]).err(),
+            Some(InvalidLogError::new(0))
+        );
+    }
+}
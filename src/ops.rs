@@ -0,0 +1,170 @@
+use crate::{
+    Roaring, Roaring128, Roaring128Iter, RoaringIter, RoaringLazy,
+    RoaringLazyIter, RoaringTreeMap, RoaringTreeMapIter, RoaringTwoLevels,
+    RoaringTwoLevelsIter, Set16, Set16Iter,
+};
+
+/// Operations common to every bitmap type, to write code generic over the
+/// representation.
+pub trait BitmapOps: Default {
+    /// Type of the values stored in the bitmap.
+    type Value;
+    /// Iterator over the values of the bitmap, in ascending order.
+    type Iter<'a>: Iterator<Item = Self::Value>
+    where
+        Self: 'a;
+
+    /// Adds a value to the bitmap.
+    ///
+    /// If the bitmap did not have this value present, true is returned.
+    /// If the bitmap did have this value present, false is returned.
+    fn insert(&mut self, value: Self::Value) -> bool;
+    /// Removes a value from the bitmap.
+    ///
+    /// Returns whether the value was present or not.
+    fn remove(&mut self, value: Self::Value) -> bool;
+    /// Returns true if the bitmap contains the value.
+    fn contains(&self, value: Self::Value) -> bool;
+    /// Computes the bitmap cardinality.
+    fn cardinality(&self) -> usize;
+    /// Finds the smallest value in the bitmap.
+    fn min(&self) -> Option<Self::Value>;
+    /// Finds the largest value in the bitmap.
+    fn max(&self) -> Option<Self::Value>;
+    /// Gets an iterator that visits the values in the bitmap in ascending
+    /// order.
+    fn iter(&self) -> Self::Iter<'_>;
+    /// Clears the bitmap, removing all values.
+    fn clear(&mut self);
+
+    /// Returns the number of values in the bitmap.
+    ///
+    /// Alias of [`cardinality`](Self::cardinality), named after the standard
+    /// collections.
+    fn len(&self) -> usize {
+        self.cardinality()
+    }
+
+    /// Returns true if the bitmap contains no elements.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the smallest value in the bitmap.
+    ///
+    /// Alias of [`min`](Self::min), named after `BTreeSet::first`.
+    fn first(&self) -> Option<Self::Value> {
+        self.min()
+    }
+
+    /// Returns the largest value in the bitmap.
+    ///
+    /// Alias of [`max`](Self::max), named after `BTreeSet::last`.
+    fn last(&self) -> Option<Self::Value> {
+        self.max()
+    }
+
+    /// Removes every given value from the bitmap.
+    ///
+    /// Returns the number of values that were present.
+    fn remove_all<I: IntoIterator<Item = Self::Value>>(
+        &mut self,
+        values: I,
+    ) -> usize {
+        values
+            .into_iter()
+            .fold(0, |acc, value| acc + usize::from(self.remove(value)))
+    }
+}
+
+/// Implements `BitmapOps` by forwarding to the inherent methods.
+macro_rules! impl_bitmap_ops {
+    ($bitmap:ty, $value:ty, $iter:ident) => {
+        impl BitmapOps for $bitmap {
+            type Value = $value;
+            type Iter<'a> = $iter<'a>;
+
+            fn insert(&mut self, value: $value) -> bool {
+                <$bitmap>::insert(self, value)
+            }
+
+            fn remove(&mut self, value: $value) -> bool {
+                <$bitmap>::remove(self, value)
+            }
+
+            fn contains(&self, value: $value) -> bool {
+                <$bitmap>::contains(self, value)
+            }
+
+            fn cardinality(&self) -> usize {
+                <$bitmap>::cardinality(self)
+            }
+
+            fn min(&self) -> Option<$value> {
+                <$bitmap>::min(self)
+            }
+
+            fn max(&self) -> Option<$value> {
+                <$bitmap>::max(self)
+            }
+
+            fn iter(&self) -> $iter<'_> {
+                <$bitmap>::iter(self)
+            }
+
+            fn clear(&mut self) {
+                <$bitmap>::clear(self);
+            }
+        }
+    };
+}
+
+impl_bitmap_ops!(Roaring, u32, RoaringIter);
+impl_bitmap_ops!(Roaring128, u128, Roaring128Iter);
+impl_bitmap_ops!(RoaringLazy, u64, RoaringLazyIter);
+impl_bitmap_ops!(RoaringTreeMap, u64, RoaringTreeMapIter);
+impl_bitmap_ops!(RoaringTwoLevels, u64, RoaringTwoLevelsIter);
+impl_bitmap_ops!(Set16, u16, Set16Iter);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Exercises a bitmap through the trait only.
+    fn check<B: BitmapOps>(values: &[B::Value])
+    where
+        B::Value: Copy + Ord + core::fmt::Debug,
+    {
+        let mut bitmap = B::default();
+        for &value in values {
+            assert!(bitmap.insert(value));
+        }
+        assert!(!bitmap.insert(values[0]), "already exists");
+        assert!(bitmap.contains(values[1]));
+        assert_eq!(bitmap.cardinality(), values.len());
+        assert_eq!(bitmap.min(), values.iter().copied().min());
+        assert_eq!(bitmap.max(), values.iter().copied().max());
+        assert_eq!(bitmap.iter().collect::<Vec<_>>(), values);
+        assert_eq!(bitmap.len(), bitmap.cardinality());
+        assert_eq!(bitmap.first(), bitmap.min());
+        assert_eq!(bitmap.last(), bitmap.max());
+
+        assert!(bitmap.remove(values[1]));
+        assert!(!bitmap.contains(values[1]));
+        assert_eq!(bitmap.remove_all([values[0], values[1]]), 1);
+        assert!(!bitmap.contains(values[0]));
+        bitmap.clear();
+        assert_eq!(bitmap.cardinality(), 0);
+        assert!(bitmap.is_empty());
+    }
+
+    #[test]
+    fn generic() {
+        check::<Roaring>(&[1, 42, u32::MAX]);
+        check::<Roaring128>(&[1, 42, u128::MAX]);
+        check::<RoaringLazy>(&[1, 42, u64::MAX]);
+        check::<RoaringTreeMap>(&[1, 42, u64::MAX]);
+        check::<RoaringTwoLevels>(&[1, 42, u64::MAX]);
+        check::<Set16>(&[1, 42, u16::MAX]);
+    }
+}
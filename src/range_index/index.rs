@@ -0,0 +1,196 @@
+use crate::Roaring;
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+
+/// Range-encoded bitmap index, mapping rows to the value of a low-cardinality
+/// attribute.
+///
+/// The `v`-th bitmap holds the rows whose value is lower than or equal to
+/// `v`: range predicates are answered with at most one set operation, at the
+/// cost of updating up to one bitmap per distinct value on insertion.
+pub struct Index {
+    /// Rows whose value is lower than or equal to the bitmap's position, for
+    /// every value but the largest one.
+    bitmaps: Vec<Roaring>,
+    /// Every indexed row.
+    rows: Roaring,
+    /// Number of distinct values of the attribute.
+    value_count: u32,
+}
+
+impl Index {
+    /// Creates an empty index for an attribute taking values in
+    /// `0..value_count`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value_count` is zero.
+    pub fn new(value_count: u32) -> Self {
+        assert!(value_count > 0, "at least one value is required");
+
+        Self {
+            bitmaps: (1..value_count).map(|_| Roaring::new()).collect(),
+            rows: Roaring::new(),
+            value_count,
+        }
+    }
+
+    /// Returns the number of distinct values of the attribute.
+    pub fn value_count(&self) -> u32 {
+        self.value_count
+    }
+
+    /// Sets the value of a row, replacing its previous value if any.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is out of range.
+    pub fn insert(&mut self, row: u32, value: u32) {
+        assert!(value < self.value_count(), "value out of range");
+
+        self.remove(row);
+        for bitmap in &mut self.bitmaps[value as usize..] {
+            bitmap.insert(row);
+        }
+        self.rows.insert(row);
+    }
+
+    /// Removes a row from the index.
+    ///
+    /// Returns the value of the row, if it was present.
+    pub fn remove(&mut self, row: u32) -> Option<u32> {
+        let value = self.get(row)?;
+        for bitmap in &mut self.bitmaps[value as usize..] {
+            bitmap.remove(row);
+        }
+        self.rows.remove(row);
+
+        Some(value)
+    }
+
+    /// Returns the value of a row, if present.
+    ///
+    /// The bitmaps being nested, the value is found by binary search.
+    pub fn get(&self, row: u32) -> Option<u32> {
+        if !self.rows.contains(row) {
+            return None;
+        }
+        let position =
+            self.bitmaps.partition_point(|bitmap| !bitmap.contains(row));
+
+        // Position is lower than the value count: no truncation.
+        #[allow(clippy::cast_possible_truncation)]
+        Some(position as u32)
+    }
+
+    /// Returns every indexed row.
+    pub fn rows(&self) -> &Roaring {
+        &self.rows
+    }
+
+    /// Returns the rows whose value is lower than or equal to `value`.
+    ///
+    /// This is one of the index's bitmaps: no set operation is needed.
+    pub fn le(&self, value: u32) -> &Roaring {
+        self.at_most(value)
+    }
+
+    /// Returns the rows whose value is greater than or equal to `value`.
+    pub fn ge(&self, value: u32) -> Roaring {
+        self.rows
+            .difference(self.below(value).unwrap_or(&Roaring::new()))
+    }
+
+    /// Returns the rows whose value is equal to `value`.
+    pub fn eq(&self, value: u32) -> Roaring {
+        self.between(value..=value)
+    }
+
+    /// Returns the rows whose value is within the range.
+    pub fn between(&self, range: RangeInclusive<u32>) -> Roaring {
+        let (start, end) = (*range.start(), *range.end());
+        if start > end {
+            return Roaring::new();
+        }
+
+        self.at_most(end)
+            .difference(self.below(start).unwrap_or(&Roaring::new()))
+    }
+
+    /// Returns the bitmap of the rows whose value is lower than or equal to
+    /// `value`.
+    fn at_most(&self, value: u32) -> &Roaring {
+        self.bitmaps.get(value as usize).unwrap_or(&self.rows)
+    }
+
+    /// Returns the bitmap of the rows whose value is lower than `value`, or
+    /// `None` if there is no lower value.
+    fn below(&self, value: u32) -> Option<&Roaring> {
+        value.checked_sub(1).map(|below| self.at_most(below))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Attribute value of each row, for the tests.
+    fn attribute(row: u32) -> u32 {
+        (row * 7) % 10
+    }
+
+    #[test]
+    fn predicates() {
+        let mut index = Index::new(10);
+        let rows = (0..100_000).step_by(3).collect::<Vec<u32>>();
+        for &row in &rows {
+            index.insert(row, attribute(row));
+        }
+        let expected = |keep: &dyn Fn(u32) -> bool| {
+            rows.iter()
+                .copied()
+                .filter(|&row| keep(attribute(row)))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(index.value_count(), 10);
+        assert_eq!(index.rows().cardinality(), rows.len());
+        for value in 0..=10 {
+            assert!(index.le(value).iter().eq(expected(&|v| v <= value)));
+            assert!(index.ge(value).iter().eq(expected(&|v| v >= value)));
+            assert!(index.eq(value).iter().eq(expected(&|v| v == value)));
+        }
+        assert!(index
+            .between(2..=5)
+            .iter()
+            .eq(expected(&|v| (2..=5).contains(&v))));
+        #[allow(clippy::reversed_empty_ranges)]
+        let empty = index.between(5..=2);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn update() {
+        let mut index = Index::new(4);
+
+        index.insert(42, 1);
+        assert_eq!(index.get(42), Some(1));
+        index.insert(42, 3);
+        assert_eq!(index.get(42), Some(3));
+        assert!(index.eq(1).is_empty(), "previous value is replaced");
+        index.insert(7, 0);
+        assert_eq!(index.get(7), Some(0));
+
+        assert_eq!(index.remove(42), Some(3));
+        assert_eq!(index.remove(42), None);
+        assert_eq!(index.get(42), None);
+        assert!(index.ge(1).is_empty());
+        assert_eq!(index.rows().cardinality(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "value out of range")]
+    fn out_of_range() {
+        Index::new(4).insert(1, 4);
+    }
+}
@@ -1,294 +1,1479 @@
-use super::{Entry, Header, Iter};
-use crate::{Chunk, Container, Stats};
-use std::mem;
+use crate::roaring_core::RoaringCore;
 
 /// Compressed bitmap for 32-bit integers.
-#[derive(Default)]
-pub struct Bitmap {
-    /// Bitmap chunks, indexed by the 16 most significant bits of the integer.
-    chunks: Vec<Chunk<Header>>,
-}
+///
+/// Chunks are indexed by the 16 most significant bits of the integer.
+pub type Bitmap = RoaringCore<u16>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ContainerKind, NotSortedError};
+
+    #[test]
+    fn insertion_deletion() {
+        let mut bitmap = Bitmap::new();
+        assert_eq!(bitmap.cardinality(), 0);
+        assert_eq!(bitmap.min(), None);
+        assert_eq!(bitmap.max(), None);
+        // No allocation for empty bitmap.
+        assert_eq!(bitmap.chunks().count(), 0);
+
+        // Chunks are created as needed.
+        bitmap.insert(1_538_809_352);
+        bitmap.insert(1_538_809_350);
+        assert_eq!(bitmap.cardinality(), 2);
+        assert_eq!(bitmap.chunks().count(), 1);
+        bitmap.insert(370_099_062);
+        assert_eq!(bitmap.cardinality(), 3);
+        assert_eq!(bitmap.chunks().count(), 2);
+
+        // Operation works accross chunks.
+        assert_eq!(bitmap.min(), Some(370_099_062));
+        assert_eq!(bitmap.max(), Some(1_538_809_352));
+
+        // Chunks are deleted when empty.
+        bitmap.remove(370_099_062);
+        assert_eq!(bitmap.cardinality(), 2);
+        assert_eq!(bitmap.chunks().count(), 1);
+    }
+
+    #[test]
+    fn contains() {
+        let mut bitmap = Bitmap::new();
+        assert!(!bitmap.contains(42));
+
+        bitmap.insert(42);
+        assert!(bitmap.contains(42));
+
+        bitmap.remove(42);
+        assert!(!bitmap.contains(42));
+    }
+
+    #[test]
+    fn contains_many() {
+        let bitmap = (0..100_000).step_by(3).collect::<Bitmap>();
+
+        assert!(bitmap.contains_all(&[]));
+        assert!(bitmap.contains_all(&[0, 3, 99_999, 65_535]));
+        assert!(bitmap.contains_all(&[99_999, 3, 3, 0]));
+        assert!(!bitmap.contains_all(&[0, 3, 4]));
+        assert!(!bitmap.contains_all(&[0, 300_000]));
+
+        assert!(!bitmap.contains_any(&[]));
+        assert!(bitmap.contains_any(&[1, 2, 65_535]));
+        assert!(bitmap.contains_any(&[300_000, 4, 99_999]));
+        assert!(!bitmap.contains_any(&[1, 2, 300_000, 65_536]));
+    }
+
+    #[test]
+    fn already_exists() {
+        let mut bitmap = Bitmap::new();
+
+        assert!(bitmap.insert(42), "new entry");
+        assert!(!bitmap.insert(42), "already exists");
+    }
+
+    #[test]
+    fn missing() {
+        let mut bitmap = Bitmap::new();
 
-impl Bitmap {
-    /// Create an empty bitmap.
-    pub fn new() -> Self {
-        Self::default()
-    }
-
-    /// Adds a value to the bitmap.
-    ///
-    /// If the bitmap did not have this value present, true is returned.
-    /// If the bitmap did have this value present, false is returned.
-    pub fn insert(&mut self, value: u32) -> bool {
-        let entry = Entry::from(value);
-
-        match self.chunks.binary_search_by_key(&entry.hi, Chunk::key) {
-            Ok(index) => self.chunks[index].insert(entry.lo),
-            Err(index) => {
-                let header = Header::new(entry.hi);
-                self.chunks.insert(index, Chunk::new(header, entry.lo));
-                true
-            },
+        bitmap.insert(11);
+
+        assert!(bitmap.remove(11), "found");
+        assert!(!bitmap.remove(11), "missing entry");
+    }
+
+    #[test]
+    fn is_empty() {
+        let mut bitmap = Bitmap::new();
+        assert!(bitmap.is_empty());
+
+        bitmap.insert(1_538_809_352);
+        bitmap.insert(1_538_809_350);
+        bitmap.insert(370_099_062);
+        assert!(!bitmap.is_empty());
+
+        bitmap.clear();
+        assert!(bitmap.is_empty());
+    }
+
+    #[test]
+    fn iterator_sparse() {
+        let input = (0..10_000).step_by(10).collect::<Vec<_>>();
+        let bitmap = input.iter().copied().collect::<Bitmap>();
+
+        let stats = bitmap.stats();
+        assert_eq!(stats.nb_bitmap_containers, 0, "sparse bitmap");
+
+        let values = (&bitmap).into_iter().collect::<Vec<_>>();
+        assert_eq!(values, input);
+    }
+
+    #[test]
+    fn iterator_dense() {
+        let input = (0..10_000).step_by(2).collect::<Vec<_>>();
+        let bitmap = input.iter().copied().collect::<Bitmap>();
+
+        let stats = bitmap.stats();
+        assert_eq!(stats.nb_array_containers, 0, "dense bitmap");
+
+        let values = (&bitmap).into_iter().collect::<Vec<_>>();
+        assert_eq!(values, input);
+    }
+
+    #[test]
+    fn iterator_runs() {
+        let input = (0..100_000).collect::<Vec<_>>();
+        let bitmap = input.iter().copied().collect::<Bitmap>();
+
+        let stats = bitmap.stats();
+        assert_eq!(stats.nb_full_containers, 1, "saturated chunk");
+        assert_eq!(stats.nb_values_full_containers, 65_536);
+        assert_eq!(stats.nb_run_containers, 1, "consecutive values");
+        assert_eq!(stats.nb_values_run_containers, 34_464);
+
+        let values = (&bitmap).into_iter().collect::<Vec<_>>();
+        assert_eq!(values, input);
+    }
+
+    #[test]
+    fn iterator_next_many() {
+        // Sparse, dense and run chunks.
+        let input = (0..100)
+            .chain((1 << 16..2 << 16).step_by(3))
+            .chain(5 << 16..(5 << 16) + 1_000)
+            .collect::<Vec<u32>>();
+        let mut bitmap = input.iter().copied().collect::<Bitmap>();
+        bitmap.run_optimize();
+
+        let mut iter = bitmap.iter();
+        let mut buf = [0; 1_000];
+        let mut values = Vec::new();
+        assert_eq!(iter.next(), Some(0));
+        loop {
+            let count = iter.next_many(&mut buf);
+            values.extend_from_slice(&buf[..count]);
+            assert_eq!(iter.size_hint().0, input.len() - values.len() - 1);
+            if count < buf.len() {
+                break;
+            }
         }
+
+        assert_eq!(values, input[1..]);
+        assert_eq!(iter.next_many(&mut buf), 0);
+        assert_eq!(iter.next(), None);
     }
 
-    /// Removes a value from the bitmap.
-    ///
-    /// Returns whether the value was present or not.
-    pub fn remove(&mut self, value: u32) -> bool {
-        let entry = Entry::from(value);
+    #[test]
+    fn write_dense_into() {
+        // Array, bitmap, run and full chunks.
+        let mut bitmap = [1, 42]
+            .into_iter()
+            .chain((1 << 16..2 << 16).step_by(3))
+            .chain((2 << 16) + 10..(2 << 16) + 5_000)
+            .chain(3 << 16..4 << 16)
+            .chain([u32::MAX - 1, u32::MAX])
+            .collect::<Bitmap>();
+        bitmap.run_optimize();
 
-        self.chunks
-            .binary_search_by_key(&entry.hi, Chunk::key)
-            .map(|index| {
-                let old_cardinality = self.chunks[index].cardinality();
-                let removed = self.chunks[index].remove(entry.lo);
+        for (start, end) in [(0, 100), (1, 1), (40, 70_000), (65_600, 262_200)]
+            .into_iter()
+            .chain([(131_075, 270_000), (u32::MAX - 130, u32::MAX)])
+        {
+            let len = (end - start) as usize / 64 + 1;
+            let mut words = vec![u64::MAX; len + 1];
+            bitmap.write_dense_into(start..=end, &mut words);
 
-                // Chunk is now empty (last element removed), delete it.
-                if old_cardinality == 1 && removed {
-                    self.chunks.remove(index);
-                }
-                removed
-            })
-            .unwrap_or(false)
+            let mut expected = vec![0; len + 1];
+            for value in
+                bitmap.iter().filter(|value| (start..=end).contains(value))
+            {
+                let bit = (value - start) as usize;
+                expected[bit / 64] |= 1 << (bit % 64);
+            }
+            // Words past the range are untouched.
+            expected[len] = u64::MAX;
+            assert_eq!(words, expected, "{start}..={end}");
+        }
+
+        // Empty range.
+        let mut words = [42];
+        bitmap.write_dense_into(10..10, &mut words);
+        assert_eq!(words, [42]);
     }
 
-    /// Returns true if the bitmap contains the value.
-    pub fn contains(&self, value: u32) -> bool {
-        let entry = Entry::from(value);
+    #[test]
+    fn to_vec() {
+        // Sparse, dense, run and full chunks.
+        let input = (0..100)
+            .chain((1 << 16..2 << 16).step_by(3))
+            .chain((5 << 16) + 7..(5 << 16) + 1_000)
+            .chain(6 << 16..7 << 16)
+            .collect::<Vec<u32>>();
+        let mut bitmap = input.iter().copied().collect::<Bitmap>();
+        bitmap.run_optimize();
 
-        self.chunks
-            .binary_search_by_key(&entry.hi, Chunk::key)
-            .map(|index| self.chunks[index].contains(entry.lo))
-            .unwrap_or(false)
+        let values = bitmap.to_vec();
+        assert_eq!(values, input);
+        assert_eq!(values.capacity(), input.len());
+        assert!(Bitmap::new().to_vec().is_empty());
     }
 
-    /// Computes the bitmap cardinality.
-    pub fn cardinality(&self) -> usize {
-        self.chunks
-            .iter()
-            .fold(0, |acc, chunk| acc + chunk.cardinality())
+    #[test]
+    fn run_compression() {
+        let input = (0..1_000).chain(100_000..100_010).collect::<Vec<_>>();
+        let mut bitmap = input.iter().copied().collect::<Bitmap>();
+        assert_eq!(bitmap.stats().nb_run_containers, 0);
+
+        assert!(bitmap.run_optimize());
+        let stats = bitmap.stats();
+        assert_eq!(stats.nb_run_containers, 2);
+        assert_eq!(bitmap.iter().collect::<Vec<_>>(), input);
+
+        assert!(bitmap.remove_run_compression());
+        assert!(!bitmap.remove_run_compression(), "no more runs");
+        let stats = bitmap.stats();
+        assert_eq!(stats.nb_run_containers, 0);
+        assert_eq!(bitmap.iter().collect::<Vec<_>>(), input);
     }
 
-    /// Finds the smallest value in the bitmap.
-    pub fn min(&self) -> Option<u32> {
-        self.chunks
-            .iter()
-            .filter_map(|chunk| {
-                chunk
-                    .min()
-                    .map(|min| Entry::from_parts(chunk.key(), min).into())
-            })
-            .min()
+    #[test]
+    fn set_operations() {
+        let lhs = (0..100_000).step_by(3).collect::<Bitmap>();
+        let rhs = (50_000..200_000).step_by(5).collect::<Bitmap>();
+        let in_lhs = |value: &u32| value.is_multiple_of(3) && *value < 100_000;
+        let in_rhs = |value: &u32| {
+            value.is_multiple_of(5) && (50_000..200_000).contains(value)
+        };
+        let expected = |keep: &dyn Fn(&u32) -> bool| {
+            (0..200_000).filter(|value| keep(value)).collect::<Vec<_>>()
+        };
+
+        let union = lhs.union(&rhs);
+        assert_eq!(
+            union.iter().collect::<Vec<_>>(),
+            expected(&|value| in_lhs(value) || in_rhs(value))
+        );
+        assert_eq!(union.cardinality(), union.iter().count());
+
+        let intersection = lhs.intersection(&rhs);
+        assert_eq!(
+            intersection.iter().collect::<Vec<_>>(),
+            expected(&|value| in_lhs(value) && in_rhs(value))
+        );
+
+        let difference = lhs.difference(&rhs);
+        assert_eq!(
+            difference.iter().collect::<Vec<_>>(),
+            expected(&|value| in_lhs(value) && !in_rhs(value))
+        );
+
+        let symmetric_difference = lhs.symmetric_difference(&rhs);
+        assert_eq!(
+            symmetric_difference.iter().collect::<Vec<_>>(),
+            expected(&|value| in_lhs(value) != in_rhs(value))
+        );
+    }
+
+    #[test]
+    fn intersection_with_limit() {
+        let mut lhs = (0..300_000).step_by(3).collect::<Bitmap>();
+        lhs.run_optimize();
+        let rhs = (100_000..500_000)
+            .step_by(2)
+            .chain(800_000..800_010)
+            .collect::<Bitmap>();
+        let shared = lhs.intersection_iter(&rhs).collect::<Vec<_>>();
+
+        for limit in [0, 1, 10, 33_332, 33_333, 33_334, 100_000, usize::MAX] {
+            let (values, more) = lhs.intersection_with_limit(&rhs, limit);
+            assert_eq!(values, shared[..limit.min(shared.len())]);
+            assert_eq!(more, limit < shared.len(), "limit {limit}");
+        }
+        assert_eq!(
+            lhs.intersection_with_limit(&Bitmap::new(), 10),
+            (Vec::new(), false)
+        );
+    }
+
+    #[test]
+    fn intersection_len_many() {
+        let mut query = (0..100_000)
+            .step_by(3)
+            .chain(200_000..300_000)
+            .collect::<Bitmap>();
+        query.run_optimize();
+        let others = [
+            (50_000..250_000).step_by(5).collect::<Bitmap>(),
+            (0..400_000).collect::<Bitmap>(),
+            [1, 3, 299_999, 500_000].into_iter().collect::<Bitmap>(),
+            Bitmap::new(),
+        ];
+        let refs = others.iter().collect::<Vec<_>>();
+
+        assert_eq!(
+            query.intersection_len_many(&refs),
+            others
+                .iter()
+                .map(|other| query.intersection(other).cardinality())
+                .collect::<Vec<_>>()
+        );
+        assert!(query.intersection_len_many(&[]).is_empty());
+    }
+
+    #[test]
+    fn diff_apply_patch() {
+        let build = |skip: u32, upper: u32, extra: &[u32]| {
+            (0..100_000)
+                .step_by(3)
+                .filter(|&value| value != skip)
+                .chain(upper..300_000)
+                .chain(extra.iter().copied())
+                .collect::<Bitmap>()
+        };
+        let old = build(0, 200_000, &[]);
+        let new = build(3, 270_000, &[1_000_000]);
+
+        let patch = Bitmap::diff(&old, &new);
+        assert_eq!(patch.len(), 4, "chunks 0, 3, 4 and 15 changed");
+
+        let mut replica = build(0, 200_000, &[]);
+        replica.apply_patch(&patch);
+        assert_eq!(replica.to_vec(), new.to_vec());
+        assert_eq!(replica.cardinality(), new.cardinality());
+        replica.apply_patch(&patch);
+        assert_eq!(replica.cardinality(), new.cardinality(), "idempotent");
+
+        // Removed chunks.
+        replica.apply_patch(&Bitmap::diff(&new, &Bitmap::new()));
+        assert!(replica.is_empty());
+
+        assert!(Bitmap::diff(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn on_conversion() {
+        use std::sync::{Arc, Mutex};
+
+        let conversions = Arc::new(Mutex::new(Vec::new()));
+        let mut bitmap =
+            (0..8_192).step_by(2).chain([65_536]).collect::<Bitmap>();
+        let log = Arc::clone(&conversions);
+        bitmap.on_conversion(move |key, from, to| {
+            log.lock().expect("not poisoned").push((key, from, to));
+        });
+
+        bitmap.insert(1);
+        bitmap.insert(3);
+        bitmap.remove(1);
+        bitmap.remove(3);
+        bitmap.extend(65_537..70_000);
+        bitmap.run_optimize();
+
+        assert_eq!(
+            *conversions.lock().expect("not poisoned"),
+            vec![
+                (0, ContainerKind::Array, ContainerKind::Bitmap),
+                (0, ContainerKind::Bitmap, ContainerKind::Array),
+                (1, ContainerKind::Array, ContainerKind::Run),
+            ]
+        );
     }
 
-    /// Finds the largest value in the bitmap.
-    pub fn max(&self) -> Option<u32> {
-        self.chunks
+    #[test]
+    fn soft_mem_limit() {
+        use std::sync::{Arc, Mutex};
+
+        let compactions = Arc::new(Mutex::new(Vec::new()));
+        let mut bitmap = Bitmap::new();
+        bitmap.set_soft_mem_limit(Some(16_384));
+        let log = Arc::clone(&compactions);
+        bitmap.on_compaction(move |before, after| {
+            log.lock().expect("not poisoned").push((before, after));
+        });
+
+        // Dense values compact into runs.
+        for value in 0..300_000 {
+            bitmap.insert(value);
+        }
+        assert_eq!(bitmap.soft_mem_limit(), Some(16_384));
+        assert_eq!(bitmap.cardinality(), 300_000);
+        assert!(bitmap.iter().eq(0..300_000));
+        assert!(bitmap.mem_size() <= 16_384);
+        let count = compactions.lock().expect("not poisoned").len();
+        assert!(count > 0);
+        assert!(compactions
+            .lock()
+            .expect("not poisoned")
             .iter()
-            .filter_map(|chunk| {
-                chunk
-                    .max()
-                    .map(|max| Entry::from_parts(chunk.key(), max).into())
-            })
-            .max()
+            .all(|&(before, after)| before > 16_384 && after < before));
+
+        // Sparse values don't: compactions back off as the bitmap grows.
+        bitmap.clear();
+        for value in (0..400_000).step_by(2) {
+            bitmap.insert(value);
+        }
+        assert_eq!(bitmap.cardinality(), 200_000);
+        assert!(bitmap.mem_size() > 16_384);
+        let sparse = compactions.lock().expect("not poisoned").len() - count;
+        assert!((1..20).contains(&sparse));
+
+        // Without a limit, the bitmap grows freely.
+        bitmap.set_soft_mem_limit(None);
+        bitmap.extend(400_000..800_000);
+        assert_eq!(
+            compactions.lock().expect("not poisoned").len(),
+            count + sparse
+        );
+    }
+
+    #[test]
+    fn send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        // With or without `std`.
+        assert_send_sync::<Bitmap>();
+    }
+
+    #[test]
+    fn unwind_safe() {
+        use std::panic::{RefUnwindSafe, UnwindSafe};
+
+        fn assert_unwind_safe<T: UnwindSafe + RefUnwindSafe>() {}
+
+        // Whatever the registered callbacks.
+        assert_unwind_safe::<Bitmap>();
+    }
+
+    #[test]
+    fn clone_from() {
+        let snapshot = (0..300_000).step_by(3).collect::<Bitmap>();
+        let mut bitmap = snapshot.clone();
+        assert_eq!(bitmap.to_vec(), snapshot.to_vec());
+
+        // Fewer, then more, chunks than the destination.
+        let small = (0..70_000).step_by(2).collect::<Bitmap>();
+        bitmap.clone_from(&small);
+        assert_eq!(bitmap.to_vec(), small.to_vec());
+        assert_eq!(bitmap.cardinality(), small.cardinality());
+        bitmap.clone_from(&snapshot);
+        assert_eq!(bitmap.to_vec(), snapshot.to_vec());
+        assert_eq!(bitmap.cardinality(), snapshot.cardinality());
+
+        // Settings are kept when overwriting.
+        let mut tracked = Bitmap::new();
+        tracked.track_dirty_chunks();
+        tracked.overwrite_from(&small);
+        assert_eq!(tracked.dirty_keys().collect::<Vec<_>>(), vec![0, 1]);
+        tracked.clone_from(&snapshot);
+        assert_eq!(tracked.dirty_keys().count(), 0, "not tracked");
+        assert!(tracked.contains(299_997));
+    }
+
+    #[test]
+    fn dirty_chunks() {
+        let build = || (0..300_000).step_by(7).collect::<Bitmap>();
+        let mut bitmap = build();
+        bitmap.insert(1);
+        assert_eq!(bitmap.dirty_keys().count(), 0, "not tracked");
+
+        bitmap.track_dirty_chunks();
+        bitmap.insert(70_000);
+        bitmap.insert(70_001);
+        bitmap.insert(1_000_000);
+        bitmap.remove(0);
+        bitmap.remove(2);
+        bitmap.run_optimize();
+        assert_eq!(bitmap.dirty_keys().collect::<Vec<_>>(), vec![0, 1, 15]);
+
+        // The dirty chunks bring a persisted copy up to date.
+        let mut persisted = build();
+        persisted.insert(1);
+        persisted.apply_patch(&bitmap.dirty_patch());
+        assert_eq!(persisted.to_vec(), bitmap.to_vec());
+
+        bitmap.mark_clean();
+        assert!(bitmap.dirty_patch().is_empty());
+        bitmap.clear();
+        assert_eq!(bitmap.dirty_keys().count(), 6, "every chunk removed");
+        persisted.apply_patch(&bitmap.dirty_patch());
+        assert!(persisted.is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn patch_serialization() {
+        let old = (0..100).collect::<Bitmap>();
+        let new = (50..200_000).step_by(7).collect::<Bitmap>();
+        let patch = Bitmap::diff(&old, &new);
+
+        let json = serde_json::to_string(&patch).expect("serializable");
+        let patch = serde_json::from_str(&json).expect("deserializable");
+        let mut replica = old;
+        replica.apply_patch(&patch);
+        assert_eq!(replica.to_vec(), new.to_vec());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn malformed_patch() {
+        use crate::RoaringPatch;
+
+        let parse = |changes: &str| {
+            let json = format!(r#"{{"changes":[{changes}]}}"#);
+            serde_json::from_str::<RoaringPatch>(&json)
+        };
+        let patch =
+            parse(r#"{"key":0,"runs":[[1,3],[10,10]]},{"key":2,"runs":[]}"#)
+                .expect("valid patch");
+        let mut bitmap = (0..100).collect::<Bitmap>();
+        bitmap.apply_patch(&patch);
+        assert_eq!(bitmap.to_vec(), vec![1, 2, 3, 10]);
+
+        for changes in [
+            // Reversed run.
+            r#"{"key":0,"runs":[[10,5]]}"#,
+            // Overlapping runs.
+            r#"{"key":0,"runs":[[0,65535],[10,20]]}"#,
+            // Adjacent runs.
+            r#"{"key":0,"runs":[[0,9],[10,20]]}"#,
+            // Unsorted runs.
+            r#"{"key":0,"runs":[[30,40],[10,20]]}"#,
+            // Unsorted and duplicate keys.
+            r#"{"key":2,"runs":[]},{"key":1,"runs":[]}"#,
+            r#"{"key":1,"runs":[[1,1]]},{"key":1,"runs":[[5,5]]}"#,
+        ] {
+            let error = parse(changes).expect_err("malformed patch");
+            assert!(error.to_string().contains("malformed runs"), "{error}");
+        }
+    }
+
+    #[test]
+    fn approx_intersection_len() {
+        let lhs = (0..10_000_000).step_by(3).collect::<Bitmap>();
+        let rhs = (0..10_000_000).step_by(5).collect::<Bitmap>();
+        let exact = lhs.intersection(&rhs).cardinality();
+
+        assert_eq!(lhs.approx_intersection_len(&rhs, 1.0), exact);
+        for precision in [0.0, 0.01, 0.1, 0.5] {
+            let estimate = lhs.approx_intersection_len(&rhs, precision);
+            assert!(estimate.abs_diff(exact) < exact / 10, "{precision}");
+        }
+
+        // Overlap restricted to a few chunks.
+        let rhs = (1_000_000..1_200_000).collect::<Bitmap>();
+        let exact = lhs.intersection(&rhs).cardinality();
+        let estimate = lhs.approx_intersection_len(&rhs, 0.5);
+        assert!(estimate.abs_diff(exact) < exact / 2);
+
+        assert_eq!(Bitmap::new().approx_intersection_len(&rhs, 0.5), 0);
+        assert_eq!(lhs.approx_intersection_len(&Bitmap::new(), 0.5), 0);
     }
 
-    /// Clears the bitmap, removing all values.
-    pub fn clear(&mut self) {
-        self.chunks.clear();
+    #[test]
+    fn nth() {
+        // Array, bitmap, run and full chunks.
+        let mut bitmap = (0..1_000)
+            .chain((65_536..131_072).step_by(3))
+            .chain((131_072..196_608).filter(|value| value % 1_000 < 500))
+            .chain(196_608..262_144)
+            .chain([300_000])
+            .collect::<Bitmap>();
+        bitmap.run_optimize();
+        let values = bitmap.to_vec();
+
+        for n in [0, 1, 999, 1_000, 5_000, 22_845, 30_000, 60_000, 120_000] {
+            let mut iter = bitmap.iter();
+            assert_eq!(iter.nth(n), values.get(n).copied(), "{n}");
+            assert_eq!(iter.size_hint().0, values.len().saturating_sub(n + 1));
+            assert_eq!(iter.next(), values.get(n + 1).copied());
+        }
+        let mut iter = bitmap.iter();
+        assert_eq!(iter.nth(values.len()), None);
+        assert_eq!(iter.size_hint(), (0, Some(0)));
+        assert_eq!(iter.next(), None);
+
+        // Successive jumps.
+        let mut iter = bitmap.iter();
+        for (n, expected) in values.iter().step_by(777).enumerate() {
+            let skip = if n == 0 { 0 } else { 776 };
+            assert_eq!(iter.nth(skip), Some(*expected));
+        }
+
+        let page = bitmap.iter().skip(100_000).take(100).collect::<Vec<_>>();
+        assert_eq!(page, values[100_000..100_100]);
     }
 
-    /// Returns true if the bitmap contains no elements.
-    pub fn is_empty(&self) -> bool {
-        self.chunks.is_empty()
+    #[test]
+    fn double_ended() {
+        // Array, bitmap, run and full chunks.
+        let mut bitmap = [1, 42, 1 << 16]
+            .into_iter()
+            .chain((2 << 16..3 << 16).step_by(2))
+            .chain((3 << 16) + 10..(3 << 16) + 5_000)
+            .chain(4 << 16..5 << 16)
+            .chain([u32::MAX])
+            .collect::<Bitmap>();
+        bitmap.run_optimize();
+        let values = bitmap.to_vec();
+
+        assert!(bitmap.iter().rev().eq(values.iter().rev().copied()));
+
+        // Both ends meet without missing or repeating a value.
+        let mut iter = bitmap.iter();
+        let (mut head, mut tail) = (Vec::new(), Vec::new());
+        while let Some(value) = iter.next_back() {
+            tail.push(value);
+            head.extend(iter.next());
+            let len = values.len() - head.len() - tail.len();
+            assert_eq!(iter.size_hint().0, len);
+        }
+        head.extend(tail.iter().rev());
+        assert_eq!(head, values);
+
+        // Seeking from the front stops at the values taken from the back.
+        let mut iter = bitmap.iter();
+        assert_eq!(iter.next_back(), Some(u32::MAX));
+        assert_eq!(iter.nth_back(65_535), Some(4 << 16));
+        iter.advance_to((3 << 16) + 4_990);
+        assert_eq!(iter.size_hint().0, 10);
+        assert!(iter.eq((3 << 16) + 4_990..(3 << 16) + 5_000));
+        let mut iter = bitmap.iter();
+        assert_eq!(iter.nth_back(1), Some((5 << 16) - 1));
+        iter.advance_to(5 << 16);
+        assert_eq!(iter.size_hint(), (0, Some(0)));
+        assert_eq!(iter.next(), None);
+        let mut iter = bitmap.iter();
+        assert_eq!(iter.nth_back(values.len() - 2), Some(42));
+        assert_eq!(iter.nth(1), None);
+
+        // Owning iteration.
+        let mut iter = bitmap.clone().into_iter();
+        assert_eq!(iter.size_hint(), (values.len(), Some(values.len())));
+        assert_eq!(iter.next_back(), Some(u32::MAX));
+        assert_eq!(iter.next(), Some(1));
+        assert!(iter
+            .rev()
+            .eq(values[1..values.len() - 1].iter().rev().copied()));
+        assert!(bitmap.into_iter().eq(values));
     }
 
-    /// Gets an iterator that visits the values in the bitmap in ascending
-    /// order.
-    pub fn iter(&self) -> Iter<'_> {
-        Iter::new(self.chunks.iter())
+    #[test]
+    fn assign_set_operations() {
+        let lhs = (0..100_000)
+            .step_by(3)
+            .chain(200_000..300_000)
+            .collect::<Bitmap>();
+        let rhs = (50_000..250_000).step_by(5).collect::<Bitmap>();
+        let mut dst = (0..500_000).step_by(2).collect::<Bitmap>();
+
+        dst.assign_union(&lhs, &rhs);
+        assert!(dst.iter().eq(lhs.union(&rhs).iter()));
+        assert_eq!(dst.cardinality(), lhs.union(&rhs).cardinality());
+
+        dst.assign_intersection(&lhs, &rhs);
+        assert!(dst.iter().eq(lhs.intersection(&rhs).iter()));
+        assert_eq!(dst.cardinality(), lhs.intersection(&rhs).cardinality());
+
+        dst.assign_difference(&lhs, &rhs);
+        assert!(dst.iter().eq(lhs.difference(&rhs).iter()));
+        dst.assign_difference(&rhs, &lhs);
+        assert!(dst.iter().eq(rhs.difference(&lhs).iter()));
+
+        dst.assign_symmetric_difference(&lhs, &rhs);
+        assert!(dst.iter().eq(lhs.symmetric_difference(&rhs).iter()));
+        assert_eq!(
+            dst.cardinality(),
+            lhs.symmetric_difference(&rhs).cardinality()
+        );
+
+        dst.assign_intersection(&lhs, &Bitmap::new());
+        assert!(dst.is_empty());
     }
 
-    /// Returns the approximate in-memory size of the bitmap, in bytes.
-    pub fn mem_size(&self) -> usize {
-        mem::size_of_val(self)
-            + self
-                .chunks
+    #[test]
+    fn advance_to() {
+        // Array, bitmap, run and full chunks.
+        let mut bitmap = [1, 42, 1 << 16]
+            .into_iter()
+            .chain((2 << 16..3 << 16).step_by(2))
+            .chain((3 << 16) + 10..(3 << 16) + 5_000)
+            .chain(4 << 16..5 << 16)
+            .chain([u32::MAX])
+            .collect::<Bitmap>();
+        bitmap.run_optimize();
+
+        for bound in [0, 2, 42, 43, 1 << 16, (2 << 16) + 1, (2 << 16) + 999]
+            .into_iter()
+            .chain([(3 << 16) + 500, (4 << 16) + 7, 5 << 16, u32::MAX])
+        {
+            let mut iter = bitmap.iter();
+            iter.advance_to(bound);
+            let expected = bitmap
                 .iter()
-                .fold(0, |acc, chunk| acc + chunk.mem_size())
+                .filter(|&value| value >= bound)
+                .collect::<Vec<_>>();
+
+            assert_eq!(iter.size_hint().0, expected.len());
+            assert!(iter.eq(expected), "advance to {bound}");
+        }
+
+        // Seeks forward only.
+        let mut iter = bitmap.iter();
+        iter.advance_to(2 << 16);
+        iter.advance_to(42);
+        assert_eq!(iter.next(), Some(2 << 16));
+        iter.advance_to((2 << 16) + 10);
+        iter.advance_to((2 << 16) + 10);
+        assert_eq!(iter.next(), Some((2 << 16) + 10));
+        iter.advance_to(u32::MAX);
+        assert_eq!(iter.next(), Some(u32::MAX));
+        iter.advance_to(0);
+        assert_eq!(iter.next(), None);
+
+        let empty = Bitmap::new();
+        let mut iter = empty.iter();
+        iter.advance_to(42);
+        assert_eq!(iter.next(), None);
     }
 
-    /// Returns detailed statistics about the composition of the bitmap.
-    pub fn stats(&self) -> Stats<u32> {
-        let mut stats = Stats {
-            nb_containers: self.chunks.len(),
-            nb_array_containers: 0,
-            nb_bitmap_containers: 0,
+    #[test]
+    fn density_profile() {
+        let bitmap = (0..10)
+            .chain((3 << 16..4 << 16).step_by(2))
+            .chain([u32::MAX])
+            .collect::<Bitmap>();
+
+        assert_eq!(
+            bitmap.density_profile().collect::<Vec<_>>(),
+            vec![(0, 10), (3, 32_768), (u16::MAX, 1)]
+        );
+        assert_eq!(Bitmap::new().density_profile().count(), 0);
+    }
 
-            nb_values: self.cardinality(),
-            nb_values_array_containers: 0,
-            nb_values_bitmap_containers: 0,
+    #[test]
+    fn add_offset() {
+        // Array, bitmap, run and full chunks, at both ends of the range.
+        let values = [1, 42]
+            .into_iter()
+            .chain((1 << 16..2 << 16).step_by(2))
+            .chain((2 << 16) + 10..(2 << 16) + 5_000)
+            .chain(3 << 16..4 << 16)
+            .chain(u32::MAX - 100..=u32::MAX)
+            .collect::<Vec<u32>>();
 
-            nb_bytes: self.mem_size(),
-            nb_bytes_array_containers: 0,
-            nb_bytes_bitmap_containers: 0,
+        for delta in [0, 1, -1, 7, 100, -100, 1 << 16, -(3 << 16), 70_000]
+            .into_iter()
+            .chain([-70_000, 1 << 31, i64::from(u32::MAX), i64::MIN])
+        {
+            let mut bitmap = values.iter().copied().collect::<Bitmap>();
+            bitmap.run_optimize();
+            bitmap.add_offset(delta);
+            let expected = values
+                .iter()
+                .filter_map(|&value| {
+                    u32::try_from(i64::from(value).checked_add(delta)?).ok()
+                })
+                .collect::<Vec<_>>();
 
-            min_value: self.min(),
-            max_value: self.max(),
+            assert_eq!(bitmap.cardinality(), expected.len(), "delta {delta}");
+            assert_eq!(bitmap.iter().collect::<Vec<_>>(), expected);
+        }
+
+        // Runs are kept compressed.
+        let mut bitmap = (10..100_000).collect::<Bitmap>();
+        bitmap.run_optimize();
+        bitmap.add_offset(12_345);
+        assert!(bitmap
+            .chunks()
+            .all(|info| info.kind != ContainerKind::Bitmap));
+    }
+
+    #[test]
+    fn count_runs() {
+        let mut bitmap = [1, 42]
+            .into_iter()
+            .chain((1 << 16..2 << 16).step_by(2))
+            .chain((2 << 16) + 10..(4 << 16) + 5_000)
+            .collect::<Bitmap>();
+        let kinds = bitmap.chunks().map(|info| info.kind).collect::<Vec<_>>();
+
+        // 2 + 32_768 + one per chunk spanned by the last run.
+        assert_eq!(bitmap.count_runs(), 32_773);
+        assert_eq!(bitmap.iter_ranges().count(), 32_771);
+        let views = bitmap.container_views().collect::<Vec<_>>();
+        assert_eq!(views[1].1.count_runs(), 32_768);
+        assert_eq!(views[3].1.count_runs(), 1);
+
+        // Nothing is converted.
+        assert_eq!(
+            bitmap.chunks().map(|info| info.kind).collect::<Vec<_>>(),
+            kinds
+        );
+        bitmap.run_optimize();
+        assert_eq!(bitmap.count_runs(), 32_773);
+        assert_eq!(Bitmap::new().count_runs(), 0);
+    }
+
+    #[test]
+    fn page_after() {
+        let mut bitmap = (0..100_000)
+            .step_by(7)
+            .chain(200_000..300_000)
+            .chain([u32::MAX])
+            .collect::<Bitmap>();
+        bitmap.run_optimize();
+
+        // Walking every page gives back every value.
+        let mut values = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = bitmap.page_after(cursor, 1_000);
+            assert!(page.len() <= 1_000);
+            let Some(&last) = page.last() else {
+                break;
+            };
+            values.extend_from_slice(&page);
+            cursor = Some(last);
+        }
+        assert_eq!(values, bitmap.to_vec());
+
+        // The cursor needn't be a value of the bitmap.
+        assert_eq!(bitmap.page_after(Some(8), 3), vec![14, 21, 28]);
+        assert_eq!(
+            bitmap.page_after(Some(299_998), 3),
+            vec![299_999, u32::MAX]
+        );
+        assert_eq!(bitmap.page_after(Some(u32::MAX), 3), Vec::<u32>::new());
+        assert_eq!(bitmap.page_after(None, 0), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn clone_range() {
+        // Array, bitmap, run and full chunks.
+        let bitmap = [1, 42]
+            .into_iter()
+            .chain((1 << 16..2 << 16).step_by(2))
+            .chain((2 << 16) + 10..(4 << 16) + 5_000)
+            .collect::<Bitmap>();
+        let values = bitmap.to_vec();
+        let expected = |range: core::ops::RangeInclusive<u32>| {
+            values
+                .iter()
+                .copied()
+                .filter(|value| range.contains(value))
+                .collect::<Vec<_>>()
+        };
+
+        for (start, end) in [
+            (0, u32::MAX),
+            (2, 41),
+            (42, 100_000),
+            (100_001, (3 << 16) - 1),
+            ((2 << 16) + 5, (4 << 16) + 1_000),
+            (3 << 16, (4 << 16) - 1),
+        ] {
+            let range = start..=end;
+            let cloned = bitmap.clone_range(range.clone());
+            assert_eq!(cloned.to_vec(), expected(range), "{start}..={end}");
+            assert_eq!(cloned.cardinality(), cloned.iter().count());
+        }
+
+        // Unbounded and empty ranges.
+        assert_eq!(bitmap.clone_range(..).to_vec(), values);
+        assert_eq!(bitmap.clone_range(..42).to_vec(), [1]);
+        assert!(bitmap.clone_range(2..2).is_empty());
+        assert!(bitmap.clone_range(5 << 16..).is_empty());
+    }
+
+    #[test]
+    fn intersect_with_range() {
+        // Array, bitmap, run and full chunks.
+        let bitmap = [1, 42]
+            .into_iter()
+            .chain((1 << 16..2 << 16).step_by(2))
+            .chain((2 << 16) + 10..(4 << 16) + 5_000)
+            .collect::<Bitmap>();
+
+        for (start, end) in [
+            (0, u32::MAX),
+            (2, 41),
+            (42, 100_000),
+            (100_001, (3 << 16) - 1),
+            ((2 << 16) + 5, (4 << 16) + 1_000),
+            (3 << 16, (4 << 16) - 1),
+            (5 << 16, u32::MAX),
+            // Within a single chunk, up to its last value.
+            (5, (1 << 16) - 1),
+            ((1 << 16) + 3, (2 << 16) - 1),
+        ] {
+            let mut clamped = bitmap.clone();
+            clamped.intersect_with_range(start..=end);
+            let expected = bitmap.clone_range(start..=end);
+            assert_eq!(clamped.to_vec(), expected.to_vec(), "{start}..={end}");
+            assert_eq!(clamped.cardinality(), expected.cardinality());
+            assert_eq!(clamped.chunks().count(), expected.chunks().count());
+        }
+
+        let mut clamped = [0, 10].into_iter().collect::<Bitmap>();
+        clamped.intersect_with_range(5..=65_535);
+        assert_eq!(clamped.to_vec(), vec![10]);
+
+        // Empty range.
+        let mut clamped = bitmap.clone();
+        clamped.intersect_with_range(2..2);
+        assert!(clamped.is_empty());
+
+        // Dropped and sliced chunks are dirty, untouched ones aren't.
+        let mut clamped = bitmap.clone();
+        clamped.track_dirty_chunks();
+        clamped.intersect_with_range(100_000..(3 << 16));
+        assert_eq!(clamped.dirty_keys().collect::<Vec<_>>(), vec![0, 1, 3, 4]);
+        let mut persisted = bitmap.clone();
+        persisted.apply_patch(&clamped.dirty_patch());
+        assert_eq!(persisted.to_vec(), clamped.to_vec());
+    }
+
+    #[test]
+    fn for_each() {
+        let mut bitmap = [1, 42]
+            .into_iter()
+            .chain((1 << 16..2 << 16).step_by(2))
+            .chain((2 << 16) + 10..(2 << 16) + 5_000)
+            .chain(3 << 16..4 << 16)
+            .chain([u32::MAX])
+            .collect::<Bitmap>();
+        bitmap.run_optimize();
+
+        let mut values = Vec::new();
+        bitmap.for_each(|value| values.push(value));
+        assert_eq!(values, bitmap.to_vec());
+
+        // Stops at the first error.
+        let mut visited = 0;
+        let result = bitmap.try_for_each(|value| {
+            visited += 1;
+            if value > 2 << 16 {
+                return Err(value);
+            }
+            Ok(())
+        });
+        assert_eq!(result, Err((2 << 16) + 10));
+        assert_eq!(visited, 2 + 32_768 + 1);
+        assert_eq!(Bitmap::new().try_for_each(|_| Err(())), Ok(()));
+    }
+
+    #[test]
+    fn for_each_in_range() {
+        use std::ops::{Bound, RangeBounds};
+
+        let mut bitmap = [1, 42]
+            .into_iter()
+            .chain((1 << 16..2 << 16).step_by(2))
+            .chain((2 << 16) + 10..(2 << 16) + 5_000)
+            .chain(3 << 16..4 << 16)
+            .chain([u32::MAX])
+            .collect::<Bitmap>();
+        bitmap.run_optimize();
+
+        let check = |range: (Bound<u32>, Bound<u32>)| {
+            let mut values = Vec::new();
+            bitmap.for_each_in_range(range, |value| values.push(value));
+            let expected = bitmap.iter().filter(|value| range.contains(value));
+            assert!(values.into_iter().eq(expected), "{range:?}");
         };
+        check((Bound::Unbounded, Bound::Unbounded));
+        check((Bound::Included(2), Bound::Excluded(42)));
+        check((Bound::Included(42), Bound::Included(42)));
+        check((Bound::Excluded(1), Bound::Included((2 << 16) + 100)));
+        check((Bound::Included(70_001), Bound::Excluded(3 << 16)));
+        check((Bound::Included((3 << 16) + 5), Bound::Unbounded));
+        check((Bound::Included(5 << 16), Bound::Unbounded));
+        check((Bound::Excluded(u32::MAX), Bound::Unbounded));
+        check((Bound::Included(10), Bound::Excluded(10)));
 
-        for chunk in &self.chunks {
-            match *chunk.container() {
-                Container::Array(_) => {
-                    stats.nb_array_containers += 1;
-                    stats.nb_values_array_containers += chunk.cardinality();
-                    stats.nb_bytes_array_containers += chunk.mem_size();
-                },
-                Container::Bitmap(_) => {
-                    stats.nb_bitmap_containers += 1;
-                    stats.nb_values_bitmap_containers += chunk.cardinality();
-                    stats.nb_bytes_bitmap_containers += chunk.mem_size();
-                },
+        // Stops at the first error.
+        let result = bitmap.try_for_each_in_range(70_000.., |value| {
+            if value >= 2 << 16 {
+                return Err(value);
             }
+            Ok(())
+        });
+        assert_eq!(result, Err((2 << 16) + 10));
+    }
+
+    #[test]
+    fn fold_chunks() {
+        let mut bitmap = (0..300_000)
+            .step_by(3)
+            .chain(1_000_000..1_200_000)
+            .collect::<Bitmap>();
+        bitmap.run_optimize();
+
+        let (chunks, values) =
+            bitmap.fold_chunks((0, 0), |(chunks, values), _, view| {
+                (chunks + 1, values + view.cardinality())
+            });
+        assert_eq!(chunks, bitmap.chunks().count());
+        assert_eq!(values, bitmap.cardinality());
+        let keys = bitmap.fold_chunks(Vec::new(), |mut keys, key, _| {
+            keys.push(key);
+            keys
+        });
+        assert!(keys.into_iter().eq(bitmap.keys()));
+    }
+
+    #[test]
+    fn container_views() {
+        // Array, bitmap, run and full chunks.
+        let mut bitmap = [1, 42]
+            .into_iter()
+            .chain((1 << 16..2 << 16).step_by(2))
+            .chain((2 << 16) + 10..(2 << 16) + 5_000)
+            .chain(3 << 16..4 << 16)
+            .collect::<Bitmap>();
+        bitmap.run_optimize();
+
+        let views = bitmap.container_views().collect::<Vec<_>>();
+        assert_eq!(
+            views
+                .iter()
+                .map(|&(key, view)| (key, view.kind(), view.cardinality()))
+                .collect::<Vec<_>>(),
+            vec![
+                (0, ContainerKind::Array, 2),
+                (1, ContainerKind::Bitmap, 32_768),
+                (2, ContainerKind::Run, 4_990),
+                (3, ContainerKind::Full, 65_536),
+            ]
+        );
+        assert!(views
+            .iter()
+            .flat_map(|&(key, view)| {
+                view.into_iter()
+                    .map(move |value| (u32::from(key) << 16) | u32::from(value))
+            })
+            .eq(bitmap.iter()));
+        for &(_, view) in &views {
+            assert_eq!(view.iter().size_hint().0, view.cardinality());
+            assert_eq!(view.min(), view.iter().next());
+            assert_eq!(view.max(), view.iter().last());
         }
+        assert!(views[0].1.contains(42));
+        assert!(!views[1].1.contains(43));
+        assert!(views[2].1.contains(4_999));
+        assert!(views[3].1.contains(u16::MAX));
+    }
 
-        stats
+    #[test]
+    fn select() {
+        // Array, bitmap, run and full chunks.
+        let mut bitmap = [1, 42]
+            .into_iter()
+            .chain((1 << 16..2 << 16).step_by(2))
+            .chain((2 << 16) + 10..(2 << 16) + 5_000)
+            .chain(3 << 16..4 << 16)
+            .collect::<Bitmap>();
+        bitmap.run_optimize();
+
+        for (n, value) in bitmap.iter().enumerate().step_by(97) {
+            assert_eq!(bitmap.select(n), Some(value));
+        }
+        assert_eq!(bitmap.select(0), bitmap.min());
+        assert_eq!(bitmap.select(bitmap.cardinality() - 1), bitmap.max());
+        assert_eq!(bitmap.select(bitmap.cardinality()), None);
     }
-}
 
-impl Extend<u32> for Bitmap {
-    fn extend<I: IntoIterator<Item = u32>>(&mut self, iterator: I) {
-        for value in iterator {
-            self.insert(value);
+    #[test]
+    fn rank() {
+        // Array, bitmap, run and full chunks.
+        let mut bitmap = [1, 42]
+            .into_iter()
+            .chain((1 << 16..2 << 16).step_by(2))
+            .chain((2 << 16) + 10..(2 << 16) + 5_000)
+            .chain(3 << 16..4 << 16)
+            .collect::<Bitmap>();
+        bitmap.run_optimize();
+
+        for (n, value) in bitmap.iter().enumerate().step_by(97) {
+            assert_eq!(bitmap.rank(value), n + 1);
         }
+        assert_eq!(bitmap.rank(0), 0);
+        assert_eq!(bitmap.rank(41), 1);
+        assert_eq!(bitmap.rank(2 << 16), 2 + 32_768);
+        assert_eq!(bitmap.rank(u32::MAX), bitmap.cardinality());
     }
-}
 
-impl FromIterator<u32> for Bitmap {
-    fn from_iter<I: IntoIterator<Item = u32>>(iterator: I) -> Self {
-        let mut bitmap = Self::new();
-        bitmap.extend(iterator);
-        bitmap
+    #[test]
+    #[cfg(feature = "rand")]
+    fn random() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(42);
+        assert_eq!(Bitmap::new().random(&mut rng), None);
+
+        // One value in the first chunk, a thousand in the second one.
+        let mut bitmap = [7]
+            .into_iter()
+            .chain((1 << 16..2 << 16).step_by(64))
+            .collect::<Bitmap>();
+        bitmap.run_optimize();
+        let mut first_chunk = 0;
+        for _ in 0..10_000 {
+            let value = bitmap.random(&mut rng).expect("non-empty bitmap");
+            assert!(bitmap.contains(value), "{value} not in bitmap");
+            first_chunk += usize::from(value == 7);
+        }
+        assert!((1..=50).contains(&first_chunk), "{first_chunk} draws");
     }
-}
 
-impl<'a> IntoIterator for &'a Bitmap {
-    type Item = u32;
-    type IntoIter = Iter<'a>;
+    #[test]
+    fn streaming_set_operations() {
+        // Array, bitmap and run containers, and chunks on one side only.
+        let mut lhs = (0..100_000)
+            .step_by(3)
+            .chain(200_000..210_000)
+            .collect::<Bitmap>();
+        let rhs = (50_000..300_000).step_by(5).collect::<Bitmap>();
+        lhs.run_optimize();
+
+        assert!(lhs
+            .intersection_iter(&rhs)
+            .eq(lhs.intersection(&rhs).iter()));
+        assert!(rhs
+            .intersection_iter(&lhs)
+            .eq(lhs.intersection(&rhs).iter()));
+        assert!(lhs
+            .intersection_iter(&rhs)
+            .take(10)
+            .eq((50_010..).step_by(15).take(10)));
+        assert_eq!(lhs.intersection_iter(&Bitmap::new()).count(), 0);
+
+        assert!(lhs.difference_iter(&rhs).eq(lhs.difference(&rhs).iter()));
+        assert!(rhs.difference_iter(&lhs).eq(rhs.difference(&lhs).iter()));
+        assert!(lhs.difference_iter(&Bitmap::new()).eq(lhs.iter()));
+        assert!(lhs
+            .symmetric_difference_iter(&rhs)
+            .eq(lhs.symmetric_difference(&rhs).iter()));
+        assert_eq!(lhs.symmetric_difference_iter(&lhs).count(), 0);
 
-    fn into_iter(self) -> Self::IntoIter {
-        self.iter()
+        let other = (0..400_000).step_by(7).collect::<Bitmap>();
+        let union = lhs.union(&rhs).union(&other);
+        assert!(Bitmap::union_iter(&[&lhs, &rhs, &other]).eq(union.iter()));
+        assert!(Bitmap::union_iter(&[&lhs, &lhs]).eq(lhs.iter()));
+        assert_eq!(Bitmap::union_iter(&[]).count(), 0);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn set_operations_empty() {
+        let bitmap = (0..1_000).collect::<Bitmap>();
+        let empty = Bitmap::new();
+
+        assert!(bitmap.intersection(&empty).is_empty());
+        assert!(bitmap.difference(&bitmap).is_empty());
+        assert!(bitmap.symmetric_difference(&bitmap).is_empty());
+        assert_eq!(bitmap.union(&empty).cardinality(), 1_000);
+    }
 
     #[test]
-    fn insertion_deletion() {
-        let mut bitmap = Bitmap::new();
-        assert_eq!(bitmap.cardinality(), 0);
-        assert_eq!(bitmap.min(), None);
-        assert_eq!(bitmap.max(), None);
-        // No allocation for empty bitmap.
-        assert_eq!(bitmap.chunks.len(), 0);
+    fn extend_batches() {
+        // Sorted, unsorted and duplicated values across several chunks.
+        let input = (0..10_000)
+            .chain((0..10_000).rev())
+            .chain(100_000..110_000)
+            .chain([5, 3, 200_000, 7])
+            .collect::<Vec<u32>>();
+        let bitmap = input.iter().copied().collect::<Bitmap>();
 
-        // Chunks are created as needed.
-        bitmap.insert(1538809352);
-        bitmap.insert(1538809350);
-        assert_eq!(bitmap.cardinality(), 2);
-        assert_eq!(bitmap.chunks.len(), 1);
-        bitmap.insert(370099062);
-        assert_eq!(bitmap.cardinality(), 3);
-        assert_eq!(bitmap.chunks.len(), 2);
+        let mut expected = input;
+        expected.sort_unstable();
+        expected.dedup();
+        assert_eq!(bitmap.cardinality(), expected.len());
+        assert_eq!(bitmap.iter().collect::<Vec<_>>(), expected);
+    }
 
-        // Operation works accross chunks.
-        assert_eq!(bitmap.min(), Some(370099062));
-        assert_eq!(bitmap.max(), Some(1538809352));
+    #[test]
+    fn from_sorted_iter() {
+        let input = (0..100)
+            .chain((1 << 16..2 << 16).step_by(3))
+            .chain(5 << 16..6 << 16)
+            .chain([u32::MAX])
+            .collect::<Vec<u32>>();
 
-        // Chunks are deleted when empty.
-        bitmap.remove(370099062);
-        assert_eq!(bitmap.cardinality(), 2);
-        assert_eq!(bitmap.chunks.len(), 1);
+        let bitmap = Bitmap::from_sorted_iter(input.iter().copied())
+            .expect("sorted input");
+        assert_eq!(bitmap.cardinality(), input.len());
+        assert_eq!(bitmap.chunks().count(), 4);
+        assert_eq!(bitmap.iter().collect::<Vec<_>>(), input);
+
+        let bitmap =
+            Bitmap::from_sorted_iter([1, 1, 2, 2, 3]).expect("duplicates");
+        assert_eq!(bitmap.iter().collect::<Vec<_>>(), vec![1, 2, 3]);
+
+        assert_eq!(
+            Bitmap::from_sorted_iter([1, 70_000, 2]).err(),
+            Some(NotSortedError::new())
+        );
+        assert!(Bitmap::from_sorted_iter([]).expect("empty").is_empty());
     }
 
     #[test]
-    fn contains() {
+    fn push() {
         let mut bitmap = Bitmap::new();
-        assert_eq!(bitmap.contains(42), false);
+        for value in (0..200_000).step_by(3) {
+            assert!(bitmap.push(value));
+        }
+        assert!(bitmap.push(u32::MAX));
+        assert_eq!(bitmap.cardinality(), 66_668);
+        assert_eq!(bitmap.chunks().count(), 5);
 
-        bitmap.insert(42);
-        assert_eq!(bitmap.contains(42), true);
+        assert!(!bitmap.push(u32::MAX), "already exists");
+        assert!(!bitmap.push(3), "smaller than max");
+        assert!(!bitmap.contains(4));
+        assert_eq!(bitmap.cardinality(), 66_668);
 
-        bitmap.remove(42);
-        assert_eq!(bitmap.contains(42), false);
+        let expected = (0..200_000).step_by(3).chain([u32::MAX]);
+        assert!(bitmap.iter().eq(expected));
     }
 
     #[test]
-    fn already_exists() {
+    fn deferred_optimization() {
         let mut bitmap = Bitmap::new();
+        for value in (0..10_000).step_by(2) {
+            assert!(bitmap.insert_deferred(value));
+        }
+        assert!(!bitmap.insert_deferred(42), "already exists");
+        assert!(bitmap.remove_deferred(42));
+        assert!(!bitmap.remove_deferred(42), "missing entry");
+        assert_eq!(bitmap.cardinality(), 4_999);
+
+        // Containers are converted only once asked to.
+        let kinds = |bitmap: &Bitmap| {
+            bitmap.chunks().map(|info| info.kind).collect::<Vec<_>>()
+        };
+        assert_eq!(kinds(&bitmap), vec![ContainerKind::Array]);
+        bitmap.optimize();
+        assert_eq!(kinds(&bitmap), vec![ContainerKind::Bitmap]);
+        let expected = (0..10_000).step_by(2).filter(|&value| value != 42);
+        assert!(bitmap.iter().eq(expected));
 
-        assert_eq!(bitmap.insert(42), true, "new entry");
-        assert_eq!(bitmap.insert(42), false, "already exists");
+        // Removing the last value still deletes the chunk.
+        let mut bitmap = Bitmap::new();
+        bitmap.insert_deferred(70_000);
+        assert!(bitmap.remove_deferred(70_000));
+        assert!(bitmap.is_empty());
     }
 
     #[test]
-    fn missing() {
-        let mut bitmap = Bitmap::new();
+    fn many_chunks() {
+        // Enough chunks to go through the chunk index.
+        let mut bitmap = (0..5_000).map(|key| key << 16).collect::<Bitmap>();
+        let mem_size = bitmap.mem_size();
+        assert!(bitmap.contains(4_999 << 16));
+        assert!(!bitmap.contains(5_000 << 16));
+        if cfg!(feature = "std") {
+            assert!(bitmap.mem_size() > mem_size, "index is built");
+        }
 
-        bitmap.insert(11);
+        // The index is kept up to date when chunks are added or removed.
+        assert!(bitmap.insert(1));
+        assert!(bitmap.insert(10_000 << 16));
+        assert!(bitmap.contains(1));
+        assert!(bitmap.contains(10_000 << 16));
+        assert!(bitmap.remove(0));
+        assert!(bitmap.remove(2_500 << 16));
+        assert!(!bitmap.contains(2_500 << 16));
+        assert!(bitmap.contains(2_501 << 16));
+        assert!(!bitmap.insert(1), "already exists");
+        assert_eq!(bitmap.cardinality(), 5_000);
+    }
+
+    #[test]
+    fn hot_chunk() {
+        let mut bitmap =
+            [1 << 16, 2 << 16, 3 << 16].into_iter().collect::<Bitmap>();
+        assert!(bitmap.contains(2 << 16));
+        assert!(!bitmap.contains((2 << 16) + 1));
 
-        assert_eq!(bitmap.remove(11), true, "found");
-        assert_eq!(bitmap.remove(11), false, "missing entry");
+        // Chunks are shifted around the last hit: the stale position is
+        // detected.
+        assert!(bitmap.insert(0));
+        assert!(bitmap.contains(0));
+        assert!(bitmap.contains(2 << 16));
+        assert!(bitmap.remove(1 << 16));
+        assert!(!bitmap.contains(1 << 16));
+        assert!(bitmap.contains(3 << 16));
+        assert!(bitmap.insert((3 << 16) + 1));
+        assert_eq!(
+            bitmap.iter().collect::<Vec<_>>(),
+            [0, 2 << 16, 3 << 16, (3 << 16) + 1]
+        );
     }
 
     #[test]
-    fn is_empty() {
-        let mut bitmap = Bitmap::new();
-        assert_eq!(bitmap.is_empty(), true);
+    fn full_chunk() {
+        // Last chunk of the key space, holding every possible value.
+        let mut bitmap = (u32::MAX - 65_535..=u32::MAX).collect::<Bitmap>();
+        assert_eq!(bitmap.cardinality(), 65_536);
+        assert!(!bitmap.insert(u32::MAX), "already exists");
+        assert_eq!(bitmap.cardinality(), 65_536);
+
+        assert!(bitmap.remove(u32::MAX));
+        assert_eq!(bitmap.cardinality(), 65_535);
+        assert!(bitmap.insert(u32::MAX));
+        assert_eq!(bitmap.cardinality(), 65_536);
+        assert_eq!(bitmap.min(), Some(u32::MAX - 65_535));
+        assert_eq!(bitmap.max(), Some(u32::MAX));
+        assert_eq!(bitmap.iter().count(), 65_536);
+    }
+
+    #[test]
+    fn chunks() {
+        let mut bitmap = (0..10)
+            .chain((65_536..65_536 + 20_000).step_by(2))
+            .collect::<Bitmap>();
+        bitmap.extend(5 << 16..6 << 16);
 
-        bitmap.insert(1538809352);
-        bitmap.insert(1538809350);
-        bitmap.insert(370099062);
-        assert_eq!(bitmap.is_empty(), false);
+        let layout = bitmap
+            .chunks()
+            .map(|info| (info.key, info.kind, info.cardinality))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            layout,
+            vec![
+                (0, ContainerKind::Array, 10),
+                (1, ContainerKind::Bitmap, 10_000),
+                (5, ContainerKind::Full, 65_536),
+            ]
+        );
+        let mem_size = bitmap.chunks().map(|info| info.mem_size).sum::<usize>();
+        assert!(mem_size <= bitmap.mem_size());
+    }
+
+    #[test]
+    fn cached_cardinality() {
+        let count = |bitmap: &Bitmap| bitmap.iter().count();
+        let mut bitmap = (0..1_000).chain([5, 70_000]).collect::<Bitmap>();
+        assert_eq!(bitmap.cardinality(), count(&bitmap));
+
+        bitmap.insert(42);
+        bitmap.insert(1_000_000);
+        assert_eq!(bitmap.cardinality(), count(&bitmap));
+        bitmap.remove(1_000_000);
+        bitmap.remove(2_000_000);
+        assert_eq!(bitmap.cardinality(), count(&bitmap));
+
+        let other = (500..80_000).step_by(7).collect::<Bitmap>();
+        for result in [
+            bitmap.union(&other),
+            bitmap.intersection(&other),
+            bitmap.difference(&other),
+            bitmap.symmetric_difference(&other),
+        ] {
+            assert_eq!(result.cardinality(), count(&result));
+        }
 
         bitmap.clear();
-        assert_eq!(bitmap.is_empty(), true);
+        assert_eq!(bitmap.cardinality(), 0);
     }
 
     #[test]
-    fn iterator_sparse() {
-        let input = (0..10_000).step_by(10).collect::<Vec<_>>();
-        let bitmap = input.iter().copied().collect::<Bitmap>();
+    fn keys() {
+        let mut bitmap = [1, 2 << 16, (2 << 16) + 1, u32::MAX]
+            .into_iter()
+            .collect::<Bitmap>();
+        assert!(bitmap.keys().eq([0, 2, u16::MAX]));
 
-        let stats = bitmap.stats();
-        assert_eq!(stats.nb_bitmap_containers, 0, "sparse bitmap");
+        bitmap.remove(1);
+        assert!(bitmap.keys().eq([2, u16::MAX]));
+        assert_eq!(Bitmap::new().keys().count(), 0);
+    }
 
-        let values = (&bitmap).into_iter().collect::<Vec<_>>();
-        assert_eq!(values, input);
+    #[test]
+    fn iter_ranges() {
+        // Runs spanning several chunks are merged.
+        let mut bitmap = (10..200_000)
+            .chain([300_000, 300_002])
+            .chain(u32::MAX - 1..=u32::MAX)
+            .collect::<Bitmap>();
+        assert!(bitmap.iter_ranges().eq([
+            10..=199_999,
+            300_000..=300_000,
+            300_002..=300_002,
+            u32::MAX - 1..=u32::MAX,
+        ]));
+
+        // Unless a chunk is missing in between.
+        for value in 131_072..196_608 {
+            bitmap.remove(value);
+        }
+        assert!(bitmap
+            .iter_ranges()
+            .take(2)
+            .eq([10..=131_071, 196_608..=199_999]));
+        assert_eq!(Bitmap::new().iter_ranges().count(), 0);
     }
 
     #[test]
-    fn iterator_dense() {
-        let input = (0..10_000).step_by(2).collect::<Vec<_>>();
-        let bitmap = input.iter().copied().collect::<Bitmap>();
+    fn bitset_words() {
+        // Dense words spanning a chunk boundary, and a sparse one.
+        let mut words = vec![u64::MAX; 1_100];
+        words[1_050] = 0b1010;
+        words[1_099] = 1 << 63;
+        let expected = (0..1_050 * 64)
+            .chain([1_050 * 64 + 1, 1_050 * 64 + 3])
+            .chain(1_051 * 64..1_099 * 64)
+            .chain([1_100 * 64 - 1])
+            .collect::<Vec<u32>>();
 
-        let stats = bitmap.stats();
-        assert_eq!(stats.nb_array_containers, 0, "dense bitmap");
+        let bitmap = Bitmap::from_bitset_words(0, &words);
+        assert!(bitmap.iter().eq(expected.iter().copied()));
+        assert_eq!(bitmap.to_bitset_words(), words);
 
-        let values = (&bitmap).into_iter().collect::<Vec<_>>();
-        assert_eq!(values, input);
+        // Unaligned offset.
+        let bitmap = Bitmap::from_bitset_words(65_500, &words);
+        assert!(bitmap
+            .iter()
+            .eq(expected.iter().map(|value| value + 65_500)));
+
+        // Bits past the value space are ignored.
+        let bitmap = Bitmap::from_bitset_words(u32::MAX - 9, &[u64::MAX; 2]);
+        assert!(bitmap.iter().eq(u32::MAX - 9..=u32::MAX));
+
+        assert!(Bitmap::from_bitset_words(42, &[0; 10]).is_empty());
+        assert!(Bitmap::new().to_bitset_words().is_empty());
+        assert_eq!(
+            Bitmap::from_iter([3, 200]).to_bitset_words(),
+            [8, 0, 0, 256]
+        );
+    }
+
+    #[test]
+    fn std_collections() {
+        use std::collections::BTreeSet;
+
+        let values = [1, 42, 1 << 16, u32::MAX];
+        let btree_set = values.into_iter().collect::<BTreeSet<_>>();
+
+        let bitmap = Bitmap::from(&btree_set);
+        assert!(bitmap.iter().eq(values));
+        assert_eq!(BTreeSet::from(&bitmap), btree_set);
+
+        #[cfg(feature = "std")]
+        {
+            let hash_set =
+                values.into_iter().collect::<std::collections::HashSet<_>>();
+            let bitmap = Bitmap::from(&hash_set);
+            assert!(bitmap.iter().eq(values));
+        }
     }
 
     #[test]
     fn mem_size() {
         let bitmap = (0..10_000).step_by(2).collect::<Bitmap>();
-        let chunks_size = bitmap
-            .chunks
-            .iter()
-            .fold(0, |acc, chunk| acc + chunk.mem_size());
+        let chunks_size =
+            bitmap.chunks().map(|info| info.mem_size).sum::<usize>();
 
         // Ensure we don't forget to account for the Vec overhead.
         assert!(bitmap.mem_size() > chunks_size);
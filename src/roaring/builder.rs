@@ -0,0 +1,262 @@
+use super::Roaring;
+use crate::{roaring_core::Quota, Tuning};
+
+/// Builder of [`Roaring`] bitmaps with a custom configuration.
+#[derive(Clone, Copy, Debug)]
+pub struct Builder {
+    /// Limits enforced by the fallible insertions.
+    quota: Quota,
+    /// Settings driving the choice of the containers.
+    tuning: Tuning,
+    /// Number of chunks allocated upfront.
+    chunk_capacity: usize,
+    /// Whether the bitmap keeps a running count of its values.
+    cache_cardinality: bool,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self {
+            quota: Quota::default(),
+            tuning: Tuning::default(),
+            chunk_capacity: 0,
+            cache_cardinality: true,
+        }
+    }
+}
+
+impl Builder {
+    /// Initializes a builder with the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of values of the bitmap.
+    ///
+    /// Enforced by [`Roaring::try_insert`]: the infallible updates ignore it.
+    #[must_use]
+    pub fn max_cardinality(mut self, max: usize) -> Self {
+        self.quota.max_cardinality = Some(max);
+        self
+    }
+
+    /// Sets the maximum approximate in-memory size of the bitmap, in bytes
+    /// (as reported by [`Roaring::mem_size`]).
+    ///
+    /// Enforced by [`Roaring::try_insert`]: the infallible updates ignore it.
+    #[must_use]
+    pub fn max_mem_size(mut self, max: usize) -> Self {
+        self.quota.max_mem_size = Some(max);
+        self
+    }
+
+    /// Sets the cardinality up to which a chunk uses an array container,
+    /// rather than a bitmap one (4096 by default).
+    ///
+    /// Applied by every update of the bitmap, including bulk ones (e.g.
+    /// `extend`): only the new bitmaps resulting from a set operation use the
+    /// default threshold.
+    #[must_use]
+    pub fn sparse_threshold(mut self, threshold: usize) -> Self {
+        self.tuning.sparse_threshold = threshold;
+        self
+    }
+
+    /// Sets whether the updates switch chunks to run containers when they are
+    /// the most compact representation (enabled by default).
+    ///
+    /// When disabled, run containers are still available through
+    /// [`Roaring::run_optimize`].
+    #[must_use]
+    pub fn use_runs(mut self, enabled: bool) -> Self {
+        self.tuning.use_runs = enabled;
+        self
+    }
+
+    /// Sets the number of chunks allocated upfront.
+    #[must_use]
+    pub fn chunk_capacity(mut self, capacity: usize) -> Self {
+        self.chunk_capacity = capacity;
+        self
+    }
+
+    /// Sets whether the bitmap caches its cardinality (enabled by default).
+    ///
+    /// When disabled, the updates no longer maintain a running count and
+    /// [`Roaring::cardinality`] sums the cardinalities of the chunks instead.
+    #[must_use]
+    pub fn cache_cardinality(mut self, enabled: bool) -> Self {
+        self.cache_cardinality = enabled;
+        self
+    }
+
+    /// Builds an empty bitmap with the configured settings.
+    pub fn build(self) -> Roaring {
+        Roaring::with_config(
+            self.quota,
+            self.tuning,
+            self.chunk_capacity,
+            self.cache_cardinality,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ContainerKind;
+
+    #[test]
+    fn max_cardinality() {
+        let mut bitmap = Builder::new().max_cardinality(3).build();
+
+        for value in [1, 100_000, 42] {
+            assert_eq!(bitmap.try_insert(value), Ok(true));
+        }
+        assert_eq!(bitmap.try_insert(42), Ok(false), "already exists");
+        assert!(bitmap.try_insert(7).is_err(), "quota exceeded");
+        assert_eq!(bitmap.cardinality(), 3);
+
+        bitmap.remove(1);
+        assert_eq!(bitmap.try_insert(7), Ok(true), "room left");
+    }
+
+    #[test]
+    fn max_mem_size() {
+        let empty = Roaring::new().mem_size();
+        let mut bitmap = Builder::new().max_mem_size(empty + 1_024).build();
+
+        // Values keep being added to the first chunk until it's too large.
+        let mut count = 0;
+        while bitmap.try_insert(count).is_ok() {
+            count += 1;
+        }
+        assert!(count > 0);
+        assert_eq!(bitmap.cardinality(), count as usize);
+        assert!(bitmap.mem_size() <= empty + 1_024);
+        assert!(!bitmap.contains(count), "rejected");
+
+        // Unlimited by default.
+        let mut bitmap = Builder::new().build();
+        assert!((0..100_000).all(|value| bitmap.try_insert(value).is_ok()));
+    }
+
+    #[test]
+    fn rejected_try_insert() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        let builder = Builder::new().sparse_threshold(4).use_runs(false);
+        let values = [0, 10, 20, 30];
+        let size = values.into_iter().collect::<Roaring>().mem_size();
+        let mut bitmap = builder.max_mem_size(size + 1_024).build();
+        bitmap.extend(values);
+        let conversions = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&conversions);
+        bitmap.on_conversion(move |_, _, _| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+        bitmap.track_dirty_chunks();
+
+        // Would switch the chunk to an 8 kB bitmap container.
+        assert!(bitmap.try_insert(40).is_err());
+        assert!(bitmap.dirty_patch().is_empty());
+        assert_eq!(conversions.load(Ordering::Relaxed), 0);
+        assert_eq!(bitmap.to_vec(), values);
+
+        // A new chunk fits.
+        assert_eq!(bitmap.try_insert(100_000), Ok(true));
+        assert_eq!(bitmap.dirty_patch().len(), 1);
+    }
+
+    #[test]
+    fn tuning() {
+        let kinds = |bitmap: &Roaring| {
+            bitmap.chunks().map(|info| info.kind).collect::<Vec<_>>()
+        };
+        let mut bitmap = Builder::new()
+            .sparse_threshold(10_000)
+            .use_runs(false)
+            .chunk_capacity(16)
+            .build();
+
+        for value in (0..16_000).chain(65_536..75_536) {
+            bitmap.insert(value);
+        }
+        assert_eq!(
+            kinds(&bitmap),
+            vec![ContainerKind::Bitmap, ContainerKind::Array]
+        );
+
+        // Default settings.
+        let mut bitmap = Builder::new().build();
+        for value in (0..16_000).chain(65_536..75_536) {
+            bitmap.insert(value);
+        }
+        assert_eq!(kinds(&bitmap), vec![ContainerKind::Run; 2]);
+    }
+
+    #[test]
+    fn cache_cardinality() {
+        let mut cached = Builder::new().build();
+        let mut uncached = Builder::new().cache_cardinality(false).build();
+
+        for bitmap in [&mut cached, &mut uncached] {
+            bitmap.extend((0..10_000).chain(65_536..70_000));
+            bitmap.insert(100_000);
+            bitmap.remove(42);
+            bitmap.add_offset(-10);
+            bitmap.intersect_with_range(10..68_000);
+            assert_eq!(bitmap.cardinality(), 9_979 + 2_474);
+        }
+        assert_eq!(cached.to_vec(), uncached.to_vec());
+
+        uncached.clear();
+        assert_eq!(uncached.cardinality(), 0);
+        uncached.overwrite_from(&cached);
+        assert_eq!(uncached.cardinality(), cached.cardinality());
+        assert_eq!(uncached.iter().count(), cached.cardinality());
+    }
+
+    #[test]
+    fn tuning_bulk_updates() {
+        let kinds = |bitmap: &Roaring| {
+            bitmap.chunks().map(|info| info.kind).collect::<Vec<_>>()
+        };
+        let build = || {
+            Builder::new()
+                .sparse_threshold(10_000)
+                .use_runs(false)
+                .build()
+        };
+
+        // New chunks.
+        let mut bitmap = build();
+        bitmap.extend((0..8_000).chain(65_536..77_536));
+        assert_eq!(
+            kinds(&bitmap),
+            vec![ContainerKind::Array, ContainerKind::Bitmap]
+        );
+
+        // Chunks rebuilt in place.
+        bitmap.add_offset(1);
+        assert_eq!(
+            kinds(&bitmap),
+            vec![ContainerKind::Array, ContainerKind::Bitmap]
+        );
+        bitmap.intersect_with_range(1..77_000);
+        assert_eq!(
+            kinds(&bitmap),
+            vec![ContainerKind::Array, ContainerKind::Bitmap]
+        );
+        let other = (0..9_000).collect::<Roaring>();
+        bitmap.assign_union(&other, &other);
+        assert_eq!(kinds(&bitmap), vec![ContainerKind::Array]);
+
+        let mut replica = build();
+        replica.apply_patch(&Roaring::diff(&Roaring::new(), &bitmap));
+        assert_eq!(kinds(&replica), vec![ContainerKind::Array]);
+    }
+}
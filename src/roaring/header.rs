@@ -1,7 +1,8 @@
 use crate::chunk;
 
 /// Chunk header.
-pub(crate) struct Header {
+#[derive(Clone)]
+pub struct Header {
     /// The 16 most significant bits.
     key: u16,
     /// Chunk's cardinality minus one.
@@ -39,4 +40,9 @@ impl chunk::Header for Header {
     fn decrease_cardinality(&mut self) {
         self.cardinality = self.cardinality.saturating_sub(1);
     }
+
+    fn set_cardinality(&mut self, cardinality: usize) {
+        self.cardinality = u16::try_from(cardinality - 1)
+            .expect("chunk cardinality fits on 16-bit");
+    }
 }
@@ -1,10 +1,25 @@
 mod bitmap;
+mod builder;
 mod entry;
 mod header;
-mod iter;
 
 pub use bitmap::Bitmap as Roaring;
+pub use builder::Builder as RoaringBuilder;
 
-pub(super) use entry::Entry;
-pub(super) use header::Header;
-pub(super) use iter::{ChunkIter, Iter};
+pub(crate) use entry::Entry;
+pub(crate) use header::Header;
+
+/// Immutable Roaring bitmap iterator.
+///
+/// This struct is created by the `iter` method on Roaring bitmap.
+pub type RoaringIter<'a> = crate::roaring_core::Iter<'a, u16>;
+
+/// Owning Roaring bitmap iterator.
+///
+/// This struct is created by the `into_iter` method on Roaring bitmap.
+pub type RoaringIntoIter = crate::roaring_core::IntoIter<u16>;
+
+/// Changes turning a Roaring bitmap into another one.
+///
+/// This struct is created by the `diff` method on Roaring bitmap.
+pub type RoaringPatch = crate::roaring_core::Patch;
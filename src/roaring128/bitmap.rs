@@ -0,0 +1,269 @@
+use super::{Entry, Iter};
+use crate::{RoaringTreeMap, Stats};
+use alloc::collections::{btree_map, BTreeMap};
+
+/// Compressed bitmap for 128-bit integers (e.g. UUID or IPv6 addresses).
+///
+/// Uses a set of 64-bit Roaring Tree-Map bitmaps, indexed by the 64 most
+/// significant bits through a tree-based map.
+#[derive(Default)]
+pub struct Bitmap {
+    /// Underlying Roaring Tree-Map bitmaps, indexed by the 64 most significant
+    /// bits of the integer.
+    bitmaps: BTreeMap<u64, RoaringTreeMap>,
+}
+
+impl Bitmap {
+    /// Create an empty bitmap.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a value to the bitmap.
+    ///
+    /// If the bitmap did not have this value present, true is returned.
+    /// If the bitmap did have this value present, false is returned.
+    pub fn insert(&mut self, value: u128) -> bool {
+        let entry = Entry::from(value);
+
+        self.bitmaps.entry(entry.hi).or_default().insert(entry.lo)
+    }
+
+    /// Removes a value from the bitmap.
+    ///
+    /// Returns whether the value was present or not.
+    pub fn remove(&mut self, value: u128) -> bool {
+        let entry = Entry::from(value);
+
+        match self.bitmaps.entry(entry.hi) {
+            btree_map::Entry::Occupied(mut slot) => {
+                let removed = slot.get_mut().remove(entry.lo);
+
+                // Remove unused bitmap.
+                if slot.get().is_empty() {
+                    slot.remove();
+                }
+                removed
+            },
+            btree_map::Entry::Vacant(_) => false,
+        }
+    }
+
+    /// Returns true if the bitmap contains the value.
+    pub fn contains(&self, value: u128) -> bool {
+        let entry = Entry::from(value);
+
+        self.bitmaps
+            .get(&entry.hi)
+            .is_some_and(|bitmap| bitmap.contains(entry.lo))
+    }
+
+    /// Computes the bitmap cardinality.
+    pub fn cardinality(&self) -> usize {
+        self.bitmaps
+            .values()
+            .fold(0, |acc, bitmap| acc + bitmap.cardinality())
+    }
+
+    /// Finds the smallest value in the bitmap.
+    pub fn min(&self) -> Option<u128> {
+        self.bitmaps.first_key_value().and_then(|(key, bitmap)| {
+            bitmap.min().map(|min| Entry::from_parts(*key, min).into())
+        })
+    }
+
+    /// Finds the largest value in the bitmap.
+    pub fn max(&self) -> Option<u128> {
+        self.bitmaps.last_key_value().and_then(|(key, bitmap)| {
+            bitmap.max().map(|max| Entry::from_parts(*key, max).into())
+        })
+    }
+
+    /// Clears the bitmap, removing all values.
+    pub fn clear(&mut self) {
+        self.bitmaps.clear();
+    }
+
+    /// Returns true if the bitmap contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.bitmaps.is_empty()
+    }
+
+    /// Gets an iterator that visits the values in the bitmap in ascending
+    /// order.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter::new(self.bitmaps.iter())
+    }
+
+    /// Returns the approximate in-memory size of the bitmap, in bytes.
+    pub fn mem_size(&self) -> usize {
+        size_of_val(self)
+            + self.bitmaps.iter().fold(0, |acc, (key, bitmap)| {
+                acc + size_of_val(key) + bitmap.mem_size()
+            })
+    }
+
+    /// Returns detailed statistics about the composition of the bitmap.
+    ///
+    /// Each underlying Tree-Map bitmap is reported as a super-chunk.
+    pub fn stats(&self) -> Stats<u128> {
+        let mut stats = Stats::new(
+            self.cardinality(),
+            self.mem_size(),
+            self.min(),
+            self.max(),
+        );
+        for bitmap in self.bitmaps.values() {
+            stats.add_super_chunk(&bitmap.stats());
+        }
+
+        stats
+    }
+}
+
+impl Extend<u128> for Bitmap {
+    fn extend<I: IntoIterator<Item = u128>>(&mut self, iterator: I) {
+        for value in iterator {
+            self.insert(value);
+        }
+    }
+}
+
+impl FromIterator<u128> for Bitmap {
+    fn from_iter<I: IntoIterator<Item = u128>>(iterator: I) -> Self {
+        let mut bitmap = Self::new();
+        bitmap.extend(iterator);
+        bitmap
+    }
+}
+
+impl<'a> IntoIterator for &'a Bitmap {
+    type Item = u128;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // IPv6 addresses from the documentation prefix (2001:db8::/32).
+    const ADDR1: u128 = 0x2001_0db8_0000_0000_0000_0000_0000_0001;
+    const ADDR2: u128 = 0x2001_0db8_0000_0000_0000_0000_0000_0002;
+    const ADDR3: u128 = 0x2001_0db8_0001_0000_0000_0000_0000_0001;
+
+    #[test]
+    fn insertion_deletion() {
+        let mut bitmap = Bitmap::new();
+        assert_eq!(bitmap.cardinality(), 0);
+        assert_eq!(bitmap.min(), None);
+        assert_eq!(bitmap.max(), None);
+        // No allocation for empty bitmap.
+        assert_eq!(bitmap.bitmaps.len(), 0);
+
+        // Bitmaps are created as needed.
+        bitmap.insert(ADDR2);
+        bitmap.insert(ADDR1);
+        assert_eq!(bitmap.cardinality(), 2);
+        assert_eq!(bitmap.bitmaps.len(), 1);
+        bitmap.insert(ADDR3);
+        assert_eq!(bitmap.cardinality(), 3);
+        assert_eq!(bitmap.bitmaps.len(), 2);
+
+        // Operation works accross bitmaps.
+        assert_eq!(bitmap.min(), Some(ADDR1));
+        assert_eq!(bitmap.max(), Some(ADDR3));
+
+        // Bitmaps are deleted when empty.
+        bitmap.remove(ADDR3);
+        assert_eq!(bitmap.cardinality(), 2);
+        assert_eq!(bitmap.bitmaps.len(), 1);
+    }
+
+    #[test]
+    fn contains() {
+        let mut bitmap = Bitmap::new();
+        assert!(!bitmap.contains(ADDR1));
+
+        bitmap.insert(ADDR1);
+        assert!(bitmap.contains(ADDR1));
+        assert!(!bitmap.contains(ADDR3), "same low bits");
+
+        bitmap.remove(ADDR1);
+        assert!(!bitmap.contains(ADDR1));
+    }
+
+    #[test]
+    fn already_exists() {
+        let mut bitmap = Bitmap::new();
+
+        assert!(bitmap.insert(ADDR1), "new entry");
+        assert!(!bitmap.insert(ADDR1), "already exists");
+    }
+
+    #[test]
+    fn missing() {
+        let mut bitmap = Bitmap::new();
+
+        bitmap.insert(ADDR1);
+
+        assert!(bitmap.remove(ADDR1), "found");
+        assert!(!bitmap.remove(ADDR1), "missing entry");
+    }
+
+    #[test]
+    fn is_empty() {
+        let mut bitmap = Bitmap::new();
+        assert!(bitmap.is_empty());
+
+        bitmap.insert(ADDR1);
+        bitmap.insert(ADDR3);
+        assert!(!bitmap.is_empty());
+
+        bitmap.clear();
+        assert!(bitmap.is_empty());
+    }
+
+    #[test]
+    fn iterator() {
+        let input = (0..10_000)
+            .map(|value| ADDR1 + value * 7)
+            .chain([ADDR3, u128::MAX])
+            .collect::<Vec<_>>();
+        let bitmap = input.iter().rev().copied().collect::<Bitmap>();
+
+        let mut iter = bitmap.iter();
+        assert_eq!(iter.size_hint(), (input.len(), Some(input.len())));
+        assert_eq!(iter.next(), Some(ADDR1));
+        assert_eq!(iter.size_hint().0, input.len() - 1);
+
+        let values = (&bitmap).into_iter().collect::<Vec<_>>();
+        assert_eq!(values, input);
+    }
+
+    #[test]
+    fn stats() {
+        let bitmap = [ADDR1, ADDR2, ADDR3].into_iter().collect::<Bitmap>();
+
+        let stats = bitmap.stats();
+        assert_eq!(stats.nb_super_chunks, 2);
+        assert_eq!(stats.nb_containers, 2);
+        assert_eq!(stats.min_value, Some(ADDR1));
+        assert_eq!(stats.max_value, Some(ADDR3));
+    }
+
+    #[test]
+    fn mem_size() {
+        let bitmap = (0..10_000).step_by(2).collect::<Bitmap>();
+        let bitmaps_size = bitmap
+            .bitmaps
+            .values()
+            .fold(0, |acc, bitmap| acc + bitmap.mem_size());
+
+        // Ensure we don't forget to account for the BTreeMap overhead.
+        assert!(bitmap.mem_size() > bitmaps_size);
+    }
+}
@@ -0,0 +1,53 @@
+/// `Roaring128` entry.
+pub(super) struct Entry {
+    /// Most significant bits.
+    pub(super) hi: u64,
+    /// Least significant bits.
+    pub(super) lo: u64,
+}
+
+impl Entry {
+    /// Initialize a new entry from its lower and higher parts.
+    pub(super) fn from_parts(hi: u64, lo: u64) -> Self {
+        Self { hi, lo }
+    }
+}
+
+impl From<u128> for Entry {
+    #[allow(clippy::cast_possible_truncation)] // We truncate on purpose here.
+    fn from(value: u128) -> Self {
+        Self::from_parts((value >> 64) as u64, value as u64)
+    }
+}
+
+impl From<Entry> for u128 {
+    fn from(entry: Entry) -> Self {
+        u128::from(entry.hi) << 64 | u128::from(entry.lo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entry() {
+        let value = 0;
+        let entry = Entry::from(value);
+        assert_eq!(entry.hi, 0);
+        assert_eq!(entry.lo, 0);
+        assert_eq!(u128::from(entry), value);
+
+        let value = 0x0000_0000_0000_0001_0000_0000_0000_0000;
+        let entry = Entry::from(value);
+        assert_eq!(entry.hi, 0x0000_0000_0000_0001);
+        assert_eq!(entry.lo, 0x0000_0000_0000_0000);
+        assert_eq!(u128::from(entry), value);
+
+        let value = 0xFEED_FACE_CAFE_BEEF_DEAD_C0DE_BAAD_F00D;
+        let entry = Entry::from(value);
+        assert_eq!(entry.hi, 0xFEED_FACE_CAFE_BEEF);
+        assert_eq!(entry.lo, 0xDEAD_C0DE_BAAD_F00D);
+        assert_eq!(u128::from(entry), value);
+    }
+}
@@ -0,0 +1,66 @@
+use super::Entry;
+use crate::{RoaringTreeMap, RoaringTreeMapIter};
+use alloc::collections::btree_map;
+
+type TreeMapFlatIter<'a> = core::iter::FlatMap<
+    btree_map::Iter<'a, u64, RoaringTreeMap>,
+    BitmapIter<'a>,
+    fn((&'a u64, &'a RoaringTreeMap)) -> BitmapIter<'a>,
+>;
+
+/// Immutable Roaring 128-bit bitmap iterator.
+///
+/// This struct is created by the `iter` method on Roaring 128-bit bitmap.
+pub struct Iter<'a> {
+    inner: TreeMapFlatIter<'a>,
+    size: usize,
+}
+
+impl<'a> Iter<'a> {
+    pub(super) fn new(
+        bitmaps: btree_map::Iter<'a, u64, RoaringTreeMap>,
+    ) -> Self {
+        Self {
+            inner: bitmaps.clone().flat_map(Into::into),
+            size: bitmaps.fold(0, |acc, bitmap| acc + bitmap.1.cardinality()),
+        }
+    }
+}
+
+impl Iterator for Iter<'_> {
+    type Item = u128;
+
+    fn next(&mut self) -> Option<u128> {
+        self.size = self.size.saturating_sub(1);
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.size, Some(self.size))
+    }
+}
+
+/// Tree-Map bitmap iterator wrapper, containing the associated key as well.
+struct BitmapIter<'a> {
+    key: u64,
+    inner: RoaringTreeMapIter<'a>,
+}
+
+impl<'a> From<(&'a u64, &'a RoaringTreeMap)> for BitmapIter<'a> {
+    fn from(entry: (&'a u64, &'a RoaringTreeMap)) -> Self {
+        Self {
+            key: *entry.0,
+            inner: entry.1.iter(),
+        }
+    }
+}
+
+impl Iterator for BitmapIter<'_> {
+    type Item = u128;
+
+    fn next(&mut self) -> Option<u128> {
+        self.inner
+            .next()
+            .map(|value| Entry::from_parts(self.key, value).into())
+    }
+}
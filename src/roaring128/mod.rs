@@ -0,0 +1,9 @@
+mod bitmap;
+mod entry;
+mod iter;
+
+pub use bitmap::Bitmap as Roaring128;
+pub use iter::Iter as Roaring128Iter;
+
+use entry::Entry;
+use iter::Iter;
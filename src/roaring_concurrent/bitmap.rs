@@ -0,0 +1,210 @@
+use super::Entry;
+use crate::Roaring;
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Default number of shards.
+const DEFAULT_SHARD_COUNT: usize = 16;
+
+/// Compressed bitmap for 32-bit integers, supporting concurrent updates.
+///
+/// Chunks are spread across a fixed set of shards, each behind its own lock:
+/// threads working on values from different shards don't contend.
+pub struct Bitmap {
+    /// Bitmap shards, the chunk `key` being held by the shard
+    /// `key % shards.len()`.
+    shards: Box<[RwLock<Roaring>]>,
+}
+
+impl Bitmap {
+    /// Create an empty bitmap.
+    pub fn new() -> Self {
+        Self::with_shards(DEFAULT_SHARD_COUNT)
+    }
+
+    /// Create an empty bitmap, spread across `count` shards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `count` is zero.
+    pub fn with_shards(count: usize) -> Self {
+        assert!(count > 0, "at least one shard is required");
+
+        Self {
+            shards: (0..count).map(|_| RwLock::default()).collect(),
+        }
+    }
+
+    /// Adds a value to the bitmap.
+    ///
+    /// If the bitmap did not have this value present, true is returned.
+    /// If the bitmap did have this value present, false is returned.
+    pub fn insert(&self, value: u32) -> bool {
+        self.write(value).insert(value)
+    }
+
+    /// Removes a value from the bitmap.
+    ///
+    /// Returns whether the value was present or not.
+    pub fn remove(&self, value: u32) -> bool {
+        self.write(value).remove(value)
+    }
+
+    /// Returns true if the bitmap contains the value.
+    pub fn contains(&self, value: u32) -> bool {
+        self.read(value).contains(value)
+    }
+
+    /// Computes the bitmap cardinality.
+    ///
+    /// Shards are visited one after the other: concurrent updates may or may
+    /// not be accounted for.
+    pub fn cardinality(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| lock_read(shard).cardinality())
+            .sum()
+    }
+
+    /// Clears the bitmap, removing all values.
+    pub fn clear(&self) {
+        for shard in &self.shards {
+            lock_write(shard).clear();
+        }
+    }
+
+    /// Returns true if the bitmap contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|shard| lock_read(shard).is_empty())
+    }
+
+    /// Returns the approximate in-memory size of the bitmap, in bytes.
+    pub fn mem_size(&self) -> usize {
+        size_of_val(self)
+            + self.shards.iter().fold(0, |acc, shard| {
+                acc + size_of_val(shard) - size_of::<Roaring>()
+                    + lock_read(shard).mem_size()
+            })
+    }
+
+    /// Merges the shards into a regular bitmap.
+    ///
+    /// Shards are visited one after the other: concurrent updates may or may
+    /// not be part of the result.
+    pub fn snapshot(&self) -> Roaring {
+        self.shards
+            .iter()
+            .fold(Roaring::new(), |acc, shard| acc.union(&lock_read(shard)))
+    }
+
+    /// Locks the shard holding `value` for reading.
+    fn read(&self, value: u32) -> RwLockReadGuard<'_, Roaring> {
+        lock_read(self.shard(value))
+    }
+
+    /// Locks the shard holding `value` for writing.
+    fn write(&self, value: u32) -> RwLockWriteGuard<'_, Roaring> {
+        lock_write(self.shard(value))
+    }
+
+    /// Returns the shard holding `value`.
+    fn shard(&self, value: u32) -> &RwLock<Roaring> {
+        let key = usize::from(Entry::from(value).hi);
+        &self.shards[key % self.shards.len()]
+    }
+}
+
+/// Locks a shard for reading.
+///
+/// A panic while holding a lock can't leave the bitmap in an invalid state:
+/// poisoning is ignored.
+fn lock_read(shard: &RwLock<Roaring>) -> RwLockReadGuard<'_, Roaring> {
+    shard.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Locks a shard for writing.
+///
+/// A panic while holding a lock can't leave the bitmap in an invalid state:
+/// poisoning is ignored.
+fn lock_write(shard: &RwLock<Roaring>) -> RwLockWriteGuard<'_, Roaring> {
+    shard.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl Default for Bitmap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<Roaring> for Bitmap {
+    fn from(bitmap: Roaring) -> Self {
+        let mut shards = (0..DEFAULT_SHARD_COUNT)
+            .map(|_| Roaring::new())
+            .collect::<Vec<_>>();
+        // Values are visited in ascending order: they can be appended.
+        for value in &bitmap {
+            let key = usize::from(Entry::from(value).hi);
+            shards[key % DEFAULT_SHARD_COUNT].push(value);
+        }
+
+        Self {
+            shards: shards.into_iter().map(RwLock::new).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insertion_deletion() {
+        let bitmap = Bitmap::with_shards(4);
+        assert!(bitmap.is_empty());
+        assert_eq!(bitmap.cardinality(), 0);
+
+        assert!(bitmap.insert(42));
+        assert!(!bitmap.insert(42), "already exists");
+        assert!(bitmap.insert(5 << 16));
+        assert!(bitmap.contains(42));
+        assert!(bitmap.contains(5 << 16));
+        assert_eq!(bitmap.cardinality(), 2);
+
+        assert!(bitmap.remove(42));
+        assert!(!bitmap.remove(42), "missing entry");
+        assert!(!bitmap.contains(42));
+        assert_eq!(bitmap.cardinality(), 1);
+
+        bitmap.clear();
+        assert!(bitmap.is_empty());
+    }
+
+    #[test]
+    fn concurrent_insertion() {
+        let bitmap = Bitmap::new();
+        std::thread::scope(|scope| {
+            for thread in 0..8 {
+                let bitmap = &bitmap;
+                scope.spawn(move || {
+                    for value in (thread..200_000).step_by(8) {
+                        bitmap.insert(value);
+                    }
+                });
+            }
+        });
+
+        assert_eq!(bitmap.cardinality(), 200_000);
+        let snapshot = bitmap.snapshot();
+        assert!(snapshot.iter().eq(0..200_000));
+    }
+
+    #[test]
+    fn from_roaring() {
+        let values = (0..1_000_000).step_by(7);
+        let roaring = values.clone().collect::<Roaring>();
+        let bitmap = Bitmap::from(values.collect::<Roaring>());
+
+        assert_eq!(bitmap.cardinality(), roaring.cardinality());
+        assert!(bitmap.snapshot().iter().eq(roaring.iter()));
+        assert!(bitmap.mem_size() > 0);
+    }
+}
@@ -0,0 +1,5 @@
+mod bitmap;
+
+pub use bitmap::Bitmap as ConcurrentRoaring;
+
+use crate::roaring::Entry;
@@ -0,0 +1,2107 @@
+use super::{
+    index::ChunkIndex,
+    iter::{Ranges, SetOpIter, UnionIter},
+    patch::Change,
+    ChunkKey, IntoIter, Iter, MemLimit, Patch, Quota,
+};
+use crate::{
+    containers::{Operation, Pool, BITMAP_WORD_COUNT},
+    Chunk, ChunkInfo, Container, ContainerKind, ContainerView, FrozenRoaring,
+    NotSortedError, QuotaExceededError, Stats, Tuning,
+};
+use alloc::{boxed::Box, collections::BTreeSet, sync::Arc, vec, vec::Vec};
+use core::{
+    cmp::Ordering,
+    convert::Infallible,
+    iter::Peekable,
+    mem,
+    ops::{Bound, RangeBounds, RangeInclusive},
+    panic::RefUnwindSafe,
+    slice,
+    sync::atomic::{AtomicUsize, Ordering as AtomicOrdering},
+};
+// Without `std`, the chunk index is never built.
+#[cfg(not(feature = "std"))]
+use super::index::NoIndex as OnceLock;
+#[cfg(feature = "std")]
+use std::{collections::HashSet, sync::OnceLock};
+
+#[cfg(feature = "rand")]
+use rand::Rng;
+
+/// Number of chunks from which point lookups go through a chunk index.
+const CHUNK_INDEX_THRESHOLD: usize = 1_024;
+/// Number of values covered by a chunk.
+const CHUNK_SIZE: i64 = 1 << 16;
+
+/// Set operation between two containers.
+pub(super) type ContainerOp =
+    fn(&Container, &Container) -> Option<(Container, usize)>;
+
+/// Compressed bitmap made of a flat, sorted, list of chunks.
+///
+/// This is the implementation shared by the Roaring variants that only differ
+/// by the width of their chunk keys: use it through the
+/// [`Roaring`](crate::Roaring) and
+/// [`RoaringTwoLevels`](crate::RoaringTwoLevels) aliases.
+pub struct RoaringCore<K: ChunkKey> {
+    /// Bitmap chunks, indexed by the most significant bits of the integer.
+    pub(super) chunks: Vec<Chunk<K::Header>>,
+    /// Number of values in the bitmap, kept up to date by every update.
+    ///
+    /// `None` when caching is disabled: the chunks are summed on demand.
+    cardinality: Option<usize>,
+    /// Index of the chunk keys, built on the first lookup once the bitmap has
+    /// enough chunks (with `std` only) and dropped whenever a chunk is added
+    /// or removed.
+    index: OnceLock<ChunkIndex<K>>,
+    /// Position of the last chunk found by a lookup, checked first by the
+    /// next one.
+    ///
+    /// Never invalidated: a stale position is detected by comparing keys.
+    hot: AtomicUsize,
+    /// Allocations of discarded containers, for the next conversions.
+    pool: Pool,
+    /// Limits enforced by the fallible insertions.
+    quota: Quota,
+    /// Keys of the chunks modified since the last `mark_clean`, if tracked.
+    dirty: Option<BTreeSet<K>>,
+    /// Callback invoked when a chunk converts its container.
+    on_conversion: Option<ConversionHook<K>>,
+    /// Soft limit on the in-memory size, compacting the bitmap once exceeded.
+    mem_limit: Option<MemLimit>,
+    /// Callback invoked after a compaction triggered by the soft limit.
+    on_compaction: Option<CompactionHook>,
+    /// Settings driving the choice of the containers on updates.
+    tuning: Tuning,
+}
+
+/// Callback receiving the key of a chunk, and its previous and new container
+/// kinds.
+type ConversionHook<K> =
+    Arc<dyn Fn(K, ContainerKind, ContainerKind) + Send + Sync + RefUnwindSafe>;
+
+/// Callback receiving the in-memory size of a bitmap before and after a
+/// compaction, in bytes.
+type CompactionHook = Arc<dyn Fn(usize, usize) + Send + Sync + RefUnwindSafe>;
+
+impl<K: ChunkKey> RoaringCore<K> {
+    /// Create an empty bitmap.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds a bitmap from values sorted in ascending order, appending each
+    /// chunk in turn instead of searching for it.
+    ///
+    /// Duplicated values are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error as soon as a value is smaller than its predecessor.
+    pub fn from_sorted_iter<I: IntoIterator<Item = K::Value>>(
+        iterator: I,
+    ) -> Result<Self, NotSortedError> {
+        let mut bitmap = Self::new();
+        let mut batch = Vec::new();
+        let mut key = None;
+        let mut last = None;
+
+        for value in iterator {
+            match last {
+                Some(last) if value == last => continue,
+                Some(last) if value < last => {
+                    return Err(NotSortedError::new());
+                },
+                _ => (),
+            }
+            last = Some(value);
+
+            let (hi, lo) = K::split(value);
+            if key != Some(hi) {
+                if let Some(key) = key {
+                    bitmap.push_chunk(key, &batch);
+                }
+                batch.clear();
+                key = Some(hi);
+            }
+            batch.push(lo);
+        }
+        if let Some(key) = key {
+            bitmap.push_chunk(key, &batch);
+        }
+
+        Ok(bitmap)
+    }
+
+    /// Adds a value to the bitmap.
+    ///
+    /// If the bitmap did not have this value present, true is returned.
+    /// If the bitmap did have this value present, false is returned.
+    pub fn insert(&mut self, value: K::Value) -> bool {
+        let (key, lo) = K::split(value);
+
+        let added = match self.search(key) {
+            Ok(index) => {
+                self.update_chunk(index, |chunk, pool, tuning| {
+                    chunk.insert_in(lo, pool, tuning)
+                })
+            },
+            Err(index) => {
+                self.insert_chunk(index, Chunk::new(key.header(), lo));
+                true
+            },
+        };
+        if added {
+            self.touch(key);
+        }
+        self.add_cardinality(usize::from(added));
+        added
+    }
+
+    /// Adds a value to the bitmap, unless it would exceed the bitmap's quota.
+    ///
+    /// If the bitmap did not have this value present, true is returned.
+    /// If the bitmap did have this value present, false is returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, leaving the bitmap untouched, if the value would
+    /// exceed the maximum cardinality or memory footprint of the bitmap (the
+    /// latter being estimated, pessimistically, before the insertion).
+    pub fn try_insert(
+        &mut self,
+        value: K::Value,
+    ) -> Result<bool, QuotaExceededError> {
+        if self.contains(value) {
+            return Ok(false);
+        }
+        if self.quota.is_full(self.cardinality())
+            || self.quota.max_mem_size.is_some()
+                && self.quota.is_too_large(
+                    self.mem_size() + self.insert_mem_delta(value),
+                )
+        {
+            return Err(QuotaExceededError::new());
+        }
+        self.insert(value);
+
+        Ok(true)
+    }
+
+    /// Estimates by how many bytes the bitmap would grow if `value`, absent
+    /// from it, was inserted.
+    fn insert_mem_delta(&self, value: K::Value) -> usize {
+        let (key, lo) = K::split(value);
+
+        match self.search(key) {
+            Ok(index) => self.chunks[index].insert_mem_delta(lo, &self.tuning),
+            Err(_) => Chunk::new(key.header(), lo).mem_size(),
+        }
+    }
+
+    /// Appends a value greater than every value in the bitmap, without
+    /// searching for its chunk.
+    ///
+    /// Returns false, leaving the bitmap untouched, if the value is not
+    /// greater than the current maximum.
+    pub fn push(&mut self, value: K::Value) -> bool {
+        let (key, lo) = K::split(value);
+
+        let pushed = match self.chunks.last() {
+            Some(chunk) if chunk.key() == key => {
+                chunk.max().is_some_and(|max| max < lo)
+                    && self.update_chunk(
+                        self.chunks.len() - 1,
+                        |chunk, pool, tuning| chunk.insert_in(lo, pool, tuning),
+                    )
+            },
+            Some(chunk) if chunk.key() > key => false,
+            _ => {
+                self.insert_chunk(
+                    self.chunks.len(),
+                    Chunk::new(key.header(), lo),
+                );
+                true
+            },
+        };
+        if pushed {
+            self.touch(key);
+        }
+        self.add_cardinality(usize::from(pushed));
+        pushed
+    }
+
+    /// Removes a value from the bitmap.
+    ///
+    /// Returns whether the value was present or not.
+    pub fn remove(&mut self, value: K::Value) -> bool {
+        let (key, lo) = K::split(value);
+
+        self.search(key).is_ok_and(|index| {
+            let old_cardinality = self.chunks[index].cardinality();
+            let removed = self.update_chunk(index, |chunk, pool, tuning| {
+                chunk.remove_in(lo, pool, tuning)
+            });
+            if removed {
+                self.touch(key);
+            }
+
+            // Chunk is now empty (last element removed), delete it.
+            if old_cardinality == 1 && removed {
+                self.remove_chunk(index);
+            }
+            self.sub_cardinality(usize::from(removed));
+            removed
+        })
+    }
+
+    /// Adds a value to the bitmap, without converting its container to a
+    /// better representation.
+    ///
+    /// Useful for bulk updates, to avoid converting a container back and forth
+    /// (e.g. between array and bitmap): `optimize` must be called once done.
+    ///
+    /// Returns whether the value was added or not.
+    pub fn insert_deferred(&mut self, value: K::Value) -> bool {
+        let (key, lo) = K::split(value);
+
+        let added = match self.search(key) {
+            Ok(index) => {
+                self.update_chunk(index, |chunk, _, _| {
+                    chunk.insert_deferred(lo)
+                })
+            },
+            Err(index) => {
+                self.insert_chunk(index, Chunk::new(key.header(), lo));
+                true
+            },
+        };
+        if added {
+            self.touch(key);
+        }
+        self.add_cardinality(usize::from(added));
+        added
+    }
+
+    /// Removes a value from the bitmap, without converting its container to
+    /// a better representation.
+    ///
+    /// Useful for bulk updates, to avoid converting a container back and forth
+    /// (e.g. between array and bitmap): `optimize` must be called once done.
+    ///
+    /// Returns whether the value was present or not.
+    pub fn remove_deferred(&mut self, value: K::Value) -> bool {
+        let (key, lo) = K::split(value);
+
+        self.search(key).is_ok_and(|index| {
+            let old_cardinality = self.chunks[index].cardinality();
+            let removed = self
+                .update_chunk(index, |chunk, _, _| chunk.remove_deferred(lo));
+            if removed {
+                self.touch(key);
+            }
+
+            // Chunk is now empty (last element removed), delete it.
+            if old_cardinality == 1 && removed {
+                self.remove_chunk(index);
+            }
+            self.sub_cardinality(usize::from(removed));
+            removed
+        })
+    }
+
+    /// Converts every container to the best representation for its
+    /// cardinality, after deferred updates.
+    pub fn optimize(&mut self) {
+        for index in 0..self.chunks.len() {
+            self.update_chunk(index, |chunk, pool, tuning| {
+                chunk.optimize_container_in(pool, tuning);
+            });
+        }
+    }
+
+    /// Returns true if the bitmap contains the value.
+    pub fn contains(&self, value: K::Value) -> bool {
+        let (key, lo) = K::split(value);
+
+        self.indexed_search(key)
+            .is_ok_and(|index| self.chunks[index].contains(lo))
+    }
+
+    /// Returns true if the bitmap contains every value of the slice.
+    ///
+    /// Values are grouped by chunk, each chunk being looked up only once, and
+    /// the check stops at the first missing value.
+    pub fn contains_all(&self, values: &[K::Value]) -> bool {
+        self.probe(values, true)
+    }
+
+    /// Returns true if the bitmap contains at least one value of the slice.
+    ///
+    /// Values are grouped by chunk, each chunk being looked up only once, and
+    /// the check stops at the first value found.
+    pub fn contains_any(&self, values: &[K::Value]) -> bool {
+        self.probe(values, false)
+    }
+
+    /// Returns the bitmap cardinality.
+    ///
+    /// The cardinality is cached by default, making this a constant-time
+    /// operation; otherwise it sums the cardinalities of the chunks.
+    pub fn cardinality(&self) -> usize {
+        self.cardinality.unwrap_or_else(|| {
+            self.chunks
+                .iter()
+                .fold(0, |acc, chunk| acc + chunk.cardinality())
+        })
+    }
+
+    /// Finds the smallest value in the bitmap.
+    ///
+    /// Chunks are sorted and never empty: only the first one is looked at.
+    pub fn min(&self) -> Option<K::Value> {
+        self.chunks
+            .first()
+            .and_then(|chunk| chunk.min().map(|min| chunk.key().join(min)))
+    }
+
+    /// Finds the largest value in the bitmap.
+    ///
+    /// Chunks are sorted and never empty: only the last one is looked at.
+    pub fn max(&self) -> Option<K::Value> {
+        self.chunks
+            .last()
+            .and_then(|chunk| chunk.max().map(|max| chunk.key().join(max)))
+    }
+
+    /// Returns the number of values in the bitmap lower than or equal to
+    /// `value`.
+    ///
+    /// Only the chunk cardinalities are summed up to the chunk of `value`,
+    /// which is the only container looked at.
+    pub fn rank(&self, value: K::Value) -> usize {
+        let (key, lo) = K::split(value);
+        let index = self.chunks.partition_point(|chunk| chunk.key() < key);
+        let rank = self.chunks[..index]
+            .iter()
+            .fold(0, |acc, chunk| acc + chunk.cardinality());
+
+        match self.chunks.get(index) {
+            Some(chunk) if chunk.key() == key => {
+                rank + chunk.container().rank(lo)
+            },
+            _ => rank,
+        }
+    }
+
+    /// Returns the `n`-th smallest value in the bitmap (starting from 0), if
+    /// any.
+    ///
+    /// Only the chunk cardinalities are scanned, the value being then
+    /// selected in the container of its chunk without decoding the others.
+    pub fn select(&self, mut n: usize) -> Option<K::Value> {
+        for chunk in &self.chunks {
+            let cardinality = chunk.cardinality();
+            if n < cardinality {
+                return chunk
+                    .container()
+                    .select(n)
+                    .map(|lo| chunk.key().join(lo));
+            }
+            n -= cardinality;
+        }
+        None
+    }
+
+    /// Picks a value of the bitmap uniformly at random.
+    #[cfg(feature = "rand")]
+    pub fn random<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<K::Value> {
+        if self.is_empty() {
+            return None;
+        }
+        self.select(rng.gen_range(0..self.cardinality()))
+    }
+
+    /// Clears the bitmap, removing all values.
+    pub fn clear(&mut self) {
+        self.touch_all();
+        self.chunks.clear();
+        self.index.take();
+        self.recount();
+        self.measure_mem_limit();
+    }
+
+    /// Returns true if the bitmap contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Replaces the values of the bitmap by the ones of `other`, keeping its
+    /// settings (quota, tuning, tracking, callback).
+    ///
+    /// Containers are copied in place when the chunks at the same position
+    /// share the same kind, reusing their allocations.
+    pub fn overwrite_from(&mut self, other: &Self) {
+        self.touch_all();
+        for chunk in self
+            .chunks
+            .drain(other.chunks.len().min(self.chunks.len())..)
+        {
+            self.pool.recycle(chunk.into_container());
+        }
+        let (shared, extra) = other.chunks.split_at(self.chunks.len());
+        for (dst, src) in self.chunks.iter_mut().zip(shared) {
+            dst.clone_from(src);
+        }
+        self.chunks.extend_from_slice(extra);
+        self.touch_all();
+
+        self.recount();
+        self.index.take();
+        self.measure_mem_limit();
+    }
+
+    /// Gets an iterator that visits the values in the bitmap in ascending
+    /// order.
+    pub fn iter(&self) -> Iter<'_, K> {
+        Iter::new(self.chunks.iter())
+    }
+
+    /// Calls `f` on each value of the bitmap in ascending order.
+    ///
+    /// Faster than going through `iter`: each container is decoded by a tight
+    /// loop over its values, words or runs.
+    pub fn for_each(&self, mut f: impl FnMut(K::Value)) {
+        let Ok(()) = self.try_for_each(|value| {
+            f(value);
+            Ok::<_, Infallible>(())
+        });
+    }
+
+    /// Calls the fallible `f` on each value of the bitmap in ascending order,
+    /// stopping at the first error.
+    ///
+    /// See `for_each`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error returned by `f`.
+    pub fn try_for_each<E>(
+        &self,
+        mut f: impl FnMut(K::Value) -> Result<(), E>,
+    ) -> Result<(), E> {
+        for chunk in &self.chunks {
+            let key = chunk.key();
+            chunk.container().try_for_each(|lo| f(key.join(lo)))?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the union of two bitmaps, as a new bitmap.
+    #[must_use]
+    pub fn union(&self, other: &Self) -> Self {
+        self.merge(other, Container::or, true, true)
+    }
+
+    /// Returns the intersection of two bitmaps, as a new bitmap.
+    #[must_use]
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.merge(other, Container::and, false, false)
+    }
+
+    /// Gets the first `limit` values shared with `other` in ascending order,
+    /// and whether more exist.
+    ///
+    /// Containers are intersected one pair at a time, the last one only up to
+    /// the limit: the remaining chunks aren't visited once it's hit.
+    pub fn intersection_with_limit(
+        &self,
+        other: &Self,
+        limit: usize,
+    ) -> (Vec<K::Value>, bool) {
+        let mut values = Vec::new();
+        let mut buffer = Vec::new();
+
+        for pair in self.pairs(other) {
+            let (Some(lhs), Some(rhs)) = pair else {
+                continue;
+            };
+            // One extra value tells whether more exist.
+            buffer.clear();
+            lhs.container().and_limited(
+                rhs.container(),
+                (limit - values.len()).saturating_add(1),
+                &mut buffer,
+            );
+            let key = lhs.key();
+            values.extend(buffer.iter().map(|&lo| key.join(lo)));
+            if values.len() > limit {
+                values.truncate(limit);
+                return (values, true);
+            }
+        }
+
+        (values, false)
+    }
+
+    /// Counts the values shared with each of the other bitmaps, walking the
+    /// bitmap's chunks only once.
+    ///
+    /// Intersections are counted container by container, without being
+    /// built.
+    pub fn intersection_len_many(&self, others: &[&Self]) -> Vec<usize> {
+        let mut lens = vec![0; others.len()];
+        // Position of the next chunk to probe in each of the other bitmaps.
+        let mut positions = vec![0; others.len()];
+
+        for chunk in &self.chunks {
+            let key = chunk.key();
+            for ((other, position), len) in
+                others.iter().zip(&mut positions).zip(&mut lens)
+            {
+                *position += other.chunks[*position..]
+                    .partition_point(|other| other.key() < key);
+                if let Some(other) = other
+                    .chunks
+                    .get(*position)
+                    .filter(|other| other.key() == key)
+                {
+                    *len += chunk.container().and_len(other.container());
+                    *position += 1;
+                }
+            }
+        }
+
+        lens
+    }
+
+    /// Estimates the number of values in both `self` and `other`, by only
+    /// intersecting a sample of the chunks of `self`.
+    ///
+    /// `precision` is the fraction of the chunks to sample, from 0 (a single
+    /// one) to 1 (every chunk, giving the exact count). Chunks are picked
+    /// with a probability proportional to their cardinality, in a
+    /// deterministic way.
+    pub fn approx_intersection_len(
+        &self,
+        other: &Self,
+        precision: f64,
+    ) -> usize {
+        // Clamped to the number of chunks: no truncation nor sign loss.
+        #[allow(
+            clippy::cast_possible_truncation,
+            clippy::cast_precision_loss,
+            clippy::cast_sign_loss
+        )]
+        let sample_size = ((precision.clamp(0.0, 1.0)
+            * self.chunks.len() as f64) as usize)
+            .max(1);
+        if self.is_empty() || sample_size >= self.chunks.len() {
+            return self.intersection_len_many(&[other])[0];
+        }
+
+        // Systematic sampling: a chunk is picked when one of the points,
+        // evenly spaced over the values of `self`, falls into it.
+        let step = self.cardinality() / sample_size;
+        let mut point = step / 2;
+        let mut offset = 0;
+        let mut estimate = 0;
+        for chunk in &self.chunks {
+            let cardinality = chunk.cardinality();
+            offset += cardinality;
+            if point >= offset {
+                continue;
+            }
+            while point < offset {
+                point += step;
+            }
+
+            if let Ok(index) = other.search(chunk.key()) {
+                let len =
+                    chunk.container().and_len(other.chunks[index].container());
+                // Weighted by the inverse of the chunk's inclusion probability.
+                estimate += if cardinality >= step {
+                    len
+                } else {
+                    len * step / cardinality
+                };
+            }
+        }
+
+        estimate
+    }
+
+    /// Gets an iterator that visits the values in both `self` and `other` in
+    /// ascending order, computing the intersection lazily.
+    pub fn intersection_iter<'a>(
+        &'a self,
+        other: &'a Self,
+    ) -> impl Iterator<Item = K::Value> + 'a {
+        SetOpIter::new(self.pairs(other), |lhs, rhs| lhs && rhs)
+    }
+
+    /// Gets an iterator that visits the values in `self` but not in `other`
+    /// in ascending order, computing the difference lazily.
+    pub fn difference_iter<'a>(
+        &'a self,
+        other: &'a Self,
+    ) -> impl Iterator<Item = K::Value> + 'a {
+        SetOpIter::new(self.pairs(other), |lhs, rhs| lhs && !rhs)
+    }
+
+    /// Gets an iterator that visits the values in `self` or in `other` but
+    /// not in both in ascending order, computing the symmetric difference
+    /// lazily.
+    pub fn symmetric_difference_iter<'a>(
+        &'a self,
+        other: &'a Self,
+    ) -> impl Iterator<Item = K::Value> + 'a {
+        SetOpIter::new(self.pairs(other), |lhs, rhs| lhs != rhs)
+    }
+
+    /// Gets an iterator that visits the values in any of the bitmaps in
+    /// ascending order, computing the union lazily.
+    ///
+    /// The bitmaps are merged value by value through a heap: memory usage
+    /// doesn't depend on the size of the union.
+    pub fn union_iter<'a>(
+        bitmaps: &[&'a Self],
+    ) -> impl Iterator<Item = K::Value> + 'a {
+        UnionIter::new(bitmaps.iter().map(|bitmap| bitmap.iter()))
+    }
+
+    /// Returns the values in `self` but not in `other`, as a new bitmap.
+    #[must_use]
+    pub fn difference(&self, other: &Self) -> Self {
+        self.merge(other, Container::and_not, true, false)
+    }
+
+    /// Returns the values in `self` or in `other` but not in both, as a new
+    /// bitmap.
+    #[must_use]
+    pub fn symmetric_difference(&self, other: &Self) -> Self {
+        self.merge(other, Container::xor, true, true)
+    }
+
+    /// Replaces the bitmap by the union of `lhs` and `rhs`.
+    ///
+    /// The bitmap's chunk list and container allocations are reused, which
+    /// avoids hammering the allocator when repeatedly combining bitmaps.
+    pub fn assign_union(&mut self, lhs: &Self, rhs: &Self) {
+        self.assign(lhs, rhs, Operation::Or);
+    }
+
+    /// Replaces the bitmap by the intersection of `lhs` and `rhs`.
+    ///
+    /// The bitmap's chunk list and container allocations are reused, which
+    /// avoids hammering the allocator when repeatedly combining bitmaps.
+    pub fn assign_intersection(&mut self, lhs: &Self, rhs: &Self) {
+        self.assign(lhs, rhs, Operation::And);
+    }
+
+    /// Replaces the bitmap by the values in `lhs` but not in `rhs`.
+    ///
+    /// The bitmap's chunk list and container allocations are reused, which
+    /// avoids hammering the allocator when repeatedly combining bitmaps.
+    pub fn assign_difference(&mut self, lhs: &Self, rhs: &Self) {
+        self.assign(lhs, rhs, Operation::AndNot);
+    }
+
+    /// Replaces the bitmap by the values in `lhs` or in `rhs` but not in both.
+    ///
+    /// The bitmap's chunk list and container allocations are reused, which
+    /// avoids hammering the allocator when repeatedly combining bitmaps.
+    pub fn assign_symmetric_difference(&mut self, lhs: &Self, rhs: &Self) {
+        self.assign(lhs, rhs, Operation::Xor);
+    }
+
+    /// Converts every container to its most compact representation, using run
+    /// containers where they are smaller.
+    ///
+    /// Returns true if the bitmap contains at least one run container.
+    pub fn run_optimize(&mut self) -> bool {
+        let mut has_runs = false;
+        for index in 0..self.chunks.len() {
+            has_runs |=
+                self.update_chunk(index, |chunk, _, _| chunk.run_optimize());
+        }
+        has_runs
+    }
+
+    /// Converts every run container back into an array or bitmap container.
+    ///
+    /// Returns true if at least one container was converted.
+    pub fn remove_run_compression(&mut self) -> bool {
+        let mut converted = false;
+        for index in 0..self.chunks.len() {
+            converted |= self.update_chunk(index, |chunk, _, tuning| {
+                chunk.remove_run_compression(tuning)
+            });
+        }
+        converted
+    }
+
+    /// Returns the approximate in-memory size of the bitmap, in bytes.
+    pub fn mem_size(&self) -> usize {
+        size_of_val(self)
+            + self
+                .chunks
+                .iter()
+                .fold(0, |acc, chunk| acc + chunk.mem_size())
+            + self.index.get().map_or(0, ChunkIndex::mem_size)
+            + self.pool.mem_size()
+    }
+
+    /// Counts the runs of consecutive values in the containers, without
+    /// converting them.
+    ///
+    /// A run spanning several chunks counts once per chunk, as it would be
+    /// stored by run containers: compare with the cardinality to estimate the
+    /// gains of `run_optimize`.
+    pub fn count_runs(&self) -> usize {
+        self.chunks
+            .iter()
+            .fold(0, |acc, chunk| acc + chunk.container().count_runs())
+    }
+
+    /// Gets an iterator that visits the maximal runs of consecutive values in
+    /// the bitmap in ascending order.
+    pub fn iter_ranges(
+        &self,
+    ) -> impl Iterator<Item = RangeInclusive<K::Value>> + '_ {
+        Ranges::<K>::new(self.chunks.iter())
+    }
+
+    /// Gets an iterator over the keys of the bitmap's chunks (i.e. the most
+    /// significant bits of their values), in ascending order.
+    ///
+    /// Chunks are never empty: each key has at least one value.
+    pub fn keys(&self) -> impl Iterator<Item = K> + '_ {
+        self.chunks.iter().map(Chunk::key)
+    }
+
+    /// Gets an iterator over the key and the cardinality of the bitmap's
+    /// chunks, in ascending key order.
+    ///
+    /// Each chunk covers a block of 2¹⁶ values: this gives the occupancy of
+    /// the populated blocks of the value space.
+    pub fn density_profile(&self) -> impl Iterator<Item = (K, usize)> + '_ {
+        self.chunks
+            .iter()
+            .map(|chunk| (chunk.key(), chunk.cardinality()))
+    }
+
+    /// Gets an iterator over the layout of the bitmap's chunks, in ascending
+    /// key order.
+    pub fn chunks(&self) -> impl Iterator<Item = ChunkInfo<K>> + '_ {
+        self.chunks.iter().map(Chunk::info)
+    }
+
+    /// Gets an iterator over the key and a read-only view of the container of
+    /// the bitmap's chunks, in ascending key order.
+    ///
+    /// Useful to implement custom chunk-wise algorithms without copying the
+    /// values out of the bitmap.
+    pub fn container_views(
+        &self,
+    ) -> impl Iterator<Item = (K, ContainerView<'_>)> + '_ {
+        self.chunks.iter().map(|chunk| {
+            (
+                chunk.key(),
+                ContainerView::new(chunk.container(), chunk.cardinality()),
+            )
+        })
+    }
+
+    /// Folds the chunks of the bitmap in ascending key order, calling `fold`
+    /// with the accumulator, the key and a read-only view of each container.
+    ///
+    /// Sequential counterpart of `par_fold_chunks` (`rayon` feature).
+    pub fn fold_chunks<T>(
+        &self,
+        init: T,
+        mut fold: impl FnMut(T, K, ContainerView<'_>) -> T,
+    ) -> T {
+        self.container_views()
+            .fold(init, |acc, (key, view)| fold(acc, key, view))
+    }
+
+    /// Returns detailed statistics about the composition of the bitmap.
+    pub fn stats(&self) -> Stats<K::Value> {
+        let mut stats = Stats::new(
+            self.cardinality(),
+            self.mem_size(),
+            self.min(),
+            self.max(),
+        );
+        for chunk in &self.chunks {
+            stats.add_chunk(chunk);
+        }
+
+        stats
+    }
+
+    /// Registers a callback invoked whenever an update converts the
+    /// container of a chunk, with the chunk's key and the previous and new
+    /// container kinds.
+    ///
+    /// Replaces the previous callback, if any. Chunks rebuilt as a whole (e.g.
+    /// by `assign_union` or `add_offset`) aren't reported.
+    ///
+    /// The callback must be `RefUnwindSafe`, for the bitmap to remain so.
+    pub fn on_conversion(
+        &mut self,
+        hook: impl Fn(K, ContainerKind, ContainerKind)
+            + Send
+            + Sync
+            + RefUnwindSafe
+            + 'static,
+    ) {
+        self.on_conversion = Some(Arc::new(hook));
+    }
+
+    /// Sets a soft limit on the approximate in-memory size of the bitmap, in
+    /// bytes, or removes it.
+    ///
+    /// Once the limit is exceeded, the bitmap compacts itself instead of
+    /// growing unbounded: its containers are converted to their most compact
+    /// representation (see `run_optimize`) and their unused capacity is
+    /// released (see `shrink_to_fit`). When that's not enough, the next
+    /// compaction waits for the bitmap to grow by half.
+    ///
+    /// Unlike the quota, the limit never fails an update.
+    pub fn set_soft_mem_limit(&mut self, max: Option<usize>) {
+        self.mem_limit = max.map(|max| MemLimit::new(max, self.mem_size()));
+        self.enforce_mem_limit();
+    }
+
+    /// Returns the soft limit on the in-memory size of the bitmap, if any.
+    pub fn soft_mem_limit(&self) -> Option<usize> {
+        self.mem_limit.map(MemLimit::max)
+    }
+
+    /// Registers a callback invoked after each compaction triggered by the
+    /// soft memory limit, with the in-memory size of the bitmap before and
+    /// after, in bytes.
+    ///
+    /// Replaces the previous callback, if any. The callback must be
+    /// `RefUnwindSafe`, for the bitmap to remain so.
+    pub fn on_compaction(
+        &mut self,
+        hook: impl Fn(usize, usize) + Send + Sync + RefUnwindSafe + 'static,
+    ) {
+        self.on_compaction = Some(Arc::new(hook));
+    }
+
+    /// Releases the unused capacity of the bitmap: chunk list, container
+    /// buffers and allocations kept for later conversions.
+    pub fn shrink_to_fit(&mut self) {
+        self.chunks.shrink_to_fit();
+        for chunk in &mut self.chunks {
+            chunk.shrink_to_fit();
+        }
+        self.pool = Pool::default();
+    }
+
+    /// Starts tracking the chunks modified by the updates, every current
+    /// chunk being considered clean.
+    ///
+    /// Representation changes (e.g. `run_optimize`) aren't modifications.
+    pub fn track_dirty_chunks(&mut self) {
+        self.dirty = Some(BTreeSet::new());
+    }
+
+    /// Marks every chunk as clean, once the modified ones are persisted.
+    pub fn mark_clean(&mut self) {
+        if let Some(ref mut dirty) = self.dirty {
+            dirty.clear();
+        }
+    }
+
+    /// Gets an iterator over the keys of the chunks modified since the last
+    /// call to `mark_clean`, in ascending order.
+    ///
+    /// Empty unless the tracking is enabled by `track_dirty_chunks`.
+    pub fn dirty_keys(&self) -> impl Iterator<Item = K> + '_ {
+        self.dirty.iter().flatten().copied()
+    }
+}
+
+impl RoaringCore<u16> {
+    /// Initializes a bitmap from dense bitset words, the bit `i` of the word
+    /// `j` standing for the value `offset + j * 64 + i`.
+    ///
+    /// Words are copied in bulk, chunk by chunk. Bits past `u32::MAX` are
+    /// ignored.
+    pub fn from_bitset_words(offset: u32, words: &[u64]) -> Self {
+        // Words are realigned on multiples of 64 when the offset isn't.
+        let shift = offset % 64;
+        let first_word = offset as usize / 64;
+        let aligned_word = |index: usize| {
+            let word = |index: Option<usize>| {
+                index.and_then(|index| words.get(index)).copied()
+            };
+            let lo = word(index.checked_sub(first_word)).unwrap_or(0) << shift;
+            let hi = match shift {
+                0 => 0,
+                _ => {
+                    word(index.checked_sub(first_word + 1)).unwrap_or(0)
+                        >> (64 - shift)
+                },
+            };
+            lo | hi
+        };
+
+        // Last aligned word, including the bits spilled by the shift.
+        let last_word = first_word + words.len();
+        let mut chunks = Vec::new();
+        for key in first_word / BITMAP_WORD_COUNT
+            ..=(last_word / BITMAP_WORD_COUNT).min(u16::MAX.into())
+        {
+            let start = key * BITMAP_WORD_COUNT;
+            let mut chunk_words = Box::new([0; BITMAP_WORD_COUNT]);
+            for (index, word) in chunk_words.iter_mut().enumerate() {
+                *word = aligned_word(start + index);
+            }
+            if let Some((container, cardinality)) =
+                Container::from_words(chunk_words)
+            {
+                // Key is bounded by `u16::MAX` above.
+                #[allow(clippy::cast_possible_truncation)]
+                let header = (key as u16).header();
+                chunks.push(Chunk::from_container(
+                    header,
+                    container,
+                    cardinality,
+                ));
+            }
+        }
+
+        Self::from_chunks(chunks)
+    }
+
+    /// Exports the bitmap as dense bitset words, the bit `i` of the word `j`
+    /// standing for the value `j * 64 + i`.
+    ///
+    /// Words are copied in bulk, chunk by chunk, up to the one holding the
+    /// largest value.
+    pub fn to_bitset_words(&self) -> Vec<u64> {
+        let Some(max) = self.max() else {
+            return Vec::new();
+        };
+        let len = max as usize / 64 + 1;
+        let mut words = vec![0; len.next_multiple_of(BITMAP_WORD_COUNT)];
+        let (blocks, _) = words.as_chunks_mut();
+        for chunk in &self.chunks {
+            chunk
+                .container()
+                .write_words(&mut blocks[usize::from(chunk.key())]);
+        }
+        words.truncate(len);
+
+        words
+    }
+
+    /// Writes the values of the bitmap within `range` into dense bitset
+    /// words, the bit `i` of the word `j` standing for the value
+    /// `start + j * 64 + i`.
+    ///
+    /// The words covering the range are overwritten, the other ones are left
+    /// untouched. Containers are copied as whole words, bitmap ones without
+    /// any decoding.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `words` is too short to cover the range.
+    pub fn write_dense_into(
+        &self,
+        range: impl RangeBounds<u32>,
+        words: &mut [u64],
+    ) {
+        let Some(range) = inclusive_bounds(range) else {
+            return;
+        };
+        let (start, end) = (*range.start(), *range.end());
+        let bit_count = u64::from(end - start) + 1;
+        let len = usize::try_from(bit_count.div_ceil(64))
+            .expect("window fits in memory");
+        assert!(words.len() >= len, "buffer too short for the range");
+        let words = &mut words[..len];
+        words.fill(0);
+
+        let ((first_key, _), (last_key, _)) =
+            (u16::split(start), u16::split(end));
+        let first =
+            self.chunks.partition_point(|chunk| chunk.key() < first_key);
+        let mut chunk_words = Box::new([0; BITMAP_WORD_COUNT]);
+        for chunk in self.chunks[first..]
+            .iter()
+            .take_while(|chunk| chunk.key() <= last_key)
+        {
+            chunk_words.fill(0);
+            chunk.container().write_words(&mut chunk_words);
+            // Position of the chunk's first bit in the window.
+            let offset = (i64::from(chunk.key()) << 16) - i64::from(start);
+            for (&word, position) in
+                chunk_words.iter().zip((offset..).step_by(64))
+            {
+                if word == 0 {
+                    continue;
+                }
+                // Bits outside of the window land outside of the buffer.
+                let (index, bit) =
+                    (position.div_euclid(64), position.rem_euclid(64));
+                if let Some(slot) = usize::try_from(index)
+                    .ok()
+                    .and_then(|index| words.get_mut(index))
+                {
+                    *slot |= word << bit;
+                }
+                if bit != 0 {
+                    if let Some(slot) = usize::try_from(index + 1)
+                        .ok()
+                        .and_then(|index| words.get_mut(index))
+                    {
+                        *slot |= word >> (64 - bit);
+                    }
+                }
+            }
+        }
+        // Clear the bits past the end of the window.
+        let tail = bit_count % 64;
+        if tail != 0 {
+            words[len - 1] &= (1 << tail) - 1;
+        }
+    }
+
+    /// Returns the values of the bitmap in ascending order, as a vector.
+    ///
+    /// The vector is allocated once, with the exact cardinality, and filled
+    /// container by container.
+    pub fn to_vec(&self) -> Vec<u32> {
+        let mut values = vec![0; self.cardinality()];
+        let count = self.iter().next_many(&mut values);
+        debug_assert_eq!(count, values.len(), "cardinality mismatch");
+
+        values
+    }
+
+    /// Returns the next `page_size` values greater than `cursor` (or the first
+    /// ones, without cursor) in ascending order.
+    ///
+    /// The cursor's chunk is found through a binary search, then its
+    /// container is searched as well: pass the last value of a page as the
+    /// cursor of the next one to serve the pages without walking the previous
+    /// ones.
+    pub fn page_after(
+        &self,
+        cursor: Option<u32>,
+        page_size: usize,
+    ) -> Vec<u32> {
+        let mut iter = self.iter();
+        if let Some(cursor) = cursor {
+            let Some(start) = cursor.checked_add(1) else {
+                return Vec::new();
+            };
+            iter.advance_to(start);
+        }
+        let mut page = vec![0; page_size.min(self.cardinality())];
+        let count = iter.next_many(&mut page);
+        page.truncate(count);
+
+        page
+    }
+
+    /// Returns a new bitmap made of the values of the bitmap within `range`.
+    ///
+    /// The chunks fully covered by the range are copied as is: only the
+    /// containers at the boundaries of the range are sliced.
+    #[must_use]
+    pub fn clone_range(&self, range: impl RangeBounds<u32>) -> Self {
+        let Some(range) = inclusive_bounds(range) else {
+            return Self::new();
+        };
+        let (start_key, start) = u16::split(*range.start());
+        let (end_key, end) = u16::split(*range.end());
+
+        let from = self.chunks.partition_point(|chunk| chunk.key() < start_key);
+        let to = self.chunks.partition_point(|chunk| chunk.key() <= end_key);
+        let chunks = self.chunks[from..to]
+            .iter()
+            .filter_map(|chunk| {
+                let lo = if chunk.key() == start_key { start } else { 0 };
+                let hi = if chunk.key() == end_key {
+                    end
+                } else {
+                    u16::MAX
+                };
+                if lo == 0 && hi == u16::MAX {
+                    Some(chunk.clone())
+                } else {
+                    slice_chunk::<u16>(
+                        chunk,
+                        lo..=hi,
+                        &mut Pool::default(),
+                        &Tuning::default(),
+                    )
+                }
+            })
+            .collect();
+
+        Self::from_chunks(chunks)
+    }
+
+    /// Calls `f` on each value of the bitmap within `range`, in ascending
+    /// order.
+    ///
+    /// The first chunk of the range is found through a binary search, then
+    /// each container is decoded by the tight loops of `for_each`, from the
+    /// start of the range.
+    pub fn for_each_in_range(
+        &self,
+        range: impl RangeBounds<u32>,
+        mut f: impl FnMut(u32),
+    ) {
+        let Ok(()) = self.try_for_each_in_range(range, |value| {
+            f(value);
+            Ok::<_, Infallible>(())
+        });
+    }
+
+    /// Calls the fallible `f` on each value of the bitmap within `range`, in
+    /// ascending order, stopping at the first error.
+    ///
+    /// See `for_each_in_range`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error returned by `f`.
+    pub fn try_for_each_in_range<E>(
+        &self,
+        range: impl RangeBounds<u32>,
+        mut f: impl FnMut(u32) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let Some(range) = inclusive_bounds(range) else {
+            return Ok(());
+        };
+        let (start_key, start) = u16::split(*range.start());
+        let (end_key, end) = u16::split(*range.end());
+
+        let from = self.chunks.partition_point(|chunk| chunk.key() < start_key);
+        for chunk in &self.chunks[from..] {
+            let key = chunk.key();
+            if key > end_key {
+                break;
+            }
+            let lo = if key == start_key { start } else { 0 };
+            let hi = if key == end_key { end } else { u16::MAX };
+            chunk
+                .container()
+                .try_for_each_in(lo..=hi, |value| f(key.join(value)))?;
+        }
+
+        Ok(())
+    }
+
+    /// Builds a bitmap holding every value of `range`.
+    pub(crate) fn from_range(range: RangeInclusive<u32>) -> Self {
+        if range.is_empty() {
+            return Self::new();
+        }
+        let (start_key, start) = u16::split(*range.start());
+        let (end_key, end) = u16::split(*range.end());
+        let chunks = (start_key..=end_key)
+            .map(|key| {
+                let lo = if key == start_key { start } else { 0 };
+                let hi = if key == end_key { end } else { u16::MAX };
+                let container = Container::from_ranges([lo..=hi]);
+                let cardinality = usize::from(hi - lo) + 1;
+                Chunk::from_container(key.header(), container, cardinality)
+            })
+            .collect();
+
+        Self::from_chunks(chunks)
+    }
+
+    /// Retains only the values of the bitmap within `range`.
+    ///
+    /// The chunks outside of the range are dropped wholesale: only the
+    /// containers at the boundaries of the range are sliced.
+    pub fn intersect_with_range(&mut self, range: impl RangeBounds<u32>) {
+        let Some(range) = inclusive_bounds(range) else {
+            self.clear();
+            return;
+        };
+        let (start_key, start) = u16::split(*range.start());
+        let (end_key, end) = u16::split(*range.end());
+
+        let from = self.chunks.partition_point(|chunk| chunk.key() < start_key);
+        let to = self.chunks.partition_point(|chunk| chunk.key() <= end_key);
+        if from != 0 || to != self.chunks.len() {
+            if let Some(ref mut dirty) = self.dirty {
+                let dropped =
+                    self.chunks[..from].iter().chain(&self.chunks[to..]);
+                dirty.extend(dropped.map(Chunk::key));
+            }
+            // Move the dropped chunks past the retained ones.
+            self.chunks.rotate_left(from);
+            let dropped = self.chunks[to - from..]
+                .iter()
+                .fold(0, |acc, chunk| acc + chunk.cardinality());
+            self.sub_cardinality(dropped);
+            for chunk in self.chunks.drain(to - from..) {
+                self.pool.recycle(chunk.into_container());
+            }
+            self.index.take();
+        }
+
+        // Slice the last chunk first, so the index of the first one holds.
+        if let Some(last) = self.chunks.last() {
+            let lo = if start_key == end_key { start } else { 0 };
+            if last.key() == end_key && (lo != 0 || end != u16::MAX) {
+                self.slice_chunk_at(self.chunks.len() - 1, lo..=end);
+            }
+        }
+        if let Some(first) = self.chunks.first() {
+            if first.key() == start_key && start != 0 && start_key != end_key {
+                self.slice_chunk_at(0, start..=u16::MAX);
+            }
+        }
+        self.measure_mem_limit();
+    }
+
+    /// Retains only the values of the chunk at `index` within `range`,
+    /// removing the chunk if none remains.
+    fn slice_chunk_at(&mut self, index: usize, range: RangeInclusive<u16>) {
+        self.touch(self.chunks[index].key());
+        self.sub_cardinality(self.chunks[index].cardinality());
+        let chunk = &self.chunks[index];
+        match slice_chunk::<u16>(chunk, range, &mut self.pool, &self.tuning) {
+            Some(sliced) => {
+                self.add_cardinality(sliced.cardinality());
+                let old = mem::replace(&mut self.chunks[index], sliced);
+                self.pool.recycle(old.into_container());
+            },
+            None => self.remove_chunk(index),
+        }
+    }
+
+    /// Shifts every value of the bitmap by `delta`, dropping the values that
+    /// fall outside of the `u32` range.
+    ///
+    /// When `delta` is a multiple of 2¹⁶, only the chunk keys are rewritten.
+    /// Otherwise each container is shifted as bitmap words and split over two
+    /// consecutive chunks, merged with the neighbouring containers.
+    pub fn add_offset(&mut self, delta: i64) {
+        let key_delta = delta.div_euclid(CHUNK_SIZE);
+        // Remainder of an Euclidean division by 2¹⁶: no truncation/sign loss.
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let shift = delta.rem_euclid(CHUNK_SIZE) as usize;
+        let shifted_key = |key: u16| i64::from(key) + key_delta;
+
+        self.touch_all();
+        let chunks = mem::take(&mut self.chunks);
+        let mut shifted = Vec::with_capacity(chunks.len());
+        if shift == 0 {
+            for chunk in chunks {
+                if let Ok(key) = u16::try_from(shifted_key(chunk.key())) {
+                    let cardinality = chunk.cardinality();
+                    shifted.push(Chunk::from_container_in(
+                        key.header(),
+                        chunk.into_container(),
+                        cardinality,
+                        &mut self.pool,
+                        &self.tuning,
+                    ));
+                }
+            }
+            self.replace_chunks(shifted);
+            return;
+        }
+
+        // Adds a chunk made of shifted words, if in range.
+        let mut push = |key: i64, words, runs: bool| {
+            let Ok(key) = u16::try_from(key) else {
+                return;
+            };
+            if let Some((container, cardinality)) = Container::from_words(words)
+            {
+                let mut chunk = Chunk::from_container_in(
+                    key.header(),
+                    container,
+                    cardinality,
+                    &mut self.pool,
+                    &self.tuning,
+                );
+                if runs {
+                    chunk.run_optimize();
+                }
+                shifted.push(chunk);
+            }
+        };
+        // Upper part of the previous container, spilled in the next chunk.
+        let mut spilled: Option<(i64, Box<[u64; BITMAP_WORD_COUNT]>, bool)> =
+            None;
+        for chunk in &chunks {
+            let key = shifted_key(chunk.key());
+            let runs = chunk.container().kind() == ContainerKind::Run;
+            let mut words = Box::new([0; BITMAP_WORD_COUNT]);
+            chunk.container().write_words(&mut words);
+            let (mut lo, hi) = shift_words(&words, shift);
+
+            match spilled.take() {
+                Some((spilled_key, spilled_words, spilled_runs))
+                    if spilled_key == key =>
+                {
+                    for (word, spilled_word) in
+                        lo.iter_mut().zip(spilled_words.iter())
+                    {
+                        *word |= spilled_word;
+                    }
+                    push(key, lo, runs || spilled_runs);
+                },
+                Some((spilled_key, spilled_words, spilled_runs)) => {
+                    push(spilled_key, spilled_words, spilled_runs);
+                    push(key, lo, runs);
+                },
+                None => push(key, lo, runs),
+            }
+            spilled = Some((key + 1, hi, runs));
+        }
+        if let Some((key, words, runs)) = spilled {
+            push(key, words, runs);
+        }
+
+        self.replace_chunks(shifted);
+    }
+
+    /// Computes the changes turning `old` into `new`.
+    ///
+    /// Only the chunks that differ are part of the patch, as runs of
+    /// consecutive values.
+    pub fn diff(old: &Self, new: &Self) -> Patch {
+        let changes = old
+            .pairs(new)
+            .filter_map(|pair| {
+                match pair {
+                    (Some(old), Some(new))
+                        if old.cardinality() == new.cardinality()
+                            && old.ranges().eq(new.ranges()) =>
+                    {
+                        None
+                    },
+                    (_, Some(new)) => {
+                        Some(Change {
+                            key: new.key(),
+                            runs: new
+                                .ranges()
+                                .map(|range| (*range.start(), *range.end()))
+                                .collect(),
+                        })
+                    },
+                    (Some(old), None) => {
+                        Some(Change {
+                            key: old.key(),
+                            runs: Vec::new(),
+                        })
+                    },
+                    (None, None) => None,
+                }
+            })
+            .collect();
+
+        Patch { changes }
+    }
+
+    /// Applies a patch computed by [`diff`](Self::diff).
+    ///
+    /// The changed chunks are replaced by their new content, whatever their
+    /// current one: applying a patch twice is harmless.
+    pub fn apply_patch(&mut self, patch: &Patch) {
+        for change in &patch.changes {
+            self.touch(change.key);
+            let position = self.search(change.key);
+            if change.runs.is_empty() {
+                if let Ok(index) = position {
+                    self.sub_cardinality(self.chunks[index].cardinality());
+                    self.remove_chunk(index);
+                }
+                continue;
+            }
+
+            let cardinality =
+                change.runs.iter().fold(0, |acc, &(start, end)| {
+                    acc + usize::from(end - start) + 1
+                });
+            let container = Container::from_ranges(
+                change.runs.iter().map(|&(start, end)| start..=end),
+            );
+            let chunk = Chunk::from_container_in(
+                change.key.header(),
+                container,
+                cardinality,
+                &mut self.pool,
+                &self.tuning,
+            );
+            self.add_cardinality(cardinality);
+            match position {
+                Ok(index) => {
+                    self.sub_cardinality(self.chunks[index].cardinality());
+                    let old = mem::replace(&mut self.chunks[index], chunk);
+                    self.pool.recycle(old.into_container());
+                },
+                Err(index) => self.insert_chunk(index, chunk),
+            }
+        }
+        self.measure_mem_limit();
+    }
+
+    /// Gets the changes made to the chunks modified since the last call to
+    /// `mark_clean`, to apply onto the last persisted state.
+    ///
+    /// Empty unless the tracking is enabled by `track_dirty_chunks`.
+    pub fn dirty_patch(&self) -> Patch {
+        let changes = self
+            .dirty_keys()
+            .map(|key| {
+                let runs = self.search(key).map_or_else(
+                    |_| Vec::new(),
+                    |index| {
+                        self.chunks[index]
+                            .ranges()
+                            .map(|range| (*range.start(), *range.end()))
+                            .collect()
+                    },
+                );
+                Change { key, runs }
+            })
+            .collect();
+
+        Patch { changes }
+    }
+
+    /// Packs the bitmap into a read-only [`FrozenRoaring`], storing every
+    /// container back-to-back in a single allocation.
+    pub fn freeze(&self) -> FrozenRoaring {
+        FrozenRoaring::from_chunks(&self.chunks)
+    }
+}
+
+// Private API.
+impl<K: ChunkKey> RoaringCore<K> {
+    /// Adds a non-empty slice of values, sorted in ascending order, to the
+    /// chunk `key`.
+    fn insert_sorted_slice(&mut self, key: K, values: &[u16]) {
+        self.touch(key);
+        match self.search(key) {
+            Ok(index) => {
+                let added = self.update_chunk(index, |chunk, pool, tuning| {
+                    chunk.insert_sorted_slice_in(values, pool, tuning)
+                });
+                self.add_cardinality(added);
+            },
+            Err(index) => {
+                let container =
+                    Container::Array(values.iter().copied().collect());
+                let chunk = Chunk::from_container_in(
+                    key.header(),
+                    container,
+                    values.len(),
+                    &mut self.pool,
+                    &self.tuning,
+                );
+                self.insert_chunk(index, chunk);
+                self.add_cardinality(values.len());
+            },
+        }
+    }
+
+    /// Initializes an empty bitmap enforcing a quota, with custom container
+    /// settings and room for `capacity` chunks.
+    pub(crate) fn with_config(
+        quota: Quota,
+        tuning: Tuning,
+        capacity: usize,
+        cache_cardinality: bool,
+    ) -> Self {
+        Self {
+            chunks: Vec::with_capacity(capacity),
+            cardinality: cache_cardinality.then_some(0),
+            quota,
+            tuning,
+            ..Self::default()
+        }
+    }
+
+    /// Adds `count` values to the cached cardinality, if any.
+    fn add_cardinality(&mut self, count: usize) {
+        if let Some(ref mut cardinality) = self.cardinality {
+            *cardinality += count;
+        }
+    }
+
+    /// Removes `count` values from the cached cardinality, if any.
+    fn sub_cardinality(&mut self, count: usize) {
+        if let Some(ref mut cardinality) = self.cardinality {
+            *cardinality -= count;
+        }
+    }
+
+    /// Recomputes the cached cardinality, if any, from the chunks.
+    fn recount(&mut self) {
+        if self.cardinality.is_some() {
+            self.cardinality = Some(
+                self.chunks
+                    .iter()
+                    .fold(0, |acc, chunk| acc + chunk.cardinality()),
+            );
+        }
+    }
+
+    /// Replaces the chunks of the bitmap by new ones, sorted by key.
+    fn replace_chunks(&mut self, chunks: Vec<Chunk<K::Header>>) {
+        self.chunks = chunks;
+        self.touch_all();
+        self.recount();
+        self.index.take();
+        self.measure_mem_limit();
+    }
+
+    /// Initializes a bitmap from chunks sorted by key.
+    pub(super) fn from_chunks(chunks: Vec<Chunk<K::Header>>) -> Self {
+        let cardinality = chunks
+            .iter()
+            .fold(0, |acc, chunk| acc + chunk.cardinality());
+
+        Self {
+            chunks,
+            cardinality: Some(cardinality),
+            index: OnceLock::new(),
+            hot: AtomicUsize::new(0),
+            pool: Pool::default(),
+            quota: Quota::default(),
+            dirty: None,
+            on_conversion: None,
+            mem_limit: None,
+            on_compaction: None,
+            tuning: Tuning::default(),
+        }
+    }
+
+    /// Searches for the chunk `key`, with the same semantic than
+    /// `slice::binary_search`.
+    ///
+    /// Checks the chunk found by the previous lookup first, then uses the
+    /// chunk index if it has already been built.
+    fn search(&self, key: K) -> Result<usize, usize> {
+        let hot = self.hot.load(AtomicOrdering::Relaxed);
+        if self.chunks.get(hot).is_some_and(|chunk| chunk.key() == key) {
+            return Ok(hot);
+        }
+
+        let result = match self.index.get() {
+            Some(index) => index.search(key),
+            None => self.chunks.binary_search_by_key(&key, Chunk::key),
+        };
+        if let Ok(index) = result {
+            self.hot.store(index, AtomicOrdering::Relaxed);
+        }
+        result
+    }
+
+    /// Searches for the chunk `key`, with the same semantic than
+    /// `slice::binary_search`.
+    ///
+    /// Builds the chunk index first if the bitmap has enough chunks.
+    fn indexed_search(&self, key: K) -> Result<usize, usize> {
+        if self.chunks.len() >= CHUNK_INDEX_THRESHOLD {
+            self.index.get_or_init(|| {
+                ChunkIndex::new(
+                    &self.chunks.iter().map(Chunk::key).collect::<Vec<_>>(),
+                )
+            });
+        }
+        self.search(key)
+    }
+
+    /// Checks whether `all` (or any) of the values are in the bitmap.
+    ///
+    /// Unsorted values are sorted first, to be grouped by chunk.
+    fn probe(&self, values: &[K::Value], all: bool) -> bool {
+        let mut sorted;
+        let values = if values.is_sorted() {
+            values
+        } else {
+            sorted = values.to_vec();
+            sorted.sort_unstable();
+            &sorted
+        };
+
+        let mut start = 0;
+        while let Some(&first) = values.get(start) {
+            let (key, _) = K::split(first);
+            let end = start
+                + values[start..]
+                    .partition_point(|&value| K::split(value).0 == key);
+            let group = &values[start..end];
+            let found = self.indexed_search(key).is_ok_and(|index| {
+                let chunk = &self.chunks[index];
+                let contains = |&value| chunk.contains(K::split(value).1);
+                if all {
+                    group.iter().all(contains)
+                } else {
+                    group.iter().any(contains)
+                }
+            });
+            // Stops on a missing value, or on a found one.
+            if found != all {
+                return found;
+            }
+            start = end;
+        }
+
+        all
+    }
+
+    /// Updates the chunk at `index`, reporting the conversion of its
+    /// container to the hook, if any, and enforcing the soft memory limit.
+    fn update_chunk<T>(
+        &mut self,
+        index: usize,
+        update: impl FnOnce(&mut Chunk<K::Header>, &mut Pool, &Tuning) -> T,
+    ) -> T {
+        let chunk = &mut self.chunks[index];
+        let kind = chunk.container().kind();
+        // Conversions move allocations between the chunk and the pool.
+        let size = self
+            .mem_limit
+            .is_some()
+            .then(|| chunk.mem_size() + self.pool.mem_size());
+        let result = update(chunk, &mut self.pool, &self.tuning);
+
+        if let Some(ref hook) = self.on_conversion {
+            let new_kind = chunk.container().kind();
+            if new_kind != kind {
+                hook(chunk.key(), kind, new_kind);
+            }
+        }
+        if let (Some(ref mut limit), Some(size)) =
+            (self.mem_limit.as_mut(), size)
+        {
+            limit.resize(size, chunk.mem_size() + self.pool.mem_size());
+        }
+        self.enforce_mem_limit();
+        result
+    }
+
+    /// Records the chunk `key` as modified, if tracked.
+    fn touch(&mut self, key: K) {
+        if let Some(ref mut dirty) = self.dirty {
+            dirty.insert(key);
+        }
+    }
+
+    /// Records every current chunk as modified, if tracked.
+    fn touch_all(&mut self) {
+        if let Some(ref mut dirty) = self.dirty {
+            dirty.extend(self.chunks.iter().map(Chunk::key));
+        }
+    }
+
+    /// Inserts a chunk at `index`, invalidating the chunk index and enforcing
+    /// the soft memory limit.
+    fn insert_chunk(&mut self, index: usize, chunk: Chunk<K::Header>) {
+        if let Some(ref mut limit) = self.mem_limit {
+            limit.resize(0, chunk.mem_size());
+        }
+        self.chunks.insert(index, chunk);
+        self.index.take();
+        self.enforce_mem_limit();
+    }
+
+    /// Removes the chunk at `index`, invalidating the chunk index.
+    fn remove_chunk(&mut self, index: usize) {
+        let chunk = self.chunks.remove(index);
+        let size = chunk.mem_size() + self.pool.mem_size();
+        self.pool.recycle(chunk.into_container());
+        if let Some(ref mut limit) = self.mem_limit {
+            limit.resize(size, self.pool.mem_size());
+        }
+        self.index.take();
+    }
+
+    /// Measures the bitmap again after a bulk update, enforcing the soft
+    /// memory limit.
+    fn measure_mem_limit(&mut self) {
+        if let Some(mut limit) = self.mem_limit {
+            limit.measured(self.mem_size());
+            self.mem_limit = Some(limit);
+            self.enforce_mem_limit();
+        }
+    }
+
+    /// Compacts the bitmap if it exceeds its soft memory limit, reporting the
+    /// compaction to the hook, if any.
+    ///
+    /// The estimated size is only a trigger: the bitmap is measured before
+    /// deciding to compact it.
+    fn enforce_mem_limit(&mut self) {
+        let Some(mut limit) =
+            self.mem_limit.filter(|limit| limit.is_exceeded())
+        else {
+            return;
+        };
+        let before = self.mem_size();
+        limit.measured(before);
+        if limit.is_exceeded() {
+            // Lifted meanwhile, so the compaction doesn't trigger itself.
+            self.mem_limit = None;
+            self.run_optimize();
+            self.shrink_to_fit();
+            let after = self.mem_size();
+            limit.compacted(after);
+            self.mem_limit = Some(limit);
+            if let Some(ref hook) = self.on_compaction {
+                hook(before, after);
+            }
+        } else {
+            self.mem_limit = Some(limit);
+        }
+    }
+
+    /// Appends a new chunk `key`, made of a non-empty slice of values sorted
+    /// in ascending order.
+    ///
+    /// `key` must be greater than every existing chunk key.
+    fn push_chunk(&mut self, key: K, values: &[u16]) {
+        self.touch(key);
+        let container = Container::Array(values.iter().copied().collect());
+        let chunk = Chunk::from_container_in(
+            key.header(),
+            container,
+            values.len(),
+            &mut self.pool,
+            &self.tuning,
+        );
+        self.insert_chunk(self.chunks.len(), chunk);
+        self.add_cardinality(values.len());
+    }
+
+    /// Merges the chunks of two bitmaps.
+    ///
+    /// Chunks present in both bitmaps are combined using `op`, chunks present
+    /// in only one of them are either copied or dropped.
+    fn merge(
+        &self,
+        other: &Self,
+        op: ContainerOp,
+        keep_lhs: bool,
+        keep_rhs: bool,
+    ) -> Self {
+        let chunks = self
+            .pairs(other)
+            .filter_map(|pair| merge_pair::<K>(pair, op, keep_lhs, keep_rhs))
+            .collect();
+
+        Self::from_chunks(chunks)
+    }
+
+    /// Replaces the bitmap by the result of a set operation between two
+    /// bitmaps, recycling its containers for the resulting ones.
+    fn assign(&mut self, lhs: &Self, rhs: &Self, op: Operation) {
+        self.touch_all();
+        for chunk in self.chunks.drain(..) {
+            self.pool.recycle(chunk.into_container());
+        }
+        self.index.take();
+
+        for pair in lhs.pairs(rhs) {
+            let chunk = match pair {
+                (Some(lhs), Some(rhs)) => {
+                    lhs.container()
+                        .combine(rhs.container(), op, &mut self.pool)
+                        .map(|(container, cardinality)| {
+                            Chunk::from_container_in(
+                                lhs.key().header(),
+                                container,
+                                cardinality,
+                                &mut self.pool,
+                                &self.tuning,
+                            )
+                        })
+                },
+                (Some(lhs), None) => op.keep(true, false).then(|| lhs.clone()),
+                (None, Some(rhs)) => op.keep(false, true).then(|| rhs.clone()),
+                (None, None) => None,
+            };
+            self.chunks.extend(chunk);
+        }
+        self.touch_all();
+        self.recount();
+        self.measure_mem_limit();
+    }
+
+    /// Gets an iterator over the chunks of two bitmaps, paired by key.
+    pub(super) fn pairs<'a>(&'a self, other: &'a Self) -> Pairs<'a, K> {
+        Pairs {
+            lhs: self.chunks.iter().peekable(),
+            rhs: other.chunks.iter().peekable(),
+        }
+    }
+}
+
+/// Chunks sharing the same key in two bitmaps, if any.
+pub(super) type Pair<'a, K> = (
+    Option<&'a Chunk<<K as ChunkKey>::Header>>,
+    Option<&'a Chunk<<K as ChunkKey>::Header>>,
+);
+
+/// Iterator over the chunks of two bitmaps, paired by key in ascending order.
+pub(super) struct Pairs<'a, K: ChunkKey> {
+    lhs: Peekable<slice::Iter<'a, Chunk<K::Header>>>,
+    rhs: Peekable<slice::Iter<'a, Chunk<K::Header>>>,
+}
+
+impl<'a, K: ChunkKey> Iterator for Pairs<'a, K> {
+    type Item = Pair<'a, K>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ordering = match (self.lhs.peek(), self.rhs.peek()) {
+            (Some(lhs), Some(rhs)) => lhs.key().cmp(&rhs.key()),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => return None,
+        };
+
+        Some(match ordering {
+            Ordering::Less => (self.lhs.next(), None),
+            Ordering::Greater => (None, self.rhs.next()),
+            Ordering::Equal => (self.lhs.next(), self.rhs.next()),
+        })
+    }
+}
+
+/// Merges a pair of chunks sharing the same key.
+///
+/// Chunks present in both bitmaps are combined using `op`, chunks present in
+/// only one of them are either copied or dropped.
+pub(super) fn merge_pair<K: ChunkKey>(
+    pair: Pair<'_, K>,
+    op: ContainerOp,
+    keep_lhs: bool,
+    keep_rhs: bool,
+) -> Option<Chunk<K::Header>> {
+    match pair {
+        (Some(lhs), Some(rhs)) => {
+            op(lhs.container(), rhs.container()).map(
+                |(container, cardinality)| {
+                    Chunk::from_container(
+                        lhs.key().header(),
+                        container,
+                        cardinality,
+                    )
+                },
+            )
+        },
+        (Some(lhs), None) => keep_lhs.then(|| lhs.clone()),
+        (None, Some(rhs)) => keep_rhs.then(|| rhs.clone()),
+        (None, None) => None,
+    }
+}
+
+impl<K: ChunkKey> Default for RoaringCore<K> {
+    fn default() -> Self {
+        Self {
+            chunks: Vec::new(),
+            cardinality: Some(0),
+            index: OnceLock::new(),
+            hot: AtomicUsize::new(0),
+            pool: Pool::default(),
+            quota: Quota::default(),
+            dirty: None,
+            on_conversion: None,
+            mem_limit: None,
+            on_compaction: None,
+            tuning: Tuning::default(),
+        }
+    }
+}
+
+impl<K: ChunkKey> Clone for RoaringCore<K> {
+    fn clone(&self) -> Self {
+        Self {
+            chunks: self.chunks.clone(),
+            cardinality: self.cardinality,
+            index: OnceLock::new(),
+            hot: AtomicUsize::new(0),
+            pool: Pool::default(),
+            quota: self.quota,
+            dirty: self.dirty.clone(),
+            on_conversion: self.on_conversion.clone(),
+            mem_limit: self.mem_limit,
+            on_compaction: self.on_compaction.clone(),
+            tuning: self.tuning,
+        }
+    }
+
+    /// Reuses the containers of the bitmap, see `overwrite_from`.
+    fn clone_from(&mut self, source: &Self) {
+        self.overwrite_from(source);
+        self.quota = source.quota;
+        self.dirty.clone_from(&source.dirty);
+        self.on_conversion.clone_from(&source.on_conversion);
+        self.on_compaction.clone_from(&source.on_compaction);
+        self.tuning = source.tuning;
+        self.mem_limit = source.mem_limit;
+        self.measure_mem_limit();
+    }
+}
+
+impl<K: ChunkKey> Extend<K::Value> for RoaringCore<K> {
+    /// Inserts the values in batches, one per sorted sequence of values
+    /// sharing the same chunk.
+    fn extend<I: IntoIterator<Item = K::Value>>(&mut self, iterator: I) {
+        let mut batch = Vec::new();
+        let mut key = None;
+
+        for value in iterator {
+            let (hi, lo) = K::split(value);
+            let is_sorted = batch.last().is_none_or(|&last| last < lo);
+
+            if key != Some(hi) || !is_sorted {
+                if let Some(key) = key {
+                    self.insert_sorted_slice(key, &batch);
+                }
+                batch.clear();
+                key = Some(hi);
+            }
+            batch.push(lo);
+        }
+        if let Some(key) = key {
+            self.insert_sorted_slice(key, &batch);
+        }
+    }
+}
+
+impl<K: ChunkKey> FromIterator<K::Value> for RoaringCore<K> {
+    fn from_iter<I: IntoIterator<Item = K::Value>>(iterator: I) -> Self {
+        let mut bitmap = Self::new();
+        bitmap.extend(iterator);
+        bitmap
+    }
+}
+
+impl<K: ChunkKey> From<&BTreeSet<K::Value>> for RoaringCore<K> {
+    fn from(set: &BTreeSet<K::Value>) -> Self {
+        // Values are sorted: inserted in batches of whole chunks.
+        set.iter().copied().collect()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K: ChunkKey, S> From<&HashSet<K::Value, S>> for RoaringCore<K> {
+    fn from(set: &HashSet<K::Value, S>) -> Self {
+        // Sorted first, to be inserted in batches of whole chunks.
+        let mut values = set.iter().copied().collect::<Vec<_>>();
+        values.sort_unstable();
+        values.into_iter().collect()
+    }
+}
+
+impl<K: ChunkKey> From<&RoaringCore<K>> for BTreeSet<K::Value> {
+    fn from(bitmap: &RoaringCore<K>) -> Self {
+        bitmap.iter().collect()
+    }
+}
+
+impl<'a, K: ChunkKey> IntoIterator for &'a RoaringCore<K> {
+    type Item = K::Value;
+    type IntoIter = Iter<'a, K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<K: ChunkKey> IntoIterator for RoaringCore<K> {
+    type Item = K::Value;
+    type IntoIter = IntoIter<K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let cardinality = self.cardinality();
+        IntoIter::new(self.chunks, cardinality)
+    }
+}
+
+/// Shifts the bits of a container's words by `shift` bits (less than 2¹⁶),
+/// returning the words of the lower and upper destination chunks.
+fn shift_words(
+    words: &[u64; BITMAP_WORD_COUNT],
+    shift: usize,
+) -> (Box<[u64; BITMAP_WORD_COUNT]>, Box<[u64; BITMAP_WORD_COUNT]>) {
+    let (word_shift, bit_shift) = (shift / 64, shift % 64);
+    let mut lo = Box::new([0; BITMAP_WORD_COUNT]);
+    let mut hi = Box::new([0; BITMAP_WORD_COUNT]);
+    let mut set = |index: usize, bits: u64| {
+        if index < BITMAP_WORD_COUNT {
+            lo[index] |= bits;
+        } else {
+            hi[index - BITMAP_WORD_COUNT] |= bits;
+        }
+    };
+
+    for (index, &word) in words.iter().enumerate() {
+        if word == 0 {
+            continue;
+        }
+        set(index + word_shift, word << bit_shift);
+        if bit_shift != 0 {
+            set(index + word_shift + 1, word >> (64 - bit_shift));
+        }
+    }
+
+    (lo, hi)
+}
+
+/// Returns the values of a chunk within `range`, or `None` if there are none.
+///
+/// The new container is adapted to `tuning`, recycling allocations through
+/// `pool`.
+fn slice_chunk<K: ChunkKey>(
+    chunk: &Chunk<K::Header>,
+    range: RangeInclusive<u16>,
+    pool: &mut Pool,
+    tuning: &Tuning,
+) -> Option<Chunk<K::Header>> {
+    chunk.container().and(&Container::from_ranges([range])).map(
+        |(container, cardinality)| {
+            Chunk::from_container_in(
+                chunk.key().header(),
+                container,
+                cardinality,
+                pool,
+                tuning,
+            )
+        },
+    )
+}
+
+/// Converts range bounds to an inclusive range, or `None` if empty.
+fn inclusive_bounds(
+    range: impl RangeBounds<u32>,
+) -> Option<RangeInclusive<u32>> {
+    let start = match range.start_bound() {
+        Bound::Included(&start) => start,
+        Bound::Excluded(&start) => start.checked_add(1)?,
+        Bound::Unbounded => u32::MIN,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&end) => end,
+        Bound::Excluded(&end) => end.checked_sub(1)?,
+        Bound::Unbounded => u32::MAX,
+    };
+
+    (start <= end).then_some(start..=end)
+}
@@ -0,0 +1,122 @@
+use alloc::{vec, vec::Vec};
+#[cfg(not(feature = "std"))]
+use core::marker::PhantomData;
+
+/// Chunk keys stored in Eytzinger layout (i.e. breadth-first order of a
+/// complete binary search tree), alongside their position in the chunk list.
+///
+/// Searching it only walks down an implicit tree, without any unpredictable
+/// branch and with prefetch-friendly memory accesses: much faster than a
+/// binary search on bitmaps with many chunks.
+pub(super) struct ChunkIndex<K> {
+    /// Keys and chunk positions, the root of the tree being at index 0 and
+    /// the children of node `k` (1-based) being at `2k` and `2k + 1`.
+    slots: Vec<(K, usize)>,
+}
+
+impl<K: Copy + Ord> ChunkIndex<K> {
+    /// Builds the index of a non-empty list of keys, sorted in ascending
+    /// order.
+    pub(super) fn new(keys: &[K]) -> Self {
+        let mut slots = vec![(keys[0], 0); keys.len()];
+        let mut position = 0;
+        Self::fill(keys, &mut slots, &mut position, 1);
+
+        Self { slots }
+    }
+
+    /// Searches for a key, with the same semantic than
+    /// `slice::binary_search` on the chunk list.
+    pub(super) fn search(&self, key: K) -> Result<usize, usize> {
+        let len = self.slots.len();
+        let mut node = 1;
+        while node <= len {
+            node = 2 * node + usize::from(self.slots[node - 1].0 < key);
+        }
+        // Cancel the right turns taken after the last left turn, to get back
+        // to the smallest key greater than or equal to the searched one.
+        node >>= node.trailing_ones() + 1;
+
+        if node == 0 {
+            return Err(len);
+        }
+        let (found, position) = self.slots[node - 1];
+        if found == key {
+            Ok(position)
+        } else {
+            Err(position)
+        }
+    }
+
+    /// Returns the number of bytes allocated by the index.
+    pub(super) fn mem_size(&self) -> usize {
+        self.slots.capacity() * size_of::<(K, usize)>()
+    }
+
+    /// Fills the subtree rooted at `node` (1-based) with an in-order
+    /// traversal of the keys, starting at `position`.
+    fn fill(
+        keys: &[K],
+        slots: &mut [(K, usize)],
+        position: &mut usize,
+        node: usize,
+    ) {
+        if node <= slots.len() {
+            Self::fill(keys, slots, position, 2 * node);
+            slots[node - 1] = (keys[*position], *position);
+            *position += 1;
+            Self::fill(keys, slots, position, 2 * node + 1);
+        }
+    }
+}
+
+/// Stand-in for `OnceLock` without `std`, which never holds an index.
+///
+/// `core` has no thread-safe once cell: lookups fall back to binary searches
+/// instead, keeping the bitmaps `Sync`.
+#[cfg(not(feature = "std"))]
+pub(super) struct NoIndex<T>(PhantomData<T>);
+
+// Mirrors the `OnceLock` methods used on the chunk index.
+#[allow(clippy::unused_self)]
+#[cfg(not(feature = "std"))]
+impl<T> NoIndex<T> {
+    /// Initializes an empty cell.
+    pub(super) const fn new() -> Self {
+        Self(PhantomData)
+    }
+
+    /// Returns the index, never built.
+    pub(super) const fn get(&self) -> Option<&T> {
+        None
+    }
+
+    /// Drops the index, never built.
+    pub(super) const fn take(&mut self) -> Option<T> {
+        None
+    }
+
+    /// Skips building the index.
+    pub(super) fn get_or_init(&self, _init: impl FnOnce() -> T) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search() {
+        for len in 1..100 {
+            let keys = (0..len).map(|key| key * 2 + 1).collect::<Vec<u16>>();
+            let index = ChunkIndex::new(&keys);
+
+            for key in 0..=len * 2 + 1 {
+                assert_eq!(
+                    index.search(key),
+                    keys.binary_search(&key),
+                    "key {key} in {len} keys"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,517 @@
+use super::{bitmap::Pairs, ChunkKey};
+use crate::{chunk, Chunk};
+use alloc::{collections::BinaryHeap, vec, vec::Vec};
+use core::{
+    cmp::{Ordering, Reverse},
+    iter::Peekable,
+    ops::RangeInclusive,
+};
+
+/// Immutable Roaring bitmap iterator.
+///
+/// This struct is created by the `iter` method on Roaring bitmaps.
+pub struct Iter<'a, K: ChunkKey> {
+    chunks: core::slice::Iter<'a, Chunk<K::Header>>,
+    current: Option<ChunkIter<K, chunk::Iter<'a>>>,
+    /// Chunk visited from the back.
+    back: Option<ChunkIter<K, chunk::Iter<'a>>>,
+    size: usize,
+}
+
+impl<'a, K: ChunkKey> Iter<'a, K> {
+    pub(super) fn new(
+        mut chunks: core::slice::Iter<'a, Chunk<K::Header>>,
+    ) -> Self {
+        Self {
+            size: chunks
+                .clone()
+                .fold(0, |acc, chunk| acc + chunk.cardinality()),
+            current: chunks.next().map(Into::into),
+            back: None,
+            chunks,
+        }
+    }
+
+    /// Returns the next chunk, taking the one visited from the back once
+    /// every other chunk is visited.
+    fn next_chunk(&mut self) -> Option<ChunkIter<K, chunk::Iter<'a>>> {
+        self.chunks
+            .next()
+            .map(ChunkIter::from)
+            .or_else(|| self.back.take())
+    }
+}
+
+impl<K: ChunkKey> Iter<'_, K> {
+    /// Skips the values lower than `value`, if any.
+    ///
+    /// The chunks before the value are skipped through a binary search, then
+    /// the container of the value's chunk is searched as well.
+    pub fn advance_to(&mut self, value: K::Value) {
+        let (key, lo) = K::split(value);
+
+        if self
+            .current
+            .as_ref()
+            .is_none_or(|current| current.key < key)
+        {
+            // Skip the rest of the current chunk.
+            self.size -= self.current.as_ref().map_or(0, ChunkIter::len);
+
+            let chunks = self.chunks.as_slice();
+            let count = chunks.partition_point(|chunk| chunk.key() < key);
+            self.size -= chunks[..count]
+                .iter()
+                .fold(0, |acc, chunk| acc + chunk.cardinality());
+            self.chunks = chunks[count..].iter();
+            self.current = self.next_chunk();
+            // The chunk visited from the back may be before the value too.
+            if let Some(ref current) = self.current {
+                if current.key < key {
+                    self.size -= current.len();
+                    self.current = None;
+                }
+            }
+        }
+        if let Some(ref mut current) = self.current {
+            if current.key == key {
+                self.size -= current.inner.advance_to(lo);
+            }
+        }
+    }
+}
+
+impl Iter<'_, u16> {
+    /// Decodes the next values into `buf`, scanning whole containers at once
+    /// rather than iterating value by value.
+    ///
+    /// Returns the number of decoded values: less than the buffer length only
+    /// when the iterator is exhausted.
+    pub fn next_many(&mut self, buf: &mut [u32]) -> usize {
+        let mut count = 0;
+
+        while count < buf.len() {
+            let Some(ref mut chunk) = self.current else {
+                break;
+            };
+            let decoded = chunk.next_many(&mut buf[count..]);
+            if decoded == 0 {
+                self.current = self.next_chunk();
+            }
+            count += decoded;
+        }
+        self.size -= count;
+
+        count
+    }
+}
+
+impl<K: ChunkKey> Iterator for Iter<'_, K> {
+    type Item = K::Value;
+
+    fn next(&mut self) -> Option<K::Value> {
+        loop {
+            if let Some(value) = self.current.as_mut().and_then(Iterator::next)
+            {
+                self.size -= 1;
+                return Some(value);
+            }
+            self.current = Some(self.next_chunk()?);
+        }
+    }
+
+    /// Skips whole chunks using their cardinality, then jumps within the
+    /// container of the value.
+    fn nth(&mut self, mut n: usize) -> Option<K::Value> {
+        let chunks = self.chunks.as_slice();
+        if n >= self.size {
+            self.size = 0;
+            self.current = None;
+            self.back = None;
+            self.chunks = chunks[chunks.len()..].iter();
+            return None;
+        }
+        self.size -= n + 1;
+
+        let len = self.current.as_ref().map_or(0, ChunkIter::len);
+        if n < len {
+            return self.current.as_mut()?.nth(n);
+        }
+        n -= len;
+
+        // The value is in the remaining chunks, as `n` is below the size.
+        let mut index = 0;
+        while index < chunks.len() && n >= chunks[index].cardinality() {
+            n -= chunks[index].cardinality();
+            index += 1;
+        }
+        self.chunks = chunks[(index + 1).min(chunks.len())..].iter();
+        let current = match chunks.get(index) {
+            Some(chunk) => self.current.insert(ChunkIter::from(chunk)),
+            None => self.current.insert(self.back.take()?),
+        };
+        current.nth(n)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.size, Some(self.size))
+    }
+}
+
+impl<K: ChunkKey> DoubleEndedIterator for Iter<'_, K> {
+    fn next_back(&mut self) -> Option<K::Value> {
+        loop {
+            if let Some(value) =
+                self.back.as_mut().and_then(DoubleEndedIterator::next_back)
+            {
+                self.size -= 1;
+                return Some(value);
+            }
+            self.back = Some(
+                self.chunks
+                    .next_back()
+                    .map(ChunkIter::from)
+                    .or_else(|| self.current.take())?,
+            );
+        }
+    }
+}
+
+/// Owning Roaring bitmap iterator.
+///
+/// This struct is created by the `into_iter` method on Roaring bitmaps.
+pub struct IntoIter<K: ChunkKey> {
+    chunks: vec::IntoIter<Chunk<K::Header>>,
+    current: Option<ChunkIter<K, chunk::IntoIter>>,
+    /// Chunk visited from the back.
+    back: Option<ChunkIter<K, chunk::IntoIter>>,
+    size: usize,
+}
+
+impl<K: ChunkKey> IntoIter<K> {
+    pub(super) fn new(chunks: Vec<Chunk<K::Header>>, size: usize) -> Self {
+        Self {
+            chunks: chunks.into_iter(),
+            current: None,
+            back: None,
+            size,
+        }
+    }
+}
+
+impl<K: ChunkKey> Iterator for IntoIter<K> {
+    type Item = K::Value;
+
+    fn next(&mut self) -> Option<K::Value> {
+        loop {
+            if let Some(value) = self.current.as_mut().and_then(Iterator::next)
+            {
+                self.size -= 1;
+                return Some(value);
+            }
+            self.current = Some(
+                self.chunks
+                    .next()
+                    .map(ChunkIter::from)
+                    .or_else(|| self.back.take())?,
+            );
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.size, Some(self.size))
+    }
+}
+
+impl<K: ChunkKey> DoubleEndedIterator for IntoIter<K> {
+    fn next_back(&mut self) -> Option<K::Value> {
+        loop {
+            if let Some(value) =
+                self.back.as_mut().and_then(DoubleEndedIterator::next_back)
+            {
+                self.size -= 1;
+                return Some(value);
+            }
+            self.back = Some(
+                self.chunks
+                    .next_back()
+                    .map(ChunkIter::from)
+                    .or_else(|| self.current.take())?,
+            );
+        }
+    }
+}
+
+/// Iterator over the maximal runs of consecutive values in a bitmap.
+///
+/// Runs are detected at the container level, then merged across chunks when
+/// they span a chunk boundary.
+pub(super) struct Ranges<'a, K: ChunkKey> {
+    chunks: core::slice::Iter<'a, Chunk<K::Header>>,
+    /// Runs of the current chunk, with its key.
+    current: Option<(K, chunk::Ranges<'a>)>,
+    /// Run read ahead while looking for the end of the previous one.
+    peeked: Option<(K, RangeInclusive<u16>)>,
+}
+
+impl<'a, K: ChunkKey> Ranges<'a, K> {
+    pub(super) fn new(chunks: core::slice::Iter<'a, Chunk<K::Header>>) -> Self {
+        Self {
+            chunks,
+            current: None,
+            peeked: None,
+        }
+    }
+
+    /// Returns the next run of the current chunk, moving on to the following
+    /// chunks as needed.
+    fn next_chunk_range(&mut self) -> Option<(K, RangeInclusive<u16>)> {
+        if let Some(peeked) = self.peeked.take() {
+            return Some(peeked);
+        }
+        loop {
+            if let Some((key, ref mut ranges)) = self.current {
+                if let Some(range) = ranges.next() {
+                    return Some((key, range));
+                }
+            }
+            let chunk = self.chunks.next()?;
+            self.current = Some((chunk.key(), chunk.ranges()));
+        }
+    }
+}
+
+impl<K: ChunkKey> Iterator for Ranges<'_, K> {
+    type Item = RangeInclusive<K::Value>;
+
+    fn next(&mut self) -> Option<RangeInclusive<K::Value>> {
+        let (mut key, range) = self.next_chunk_range()?;
+        let start = key.join(*range.start());
+        let mut end = *range.end();
+
+        // Extend the run while it continues at the start of the next chunk.
+        while end == u16::MAX {
+            match self.next_chunk_range() {
+                Some((next_key, next))
+                    if key.checked_next() == Some(next_key)
+                        && *next.start() == u16::MIN =>
+                {
+                    key = next_key;
+                    end = *next.end();
+                },
+                next => {
+                    self.peeked = next;
+                    break;
+                },
+            }
+        }
+
+        Some(start..=key.join(end))
+    }
+}
+
+/// Lazy set operation between two bitmaps.
+///
+/// Chunks are paired by key, then the values of each pair of containers are
+/// merged, keeping those selected by the operation.
+pub(super) struct SetOpIter<'a, K: ChunkKey> {
+    pairs: Pairs<'a, K>,
+    /// Returns true if a value is part of the result, given its presence in
+    /// the left and right operands.
+    keep: fn(bool, bool) -> bool,
+    /// Merge of the current pair of chunks.
+    current: Option<ChunkMerge<'a, K>>,
+}
+
+impl<'a, K: ChunkKey> SetOpIter<'a, K> {
+    pub(super) fn new(
+        pairs: Pairs<'a, K>,
+        keep: fn(bool, bool) -> bool,
+    ) -> Self {
+        Self {
+            pairs,
+            keep,
+            current: None,
+        }
+    }
+}
+
+impl<K: ChunkKey> Iterator for SetOpIter<'_, K> {
+    type Item = K::Value;
+
+    fn next(&mut self) -> Option<K::Value> {
+        loop {
+            if let Some(ref mut merge) = self.current {
+                if let Some(value) = merge.next(self.keep) {
+                    return Some(value);
+                }
+            }
+
+            let (lhs, rhs) = self.pairs.next()?;
+            // Skip the chunks that can't be part of the result.
+            let (keep_lhs, keep_rhs) = match (lhs, rhs) {
+                (Some(_), Some(_)) => (true, true),
+                _ => ((self.keep)(true, false), (self.keep)(false, true)),
+            };
+            let lhs = lhs.filter(|_| keep_lhs);
+            let rhs = rhs.filter(|_| keep_rhs);
+            self.current = lhs.or(rhs).map(|chunk| {
+                ChunkMerge {
+                    key: chunk.key(),
+                    lhs: lhs.map(|chunk| chunk.iter().peekable()),
+                    rhs: rhs.map(|chunk| chunk.iter().peekable()),
+                }
+            });
+        }
+    }
+}
+
+/// Lazy union of many bitmaps.
+///
+/// The bitmap iterators are merged through a min-heap of their next value.
+pub(super) struct UnionIter<'a, K: ChunkKey> {
+    iters: Vec<Iter<'a, K>>,
+    /// Next value of each iterator, with the iterator's index.
+    heap: BinaryHeap<Reverse<(K::Value, usize)>>,
+    /// Last value returned, to skip duplicates.
+    last: Option<K::Value>,
+}
+
+impl<'a, K: ChunkKey> UnionIter<'a, K> {
+    pub(super) fn new(iters: impl Iterator<Item = Iter<'a, K>>) -> Self {
+        let mut iters = iters.collect::<Vec<_>>();
+        let heap = iters
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, iter)| {
+                iter.next().map(|value| Reverse((value, index)))
+            })
+            .collect();
+
+        Self {
+            iters,
+            heap,
+            last: None,
+        }
+    }
+}
+
+impl<K: ChunkKey> Iterator for UnionIter<'_, K> {
+    type Item = K::Value;
+
+    fn next(&mut self) -> Option<K::Value> {
+        loop {
+            let Reverse((value, index)) = self.heap.pop()?;
+            if let Some(next) = self.iters[index].next() {
+                self.heap.push(Reverse((next, index)));
+            }
+            if self.last != Some(value) {
+                self.last = Some(value);
+                return Some(value);
+            }
+        }
+    }
+}
+
+/// Merge of the values of two chunks sharing the same key.
+struct ChunkMerge<'a, K> {
+    key: K,
+    lhs: Option<Peekable<chunk::Iter<'a>>>,
+    rhs: Option<Peekable<chunk::Iter<'a>>>,
+}
+
+impl<K: ChunkKey> ChunkMerge<'_, K> {
+    /// Returns the next value selected by `keep`.
+    fn next(&mut self, keep: fn(bool, bool) -> bool) -> Option<K::Value> {
+        loop {
+            let lhs = self.lhs.as_mut().and_then(Peekable::peek).copied();
+            let rhs = self.rhs.as_mut().and_then(Peekable::peek).copied();
+            let (value, in_lhs, in_rhs) = match (lhs, rhs) {
+                (Some(lhs), Some(rhs)) => {
+                    match lhs.cmp(&rhs) {
+                        Ordering::Less => (lhs, true, false),
+                        Ordering::Greater => (rhs, false, true),
+                        Ordering::Equal => (lhs, true, true),
+                    }
+                },
+                // One side is exhausted: stop if the other one is discarded.
+                (Some(lhs), None) if keep(true, false) => (lhs, true, false),
+                (None, Some(rhs)) if keep(false, true) => (rhs, false, true),
+                _ => return None,
+            };
+
+            if in_lhs {
+                self.lhs.as_mut().and_then(Iterator::next);
+            }
+            if in_rhs {
+                self.rhs.as_mut().and_then(Iterator::next);
+            }
+            if keep(in_lhs, in_rhs) {
+                return Some(self.key.join(value));
+            }
+        }
+    }
+}
+
+/// Chunk iterator wrapper, containing the associated key as well.
+pub(super) struct ChunkIter<K, I> {
+    key: K,
+    inner: I,
+}
+
+impl<K, I: Iterator<Item = u16>> ChunkIter<K, I> {
+    /// Returns the number of values left in the chunk.
+    fn len(&self) -> usize {
+        self.inner.size_hint().0
+    }
+}
+
+impl ChunkIter<u16, chunk::Iter<'_>> {
+    /// Decodes the next values of the chunk into `buf`.
+    ///
+    /// Returns the number of decoded values.
+    fn next_many(&mut self, buf: &mut [u32]) -> usize {
+        self.inner.next_many(buf, self.key.join(0))
+    }
+}
+
+impl<'a, K: ChunkKey> From<&'a Chunk<K::Header>>
+    for ChunkIter<K, chunk::Iter<'a>>
+{
+    fn from(chunk: &'a Chunk<K::Header>) -> Self {
+        Self {
+            key: chunk.key(),
+            inner: chunk.iter(),
+        }
+    }
+}
+
+impl<K: ChunkKey> From<Chunk<K::Header>> for ChunkIter<K, chunk::IntoIter> {
+    fn from(chunk: Chunk<K::Header>) -> Self {
+        Self {
+            key: chunk.key(),
+            inner: chunk.into_iter(),
+        }
+    }
+}
+
+impl<K: ChunkKey, I: Iterator<Item = u16>> Iterator for ChunkIter<K, I> {
+    type Item = K::Value;
+
+    fn next(&mut self) -> Option<K::Value> {
+        self.inner.next().map(|value| self.key.join(value))
+    }
+
+    fn nth(&mut self, n: usize) -> Option<K::Value> {
+        self.inner.nth(n).map(|value| self.key.join(value))
+    }
+}
+
+impl<K: ChunkKey, I> DoubleEndedIterator for ChunkIter<K, I>
+where
+    I: DoubleEndedIterator<Item = u16>,
+{
+    fn next_back(&mut self) -> Option<K::Value> {
+        self.inner.next_back().map(|value| self.key.join(value))
+    }
+}
@@ -0,0 +1,79 @@
+use crate::{chunk::Header, roaring, roaring_two_levels};
+
+/// Key of a chunk, made of the most significant bits of its values.
+///
+/// The key width is the only difference between the Roaring variants using a
+/// flat list of chunks.
+///
+/// Implemented by `u16` (for [`Roaring`](crate::Roaring)) and `u64` (for
+/// [`RoaringTwoLevels`](crate::RoaringTwoLevels)) only: this trait is sealed,
+/// it can't be implemented outside of this crate.
+pub trait ChunkKey: sealed::Sealed + Copy + Ord {
+    /// Type of the values stored in the bitmap.
+    type Value: Copy + Ord;
+    /// Header of the chunks indexed by this key.
+    #[doc(hidden)]
+    type Header: Header<Key = Self> + Clone;
+
+    /// Splits a value into its chunk key and its 16 least significant bits.
+    fn split(value: Self::Value) -> (Self, u16);
+    /// Joins the chunk key with the 16 least significant bits of a value.
+    fn join(self, value: u16) -> Self::Value;
+    /// Initializes the header of a chunk with this key.
+    #[doc(hidden)]
+    fn header(self) -> Self::Header;
+    /// Returns the key following this one, if any.
+    fn checked_next(self) -> Option<Self>;
+}
+
+/// Restricts the implementations of `ChunkKey` to this crate.
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for u16 {}
+    impl Sealed for u64 {}
+}
+
+impl ChunkKey for u16 {
+    type Value = u32;
+    type Header = roaring::Header;
+
+    fn split(value: u32) -> (Self, u16) {
+        let entry = roaring::Entry::from(value);
+        (entry.hi, entry.lo)
+    }
+
+    fn join(self, value: u16) -> u32 {
+        roaring::Entry::from_parts(self, value).into()
+    }
+
+    fn header(self) -> Self::Header {
+        roaring::Header::new(self)
+    }
+
+    fn checked_next(self) -> Option<Self> {
+        self.checked_add(1)
+    }
+}
+
+impl ChunkKey for u64 {
+    type Value = u64;
+    type Header = roaring_two_levels::Header;
+
+    fn split(value: u64) -> (Self, u16) {
+        let entry = roaring_two_levels::Entry::from(value);
+        (entry.hi, entry.lo)
+    }
+
+    fn join(self, value: u16) -> u64 {
+        roaring_two_levels::Entry::from_parts(self, value).into()
+    }
+
+    fn header(self) -> Self::Header {
+        roaring_two_levels::Header::new(self)
+    }
+
+    fn checked_next(self) -> Option<Self> {
+        self.checked_add(1)
+    }
+}
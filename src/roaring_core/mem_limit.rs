@@ -0,0 +1,89 @@
+/// Soft limit on the in-memory size of a bitmap, compacting it once exceeded.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct MemLimit {
+    /// Approximate in-memory size, in bytes, above which the bitmap is
+    /// compacted.
+    max: usize,
+    /// Size from which the next compaction is attempted.
+    ///
+    /// That's the limit itself, unless the last compaction couldn't bring the
+    /// bitmap back under it: compacting again after each update would be
+    /// pointless, so the bitmap has to grow by half first.
+    threshold: usize,
+    /// Estimated in-memory size of the bitmap, kept up to date by the updates
+    /// and measured again by the compactions.
+    size: usize,
+}
+
+impl MemLimit {
+    /// Initializes a limit of `max` bytes, for a bitmap of `size` bytes.
+    pub(crate) fn new(max: usize, size: usize) -> Self {
+        Self {
+            max,
+            threshold: max,
+            size,
+        }
+    }
+
+    /// Returns the maximum size, in bytes.
+    pub(crate) fn max(self) -> usize {
+        self.max
+    }
+
+    /// Records a change of the estimated size, from `before` to `after`
+    /// bytes.
+    pub(crate) fn resize(&mut self, before: usize, after: usize) {
+        self.size = (self.size + after).saturating_sub(before);
+    }
+
+    /// Records the measured size of the bitmap, in bytes.
+    pub(crate) fn measured(&mut self, size: usize) {
+        self.size = size;
+        if size <= self.max {
+            self.threshold = self.max;
+        }
+    }
+
+    /// Records the measured size of the bitmap after a compaction, backing
+    /// off if it's still above the limit.
+    pub(crate) fn compacted(&mut self, size: usize) {
+        self.size = size;
+        self.threshold = if size > self.max {
+            size.saturating_add(size / 2)
+        } else {
+            self.max
+        };
+    }
+
+    /// Returns true if the bitmap is estimated to be due for a compaction.
+    pub(crate) fn is_exceeded(self) -> bool {
+        self.size > self.threshold
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn back_off() {
+        let mut limit = MemLimit::new(100, 40);
+        assert!(!limit.is_exceeded());
+
+        limit.resize(0, 70);
+        assert!(limit.is_exceeded());
+
+        // Compaction wasn't enough: wait for 50% more growth.
+        limit.compacted(120);
+        assert!(!limit.is_exceeded());
+        limit.resize(10, 60);
+        assert!(!limit.is_exceeded());
+        limit.resize(0, 20);
+        assert!(limit.is_exceeded());
+
+        // Back under the limit: the threshold is reset.
+        limit.measured(90);
+        limit.resize(0, 20);
+        assert!(limit.is_exceeded());
+    }
+}
@@ -0,0 +1,20 @@
+mod bitmap;
+mod index;
+mod iter;
+mod key;
+mod mem_limit;
+mod ops;
+#[cfg(feature = "rayon")]
+mod par;
+mod patch;
+mod quota;
+
+pub use bitmap::RoaringCore;
+pub(crate) use iter::{IntoIter, Iter};
+
+#[cfg(feature = "rayon")]
+use iter::ChunkIter;
+pub use key::ChunkKey;
+use mem_limit::MemLimit;
+pub(crate) use patch::Patch;
+pub(crate) use quota::Quota;
@@ -0,0 +1,86 @@
+use super::{ChunkKey, RoaringCore};
+use core::ops::{BitAnd, BitOr, BitXor, Sub};
+
+/// Implements a set operator on bitmaps, for every combination of owned and
+/// borrowed operands, by forwarding to the inherent method.
+macro_rules! impl_set_operator {
+    ($trait:ident, $method:ident, $operation:ident) => {
+        impl<K: ChunkKey> $trait<&RoaringCore<K>> for &RoaringCore<K> {
+            type Output = RoaringCore<K>;
+
+            fn $method(self, rhs: &RoaringCore<K>) -> RoaringCore<K> {
+                self.$operation(rhs)
+            }
+        }
+
+        impl<K: ChunkKey> $trait<RoaringCore<K>> for &RoaringCore<K> {
+            type Output = RoaringCore<K>;
+
+            fn $method(self, rhs: RoaringCore<K>) -> RoaringCore<K> {
+                self.$operation(&rhs)
+            }
+        }
+
+        impl<K: ChunkKey> $trait<&RoaringCore<K>> for RoaringCore<K> {
+            type Output = RoaringCore<K>;
+
+            fn $method(self, rhs: &RoaringCore<K>) -> RoaringCore<K> {
+                self.$operation(rhs)
+            }
+        }
+
+        impl<K: ChunkKey> $trait<RoaringCore<K>> for RoaringCore<K> {
+            type Output = RoaringCore<K>;
+
+            fn $method(self, rhs: RoaringCore<K>) -> RoaringCore<K> {
+                self.$operation(&rhs)
+            }
+        }
+    };
+}
+
+impl_set_operator!(BitOr, bitor, union);
+impl_set_operator!(BitAnd, bitand, intersection);
+impl_set_operator!(BitXor, bitxor, symmetric_difference);
+impl_set_operator!(Sub, sub, difference);
+
+#[cfg(test)]
+mod tests {
+    use crate::Roaring;
+
+    #[test]
+    fn operators() {
+        let lhs = (0..100_000).step_by(3).collect::<Roaring>();
+        let rhs = (50_000..200_000).step_by(5).collect::<Roaring>();
+        let owned = || {
+            (
+                (0..100_000).step_by(3).collect::<Roaring>(),
+                (50_000..200_000).step_by(5).collect::<Roaring>(),
+            )
+        };
+
+        let union = lhs.union(&rhs);
+        assert!((&lhs | &rhs).iter().eq(union.iter()));
+        let (a, b) = owned();
+        assert!((a | &b).iter().eq(union.iter()));
+        let (a, b) = owned();
+        assert!((&a | b).iter().eq(union.iter()));
+        let (a, b) = owned();
+        assert!((a | b).iter().eq(union.iter()));
+
+        let intersection = lhs.intersection(&rhs);
+        assert!((&lhs & &rhs).iter().eq(intersection.iter()));
+        let (a, b) = owned();
+        assert!((a & b).iter().eq(intersection.iter()));
+
+        let xor = lhs.symmetric_difference(&rhs);
+        assert!((&lhs ^ &rhs).iter().eq(xor.iter()));
+        let (a, b) = owned();
+        assert!((a ^ b).iter().eq(xor.iter()));
+
+        assert!((&lhs - &rhs).iter().eq(lhs.difference(&rhs).iter()));
+        assert!((&rhs - &lhs).iter().eq(rhs.difference(&lhs).iter()));
+        let (a, b) = owned();
+        assert!((a - b).iter().eq(lhs.difference(&rhs).iter()));
+    }
+}
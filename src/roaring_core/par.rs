@@ -0,0 +1,328 @@
+use super::{
+    bitmap::{merge_pair, ContainerOp},
+    ChunkIter, ChunkKey, RoaringCore,
+};
+use crate::{Chunk, Container, ContainerView};
+use rayon::prelude::*;
+
+impl<K> RoaringCore<K>
+where
+    K: ChunkKey + Send + Sync,
+    K::Value: Send + Sync,
+    K::Header: Send + Sync,
+{
+    /// Gets a parallel iterator over the values in the bitmap.
+    ///
+    /// The work is split at chunk boundaries, values of a chunk being visited
+    /// sequentially in ascending order.
+    pub fn par_iter(&self) -> impl ParallelIterator<Item = K::Value> + '_ {
+        self.chunks
+            .par_iter()
+            .flat_map_iter(ChunkIter::<K, _>::from)
+    }
+
+    /// Returns the union of two bitmaps, as a new bitmap.
+    ///
+    /// Chunks are merged in parallel.
+    #[must_use]
+    pub fn par_union(&self, other: &Self) -> Self {
+        self.par_merge(other, Container::or, true, true)
+    }
+
+    /// Returns the intersection of two bitmaps, as a new bitmap.
+    ///
+    /// Chunks are merged in parallel.
+    #[must_use]
+    pub fn par_intersection(&self, other: &Self) -> Self {
+        self.par_merge(other, Container::and, false, false)
+    }
+
+    /// Returns the values in `self` but not in `other`, as a new bitmap.
+    ///
+    /// Chunks are merged in parallel.
+    #[must_use]
+    pub fn par_difference(&self, other: &Self) -> Self {
+        self.par_merge(other, Container::and_not, true, false)
+    }
+
+    /// Returns the values in `self` or in `other` but not in both, as a new
+    /// bitmap.
+    ///
+    /// Chunks are merged in parallel.
+    #[must_use]
+    pub fn par_symmetric_difference(&self, other: &Self) -> Self {
+        self.par_merge(other, Container::xor, true, true)
+    }
+
+    /// Returns the union of many bitmaps, as a new bitmap.
+    ///
+    /// Bitmaps are merged pairwise, in parallel.
+    pub fn par_union_many<'a, I>(bitmaps: I) -> Self
+    where
+        I: IntoParallelIterator<Item = &'a Self>,
+        K: 'a,
+    {
+        bitmaps
+            .into_par_iter()
+            .fold(Self::new, |acc, bitmap| acc.union(bitmap))
+            .reduce(Self::new, |lhs, rhs| lhs.union(&rhs))
+    }
+
+    /// Folds the chunks of the bitmap in parallel, calling `fold` with an
+    /// accumulator, the key and a read-only view of each container.
+    ///
+    /// Each task starts from `identity` and folds a subset of the chunks, the
+    /// results being merged by `combine`: as the order in which the chunks are
+    /// split isn't specified, `combine` must be associative.
+    pub fn par_fold_chunks<T, I, F, C>(
+        &self,
+        identity: I,
+        fold: F,
+        combine: C,
+    ) -> T
+    where
+        T: Send,
+        I: Fn() -> T + Send + Sync,
+        F: Fn(T, K, ContainerView<'_>) -> T + Send + Sync,
+        C: Fn(T, T) -> T + Send + Sync,
+    {
+        self.chunks
+            .par_iter()
+            .fold(&identity, |acc, chunk| {
+                let view =
+                    ContainerView::new(chunk.container(), chunk.cardinality());
+                fold(acc, chunk.key(), view)
+            })
+            .reduce(&identity, combine)
+    }
+
+    /// Merges the chunks of two bitmaps, in parallel.
+    ///
+    /// See `merge` for the details.
+    fn par_merge(
+        &self,
+        other: &Self,
+        op: ContainerOp,
+        keep_lhs: bool,
+        keep_rhs: bool,
+    ) -> Self {
+        let pairs = self.pairs(other).collect::<Vec<_>>();
+        let chunks = pairs
+            .into_par_iter()
+            .filter_map(|pair| merge_pair::<K>(pair, op, keep_lhs, keep_rhs))
+            .collect();
+
+        Self::from_chunks(chunks)
+    }
+}
+
+impl<K> FromParallelIterator<K::Value> for RoaringCore<K>
+where
+    K: ChunkKey + Send + Sync,
+    K::Value: Send + Sync,
+    K::Header: Send,
+{
+    /// Sorts the values in parallel, then builds every chunk in parallel.
+    fn from_par_iter<I>(iterator: I) -> Self
+    where
+        I: IntoParallelIterator<Item = K::Value>,
+    {
+        let mut values = iterator.into_par_iter().collect::<Vec<_>>();
+        values.par_sort_unstable();
+        values.dedup();
+
+        let groups = values
+            .chunk_by(|&lhs, &rhs| K::split(lhs).0 == K::split(rhs).0)
+            .collect::<Vec<_>>();
+        let chunks = groups
+            .into_par_iter()
+            .map(|group| {
+                let (key, _) = K::split(group[0]);
+                let container = Container::Array(
+                    group.iter().map(|&value| K::split(value).1).collect(),
+                );
+                Chunk::from_container(key.header(), container, group.len())
+            })
+            .collect();
+
+        Self::from_chunks(chunks)
+    }
+}
+
+impl<K> ParallelExtend<K::Value> for RoaringCore<K>
+where
+    K: ChunkKey + Send + Sync,
+    K::Value: Send + Sync,
+    K::Header: Send,
+{
+    /// Sorts the values in parallel, then inserts them in place, in batches
+    /// of whole chunks: the bitmap's settings and callbacks apply as with
+    /// `extend`.
+    fn par_extend<I>(&mut self, iterator: I)
+    where
+        I: IntoParallelIterator<Item = K::Value>,
+    {
+        let mut values = iterator.into_par_iter().collect::<Vec<_>>();
+        values.par_sort_unstable();
+        values.dedup();
+
+        self.extend(values);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ContainerView, Roaring};
+    use rayon::prelude::*;
+
+    #[test]
+    fn from_par_iter() {
+        let input = (0..300_000u32)
+            .step_by(3)
+            .chain((1_000_000..1_070_000).rev())
+            .chain([7, 7, u32::MAX])
+            .collect::<Vec<_>>();
+
+        let bitmap = input.par_iter().copied().collect::<Roaring>();
+        let expected = input.iter().copied().collect::<Roaring>();
+        assert_eq!(bitmap.cardinality(), expected.cardinality());
+        assert!(bitmap.iter().eq(expected.iter()));
+    }
+
+    #[test]
+    fn par_iter() {
+        let bitmap = (0..1_000_000).step_by(7).collect::<Roaring>();
+
+        let sum = bitmap.par_iter().map(u64::from).sum::<u64>();
+        assert_eq!(sum, bitmap.iter().map(u64::from).sum::<u64>());
+        let mut values = bitmap.par_iter().collect::<Vec<_>>();
+        values.sort_unstable();
+        assert!(values.into_iter().eq(bitmap.iter()));
+    }
+
+    #[test]
+    fn set_operations() {
+        let lhs = (0..300_000).step_by(3).collect::<Roaring>();
+        let rhs = (100_000..500_000).step_by(5).collect::<Roaring>();
+
+        let check = |result: &Roaring, expected: &Roaring| {
+            assert_eq!(result.cardinality(), expected.cardinality());
+            assert!(result.iter().eq(expected.iter()));
+        };
+        check(&lhs.par_union(&rhs), &lhs.union(&rhs));
+        check(&lhs.par_intersection(&rhs), &lhs.intersection(&rhs));
+        check(&lhs.par_difference(&rhs), &lhs.difference(&rhs));
+        check(
+            &lhs.par_symmetric_difference(&rhs),
+            &lhs.symmetric_difference(&rhs),
+        );
+    }
+
+    #[test]
+    fn par_fold_chunks() {
+        let mut bitmap = (0..1_000_000)
+            .step_by(7)
+            .chain(2_000_000..2_500_000)
+            .collect::<Roaring>();
+        bitmap.run_optimize();
+
+        // Histogram of the container kinds, and per-chunk counts.
+        let histogram = |mut acc: [usize; 4], _, view: ContainerView<'_>| {
+            acc[view.kind() as usize] += view.cardinality();
+            acc
+        };
+        assert_eq!(
+            bitmap.par_fold_chunks(
+                || [0; 4],
+                histogram,
+                |lhs, rhs| {
+                    [
+                        lhs[0] + rhs[0],
+                        lhs[1] + rhs[1],
+                        lhs[2] + rhs[2],
+                        lhs[3] + rhs[3],
+                    ]
+                },
+            ),
+            bitmap.fold_chunks([0; 4], histogram)
+        );
+        let counts = bitmap.par_fold_chunks(
+            Vec::new,
+            |mut acc, key, view| {
+                acc.push((key, view.cardinality()));
+                acc
+            },
+            |mut lhs, mut rhs| {
+                lhs.append(&mut rhs);
+                lhs
+            },
+        );
+        assert!(counts.into_iter().eq(bitmap.density_profile()));
+    }
+
+    #[test]
+    fn union_many() {
+        let bitmaps = (0..10)
+            .map(|i| (i * 50_000..(i + 2) * 50_000).collect::<Roaring>())
+            .collect::<Vec<_>>();
+
+        let union = Roaring::par_union_many(&bitmaps);
+        assert_eq!(union.cardinality(), 550_000);
+        assert!(union.iter().eq(0..550_000));
+        assert!(Roaring::par_union_many(&Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn par_extend() {
+        let mut bitmap = (0..1_000).collect::<Roaring>();
+        bitmap.par_extend((500..200_000).into_par_iter());
+
+        assert_eq!(bitmap.cardinality(), 200_000);
+        assert!(bitmap.iter().eq(0..200_000));
+    }
+
+    #[test]
+    fn par_extend_dirty_chunks() {
+        let mut bitmap =
+            (0..1_000).chain(100_000..101_000).collect::<Roaring>();
+        bitmap.track_dirty_chunks();
+
+        bitmap.par_extend((1_000..2_000).into_par_iter().chain([300_000]));
+        assert_eq!(bitmap.dirty_keys().collect::<Vec<_>>(), vec![0, 4]);
+        let mut persisted =
+            (0..1_000).chain(100_000..101_000).collect::<Roaring>();
+        persisted.apply_patch(&bitmap.dirty_patch());
+        assert!(persisted.iter().eq(bitmap.iter()));
+    }
+
+    #[test]
+    fn par_extend_settings() {
+        use crate::{ContainerKind, RoaringBuilder};
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        let kinds = |bitmap: &Roaring| {
+            bitmap.chunks().map(|info| info.kind).collect::<Vec<_>>()
+        };
+        let mut bitmap = RoaringBuilder::new()
+            .sparse_threshold(10_000)
+            .use_runs(false)
+            .max_cardinality(10)
+            .build();
+        let conversions = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&conversions);
+        bitmap.on_conversion(move |_, _, _| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+
+        bitmap.par_extend((0..8_000).into_par_iter());
+        assert_eq!(kinds(&bitmap), vec![ContainerKind::Array]);
+        assert!(bitmap.try_insert(100_000).is_err(), "quota kept");
+
+        bitmap.par_extend((8_000..12_000).into_par_iter());
+        assert_eq!(kinds(&bitmap), vec![ContainerKind::Bitmap]);
+        assert_eq!(conversions.load(Ordering::Relaxed), 1, "callback kept");
+    }
+}
@@ -0,0 +1,86 @@
+#[cfg(feature = "serde")]
+use crate::InvalidPatchError;
+use alloc::vec::Vec;
+
+/// Changes turning a bitmap into another one, chunk by chunk.
+///
+/// Produced by [`Roaring::diff`](crate::Roaring::diff) and applied by
+/// [`Roaring::apply_patch`](crate::Roaring::apply_patch).
+///
+/// Deserialized patches are validated, as applying malformed changes would
+/// corrupt the bitmap.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "UncheckedPatch")
+)]
+pub struct Patch {
+    /// Changed chunks, sorted by key.
+    pub(super) changes: Vec<Change>,
+}
+
+impl Patch {
+    /// Returns the number of changed chunks.
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    /// Returns true if the patch doesn't change anything.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// Deserialized patch, not validated yet.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+#[serde(rename = "Patch")]
+struct UncheckedPatch {
+    changes: Vec<Change>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<UncheckedPatch> for Patch {
+    type Error = InvalidPatchError;
+
+    /// Checks that the chunks are sorted by key, without duplicates, and
+    /// that their runs are well-formed.
+    fn try_from(patch: UncheckedPatch) -> Result<Self, InvalidPatchError> {
+        let is_sorted = patch
+            .changes
+            .windows(2)
+            .all(|pair| pair[0].key < pair[1].key);
+        if !is_sorted || !patch.changes.iter().all(Change::is_valid) {
+            return Err(InvalidPatchError::new());
+        }
+
+        Ok(Self {
+            changes: patch.changes,
+        })
+    }
+}
+
+/// New content of a chunk.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(super) struct Change {
+    /// Chunk's key.
+    pub(super) key: u16,
+    /// Runs of consecutive values of the chunk (start and end, inclusive),
+    /// empty if the chunk is removed.
+    pub(super) runs: Vec<(u16, u16)>,
+}
+
+impl Change {
+    /// Returns true if the runs are non-empty, sorted in ascending order and
+    /// neither overlapping nor adjacent, as produced by `diff`.
+    #[cfg(feature = "serde")]
+    fn is_valid(&self) -> bool {
+        self.runs.iter().all(|&(start, end)| start <= end)
+            && self
+                .runs
+                .windows(2)
+                .all(|pair| u32::from(pair[0].1) + 1 < u32::from(pair[1].0))
+    }
+}
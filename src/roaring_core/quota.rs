@@ -0,0 +1,21 @@
+/// Limits enforced by the fallible insertions of a bitmap.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Quota {
+    /// Maximum number of values, if any.
+    pub(crate) max_cardinality: Option<usize>,
+    /// Maximum approximate in-memory size, in bytes, if any.
+    pub(crate) max_mem_size: Option<usize>,
+}
+
+impl Quota {
+    /// Returns true if adding one value to a bitmap holding `cardinality`
+    /// values exceeds the quota.
+    pub(crate) fn is_full(self, cardinality: usize) -> bool {
+        self.max_cardinality.is_some_and(|max| cardinality >= max)
+    }
+
+    /// Returns true if a bitmap of `mem_size` bytes exceeds the quota.
+    pub(crate) fn is_too_large(self, mem_size: usize) -> bool {
+        self.max_mem_size.is_some_and(|max| mem_size > max)
+    }
+}
@@ -0,0 +1,211 @@
+use super::{Entry, Iter};
+use crate::{
+    containers::View, roaring::Header, Chunk, ChunkInfo, ContainerKind,
+};
+use alloc::vec::Vec;
+
+/// Read-only compressed bitmap for 32-bit integers, produced by
+/// [`Roaring::freeze`](crate::Roaring::freeze).
+///
+/// The containers are stored back-to-back in a single buffer, alongside a
+/// compact descriptor per chunk, for better cache behaviour and lower
+/// per-chunk overhead than a mutable bitmap.
+#[derive(Clone, Default)]
+pub struct Bitmap {
+    /// Chunk descriptors, sorted by key.
+    chunks: Vec<Descriptor>,
+    /// Frozen containers, in the order of their chunks.
+    data: Vec<u16>,
+    /// Number of values in the bitmap.
+    cardinality: usize,
+}
+
+/// Location and layout of a frozen chunk.
+#[derive(Clone)]
+struct Descriptor {
+    /// Chunk's key (i.e. the 16 most significant bits of its values).
+    key: u16,
+    /// Kind of container used by the chunk.
+    kind: ContainerKind,
+    /// Number of values in the chunk.
+    cardinality: usize,
+    /// Offset of the chunk's container in the data buffer.
+    offset: usize,
+}
+
+impl Bitmap {
+    /// Packs the given chunks into a frozen bitmap.
+    pub(crate) fn from_chunks(chunks: &[Chunk<Header>]) -> Self {
+        let mut bitmap = Self {
+            chunks: Vec::with_capacity(chunks.len()),
+            data: Vec::new(),
+            cardinality: 0,
+        };
+        for chunk in chunks {
+            bitmap.chunks.push(Descriptor {
+                key: chunk.key(),
+                kind: chunk.container().kind(),
+                cardinality: chunk.cardinality(),
+                offset: bitmap.data.len(),
+            });
+            chunk.container().freeze_into(&mut bitmap.data);
+            bitmap.cardinality += chunk.cardinality();
+        }
+        bitmap.data.shrink_to_fit();
+
+        bitmap
+    }
+
+    /// Returns true if the bitmap contains the value.
+    pub fn contains(&self, value: u32) -> bool {
+        let entry = Entry::from(value);
+
+        self.chunks
+            .binary_search_by_key(&entry.hi, |chunk| chunk.key)
+            .is_ok_and(|index| self.view(index).contains(entry.lo))
+    }
+
+    /// Returns the bitmap cardinality.
+    pub fn cardinality(&self) -> usize {
+        self.cardinality
+    }
+
+    /// Finds the smallest value in the bitmap.
+    pub fn min(&self) -> Option<u32> {
+        self.chunks.first().and_then(|chunk| {
+            self.view(0)
+                .min()
+                .map(|min| Entry::from_parts(chunk.key, min).into())
+        })
+    }
+
+    /// Finds the largest value in the bitmap.
+    pub fn max(&self) -> Option<u32> {
+        self.chunks.last().and_then(|chunk| {
+            self.view(self.chunks.len() - 1)
+                .max()
+                .map(|max| Entry::from_parts(chunk.key, max).into())
+        })
+    }
+
+    /// Returns true if the bitmap contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Gets an iterator that visits the values in the bitmap in ascending
+    /// order.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter::new(self)
+    }
+
+    /// Returns the approximate in-memory size of the bitmap, in bytes.
+    pub fn mem_size(&self) -> usize {
+        size_of_val(self)
+            + self.chunks.capacity() * size_of::<Descriptor>()
+            + self.data.capacity() * size_of::<u16>()
+    }
+
+    /// Gets an iterator over the layout of each chunk.
+    pub fn chunks(&self) -> impl Iterator<Item = ChunkInfo<u16>> + '_ {
+        self.chunks.iter().enumerate().map(|(index, chunk)| {
+            ChunkInfo {
+                key: chunk.key,
+                kind: chunk.kind,
+                cardinality: chunk.cardinality,
+                mem_size: size_of::<Descriptor>()
+                    + self.range(index).len() * size_of::<u16>(),
+            }
+        })
+    }
+
+    /// Returns the key and the container view of the `index`-th chunk, if
+    /// any.
+    pub(super) fn chunk(&self, index: usize) -> Option<(u16, View<'_>)> {
+        self.chunks
+            .get(index)
+            .map(|chunk| (chunk.key, self.view(index)))
+    }
+
+    /// Returns a view over the container of the `index`-th chunk.
+    fn view(&self, index: usize) -> View<'_> {
+        View::new(self.chunks[index].kind, &self.data[self.range(index)])
+    }
+
+    /// Returns the location of the `index`-th chunk's container in the data
+    /// buffer.
+    fn range(&self, index: usize) -> core::ops::Range<usize> {
+        let end = self
+            .chunks
+            .get(index + 1)
+            .map_or(self.data.len(), |chunk| chunk.offset);
+
+        self.chunks[index].offset..end
+    }
+}
+
+impl<'a> IntoIterator for &'a Bitmap {
+    type Item = u32;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Roaring;
+
+    #[test]
+    fn freeze() {
+        // Array, bitmap, run and full chunks.
+        let mut roaring = [1, 42, 1 << 16]
+            .into_iter()
+            .chain((2 << 16..3 << 16).step_by(2))
+            .chain((3 << 16) + 10..(3 << 16) + 5_000)
+            .chain(4 << 16..5 << 16)
+            .chain([u32::MAX])
+            .collect::<Roaring>();
+        roaring.run_optimize();
+        let frozen = roaring.freeze();
+
+        assert_eq!(frozen.cardinality(), roaring.cardinality());
+        assert_eq!(frozen.min(), roaring.min());
+        assert_eq!(frozen.max(), roaring.max());
+        assert!(frozen.iter().eq(roaring.iter()));
+        assert_eq!(frozen.iter().size_hint().0, roaring.cardinality());
+        assert!(frozen
+            .chunks()
+            .map(|info| (info.key, info.kind, info.cardinality))
+            .eq(roaring
+                .chunks()
+                .map(|info| (info.key, info.kind, info.cardinality))));
+        for value in [0, 1, 2, 42, 1 << 16, 2 << 16, (2 << 16) + 1, 3 << 16]
+            .into_iter()
+            .chain([(3 << 16) + 10, (4 << 16) + 7, 5 << 16, u32::MAX])
+        {
+            assert_eq!(frozen.contains(value), roaring.contains(value));
+        }
+    }
+
+    #[test]
+    fn empty() {
+        let frozen = Roaring::new().freeze();
+
+        assert!(frozen.is_empty());
+        assert_eq!(frozen.cardinality(), 0);
+        assert_eq!(frozen.min(), None);
+        assert_eq!(frozen.max(), None);
+        assert!(!frozen.contains(42));
+        assert_eq!((&frozen).into_iter().count(), 0);
+    }
+
+    #[test]
+    fn mem_size() {
+        let roaring = (0..100_000).step_by(3).collect::<Roaring>();
+        let frozen = roaring.freeze();
+
+        assert!(frozen.mem_size() < roaring.mem_size());
+    }
+}
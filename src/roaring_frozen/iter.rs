@@ -0,0 +1,48 @@
+use super::{Entry, FrozenRoaring};
+use crate::containers::ViewIter;
+
+/// Immutable frozen Roaring bitmap iterator.
+///
+/// This struct is created by the `iter` method on frozen Roaring bitmap.
+pub struct Iter<'a> {
+    bitmap: &'a FrozenRoaring,
+    /// Index of the next chunk to visit.
+    index: usize,
+    /// Key and values of the current chunk.
+    current: Option<(u16, ViewIter<'a>)>,
+    size: usize,
+}
+
+impl<'a> Iter<'a> {
+    pub(super) fn new(bitmap: &'a FrozenRoaring) -> Self {
+        Self {
+            bitmap,
+            index: 1,
+            current: bitmap.chunk(0).map(|(key, view)| (key, view.iter())),
+            size: bitmap.cardinality(),
+        }
+    }
+}
+
+impl Iterator for Iter<'_> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        loop {
+            let (key, ref mut values) = *self.current.as_mut()?;
+            if let Some(value) = values.next() {
+                self.size -= 1;
+                return Some(Entry::from_parts(key, value).into());
+            }
+            self.current = self
+                .bitmap
+                .chunk(self.index)
+                .map(|(key, view)| (key, view.iter()));
+            self.index += 1;
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.size, Some(self.size))
+    }
+}
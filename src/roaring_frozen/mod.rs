@@ -0,0 +1,8 @@
+mod bitmap;
+mod iter;
+
+pub use bitmap::Bitmap as FrozenRoaring;
+pub use iter::Iter as FrozenRoaringIter;
+
+use crate::roaring::Entry;
+use iter::Iter;
@@ -1,6 +1,7 @@
-use super::{Entry, Iter, SuperChunk};
+use super::{Entry, IntoIter, Iter, SuperChunk};
 use crate::Stats;
-use std::mem;
+use alloc::vec::Vec;
+use core::ops::{Bound, RangeBounds, RangeInclusive};
 
 /// Compressed bitmap for 64-bit integers, using a 2-level indexing.
 ///
@@ -44,7 +45,7 @@ impl Bitmap {
 
         self.chunks
             .binary_search_by_key(&entry.hi, SuperChunk::key)
-            .map(|index| {
+            .is_ok_and(|index| {
                 let old_cardinality = self.chunks[index].cardinality();
                 let removed = self.chunks[index].remove(entry.lo);
 
@@ -54,7 +55,47 @@ impl Bitmap {
                 }
                 removed
             })
-            .unwrap_or(false)
+    }
+
+    /// Adds every value of a range to the bitmap.
+    ///
+    /// Missing super chunks are created, holding the part of the range they
+    /// cover.
+    pub fn insert_range(&mut self, range: impl RangeBounds<u64>) {
+        if let Some((start, end)) = inclusive_bounds(range) {
+            self.update_range(&start, &end, SuperChunk::insert_range);
+        }
+    }
+
+    /// Removes every value of a range from the bitmap.
+    ///
+    /// The super chunks entirely covered by the range are dropped wholesale.
+    pub fn remove_range(&mut self, range: impl RangeBounds<u64>) {
+        let Some((start, end)) = inclusive_bounds(range) else {
+            return;
+        };
+        self.chunks.retain_mut(|chunk| {
+            let key = chunk.key();
+            if key < start.hi || key > end.hi {
+                return true;
+            }
+            let range = chunk_range(key, &start, &end);
+            if range == (0..=u32::MAX) {
+                return false;
+            }
+            chunk.remove_range(range);
+            !chunk.is_empty()
+        });
+    }
+
+    /// Flips every value of a range: the values present are removed, the
+    /// missing ones are added.
+    ///
+    /// Missing super chunks are created and the emptied ones are dropped.
+    pub fn flip_range(&mut self, range: impl RangeBounds<u64>) {
+        if let Some((start, end)) = inclusive_bounds(range) {
+            self.update_range(&start, &end, SuperChunk::flip_range);
+        }
     }
 
     /// Returns true if the bitmap contains the value.
@@ -63,8 +104,7 @@ impl Bitmap {
 
         self.chunks
             .binary_search_by_key(&entry.hi, SuperChunk::key)
-            .map(|index| self.chunks[index].contains(entry.lo))
-            .unwrap_or(false)
+            .is_ok_and(|index| self.chunks[index].contains(entry.lo))
     }
 
     /// Computes the bitmap cardinality.
@@ -75,27 +115,65 @@ impl Bitmap {
     }
 
     /// Finds the smallest value in the bitmap.
+    ///
+    /// Super chunks are sorted and never empty: only the first one is looked
+    /// at.
     pub fn min(&self) -> Option<u64> {
-        self.chunks
-            .iter()
-            .filter_map(|chunk| {
-                chunk
-                    .min()
-                    .map(|min| Entry::from_parts(chunk.key(), min).into())
-            })
-            .min()
+        self.chunks.first().and_then(|chunk| {
+            chunk
+                .min()
+                .map(|min| Entry::from_parts(chunk.key(), min).into())
+        })
     }
 
     /// Finds the largest value in the bitmap.
+    ///
+    /// Super chunks are sorted and never empty: only the last one is looked
+    /// at.
     pub fn max(&self) -> Option<u64> {
-        self.chunks
+        self.chunks.last().and_then(|chunk| {
+            chunk
+                .max()
+                .map(|max| Entry::from_parts(chunk.key(), max).into())
+        })
+    }
+
+    /// Returns the number of values in the bitmap lower than or equal to
+    /// `value`.
+    ///
+    /// The cardinalities of the super chunks are summed up to the one of
+    /// `value`, which is the only one looked into.
+    pub fn rank(&self, value: u64) -> usize {
+        let entry = Entry::from(value);
+        let index = self.chunks.partition_point(|chunk| chunk.key() < entry.hi);
+        let rank = self.chunks[..index]
             .iter()
-            .filter_map(|chunk| {
-                chunk
-                    .max()
-                    .map(|max| Entry::from_parts(chunk.key(), max).into())
-            })
-            .max()
+            .fold(0, |acc, chunk| acc + chunk.cardinality());
+
+        match self.chunks.get(index) {
+            Some(chunk) if chunk.key() == entry.hi => {
+                rank + chunk.rank(entry.lo)
+            },
+            _ => rank,
+        }
+    }
+
+    /// Returns the `n`-th smallest value in the bitmap (starting from 0), if
+    /// any.
+    ///
+    /// The cardinalities of the super chunks are scanned to find the one
+    /// holding the value, which is the only one looked into.
+    pub fn select(&self, mut n: usize) -> Option<u64> {
+        for chunk in &self.chunks {
+            let cardinality = chunk.cardinality();
+            if n < cardinality {
+                return chunk
+                    .select(n)
+                    .map(|lo| Entry::from_parts(chunk.key(), lo).into());
+            }
+            n -= cardinality;
+        }
+        None
     }
 
     /// Clears the bitmap, removing all values.
@@ -116,7 +194,7 @@ impl Bitmap {
 
     /// Returns the approximate in-memory size of the bitmap, in bytes.
     pub fn mem_size(&self) -> usize {
-        mem::size_of_val(self)
+        size_of_val(self)
             + self
                 .chunks
                 .iter()
@@ -125,37 +203,74 @@ impl Bitmap {
 
     /// Returns detailed statistics about the composition of the bitmap.
     pub fn stats(&self) -> Stats<u64> {
-        let stats = Stats {
-            nb_containers: 0,
-            nb_array_containers: 0,
-            nb_bitmap_containers: 0,
-
-            nb_values: self.cardinality(),
-            nb_values_array_containers: 0,
-            nb_values_bitmap_containers: 0,
-
-            nb_bytes: self.mem_size(),
-            nb_bytes_array_containers: 0,
-            nb_bytes_bitmap_containers: 0,
+        let mut stats = Stats::new(
+            self.cardinality(),
+            self.mem_size(),
+            self.min(),
+            self.max(),
+        );
+        for chunk in &self.chunks {
+            stats.add_super_chunk(&chunk.stats());
+        }
 
-            min_value: self.min(),
-            max_value: self.max(),
-        };
+        stats
+    }
+}
 
-        self.chunks.iter().fold(stats, |mut acc, chunk| {
-            let sub = chunk.stats();
+impl Bitmap {
+    /// Updates the super chunks spanned by the range from `start` to `end`,
+    /// creating the missing ones from their part of the range and dropping
+    /// the ones left empty.
+    fn update_range(
+        &mut self,
+        start: &Entry,
+        end: &Entry,
+        mut update: impl FnMut(&mut SuperChunk, RangeInclusive<u32>),
+    ) {
+        let from = self.chunks.partition_point(|chunk| chunk.key() < start.hi);
+        let to = self.chunks.partition_point(|chunk| chunk.key() <= end.hi);
+        let tail = self.chunks.split_off(to);
+        let mut spanned = self.chunks.split_off(from).into_iter().peekable();
+
+        for key in start.hi..=end.hi {
+            let range = chunk_range(key, start, end);
+            match spanned.next_if(|chunk| chunk.key() == key) {
+                Some(mut chunk) => {
+                    update(&mut chunk, range);
+                    if !chunk.is_empty() {
+                        self.chunks.push(chunk);
+                    }
+                },
+                None => self.chunks.push(SuperChunk::from_range(key, range)),
+            }
+        }
+        self.chunks.extend(tail);
+    }
+}
 
-            acc.nb_containers += sub.nb_containers;
-            acc.nb_array_containers += sub.nb_array_containers;
-            acc.nb_bitmap_containers += sub.nb_bitmap_containers;
-            acc.nb_values_array_containers += sub.nb_values_array_containers;
-            acc.nb_values_bitmap_containers += sub.nb_values_bitmap_containers;
-            acc.nb_bytes_array_containers += sub.nb_bytes_array_containers;
-            acc.nb_bytes_bitmap_containers += sub.nb_bytes_bitmap_containers;
+/// Converts range bounds to the entries of its first and last values, or
+/// `None` if empty.
+fn inclusive_bounds(range: impl RangeBounds<u64>) -> Option<(Entry, Entry)> {
+    let start = match range.start_bound() {
+        Bound::Included(&start) => start,
+        Bound::Excluded(&start) => start.checked_add(1)?,
+        Bound::Unbounded => u64::MIN,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&end) => end,
+        Bound::Excluded(&end) => end.checked_sub(1)?,
+        Bound::Unbounded => u64::MAX,
+    };
+
+    (start <= end).then(|| (Entry::from(start), Entry::from(end)))
+}
 
-            acc
-        })
-    }
+/// Returns the part of the range from `start` to `end` covered by the super
+/// chunk `key`.
+fn chunk_range(key: u32, start: &Entry, end: &Entry) -> RangeInclusive<u32> {
+    let lo = if key == start.hi { start.lo } else { 0 };
+    let hi = if key == end.hi { end.lo } else { u32::MAX };
+    lo..=hi
 }
 
 impl Extend<u64> for Bitmap {
@@ -183,6 +298,15 @@ impl<'a> IntoIterator for &'a Bitmap {
     }
 }
 
+impl IntoIterator for Bitmap {
+    type Item = u64;
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter::new(self.chunks)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -197,20 +321,20 @@ mod tests {
         assert_eq!(bitmap.chunks.len(), 0);
 
         // Chunks are created as needed.
-        bitmap.insert(250070690292783730);
-        bitmap.insert(250070690272783732);
+        bitmap.insert(250_070_690_292_783_730);
+        bitmap.insert(250_070_690_272_783_732);
         assert_eq!(bitmap.cardinality(), 2);
         assert_eq!(bitmap.chunks.len(), 1);
-        bitmap.insert(188740018811086);
+        bitmap.insert(188_740_018_811_086);
         assert_eq!(bitmap.cardinality(), 3);
         assert_eq!(bitmap.chunks.len(), 2);
 
         // Operation works accross chunks.
-        assert_eq!(bitmap.min(), Some(188740018811086));
-        assert_eq!(bitmap.max(), Some(250070690292783730));
+        assert_eq!(bitmap.min(), Some(188_740_018_811_086));
+        assert_eq!(bitmap.max(), Some(250_070_690_292_783_730));
 
         // Chunks are deleted when empty.
-        bitmap.remove(188740018811086);
+        bitmap.remove(188_740_018_811_086);
         assert_eq!(bitmap.cardinality(), 2);
         assert_eq!(bitmap.chunks.len(), 1);
     }
@@ -218,21 +342,21 @@ mod tests {
     #[test]
     fn contains() {
         let mut bitmap = Bitmap::new();
-        assert_eq!(bitmap.contains(42), false);
+        assert!(!bitmap.contains(42));
 
         bitmap.insert(42);
-        assert_eq!(bitmap.contains(42), true);
+        assert!(bitmap.contains(42));
 
         bitmap.remove(42);
-        assert_eq!(bitmap.contains(42), false);
+        assert!(!bitmap.contains(42));
     }
 
     #[test]
     fn already_exists() {
         let mut bitmap = Bitmap::new();
 
-        assert_eq!(bitmap.insert(42), true, "new entry");
-        assert_eq!(bitmap.insert(42), false, "already exists");
+        assert!(bitmap.insert(42), "new entry");
+        assert!(!bitmap.insert(42), "already exists");
     }
 
     #[test]
@@ -241,22 +365,95 @@ mod tests {
 
         bitmap.insert(11);
 
-        assert_eq!(bitmap.remove(11), true, "found");
-        assert_eq!(bitmap.remove(11), false, "missing entry");
+        assert!(bitmap.remove(11), "found");
+        assert!(!bitmap.remove(11), "missing entry");
+    }
+
+    #[test]
+    fn ranges() {
+        let mut bitmap =
+            [1, 2 << 32, (3 << 32) + 7].into_iter().collect::<Bitmap>();
+
+        // Spans a partial, a missing (created) and an existing super chunk.
+        let start = (1 << 32) - 10;
+        let end = (3 << 32) + 5;
+        bitmap.insert_range(start..=end);
+        assert_eq!(bitmap.chunks.len(), 4);
+        assert_eq!(bitmap.cardinality(), 1 + 2 * (1 << 32) + 10 + 6 + 1);
+        assert!(bitmap.contains(start) && bitmap.contains(end));
+        assert!(!bitmap.contains(start - 1) && !bitmap.contains(end + 1));
+        assert_eq!(bitmap.chunks[1].stats().nb_full_containers, 65_536);
+
+        // Fully covered super chunks are dropped wholesale.
+        bitmap.remove_range(2..(3 << 32));
+        assert_eq!(bitmap.chunks.len(), 2);
+        assert_eq!(
+            bitmap.iter().collect::<Vec<_>>(),
+            [
+                1,
+                3 << 32,
+                (3 << 32) + 1,
+                (3 << 32) + 2,
+                (3 << 32) + 3,
+                (3 << 32) + 4,
+                (3 << 32) + 5,
+                (3 << 32) + 7
+            ]
+        );
+
+        // Flipping creates the missing super chunks and drops emptied ones.
+        bitmap.flip_range((3 << 32)..=(4 << 32) + 1);
+        assert_eq!(bitmap.chunks.len(), 3);
+        assert!(bitmap.contains(1) && bitmap.contains((3 << 32) + 6));
+        assert!(!bitmap.contains(3 << 32) && !bitmap.contains((3 << 32) + 7));
+        assert!(bitmap.contains(4 << 32) && bitmap.contains((4 << 32) + 1));
+        assert_eq!(bitmap.cardinality(), 1 + (1 << 32) - 7 + 2);
+        bitmap.flip_range((3 << 32)..=(4 << 32) + 1);
+        assert_eq!(bitmap.chunks.len(), 2);
+        assert_eq!(bitmap.cardinality(), 8);
+
+        // Empty ranges.
+        bitmap.insert_range(10..10);
+        bitmap.remove_range(1..1);
+        bitmap.flip_range(u64::MAX..);
+        assert_eq!(bitmap.cardinality(), 9, "u64::MAX flipped");
+        bitmap.remove_range(..);
+        assert!(bitmap.is_empty());
+    }
+
+    #[test]
+    fn rank_select() {
+        let mut bitmap = [1, 42, 1 << 40].into_iter().collect::<Bitmap>();
+        bitmap.insert_range((2 << 32) + 10..(2 << 32) + 100_000);
+
+        assert_eq!(bitmap.rank(0), 0);
+        assert_eq!(bitmap.rank(1), 1);
+        assert_eq!(bitmap.rank(1 << 32), 2);
+        assert_eq!(bitmap.rank((2 << 32) + 10), 3);
+        assert_eq!(bitmap.rank((2 << 32) + 70_000), 70_001 - 8);
+        assert_eq!(bitmap.rank(u64::MAX), bitmap.cardinality());
+
+        for (n, value) in bitmap.iter().enumerate().step_by(997) {
+            assert_eq!(bitmap.select(n), Some(value));
+            assert_eq!(bitmap.rank(value), n + 1);
+        }
+        assert_eq!(bitmap.select(bitmap.cardinality() - 1), Some(1 << 40));
+        assert_eq!(bitmap.select(bitmap.cardinality()), None);
+        assert_eq!(Bitmap::new().rank(42), 0);
     }
 
     #[test]
     fn is_empty() {
         let mut bitmap = Bitmap::new();
-        assert_eq!(bitmap.is_empty(), true);
+        assert!(bitmap.is_empty());
 
-        bitmap.insert(250070690292783730);
-        bitmap.insert(250070690272783732);
-        bitmap.insert(188740018811086);
-        assert_eq!(bitmap.is_empty(), false);
+        bitmap.insert(250_070_690_292_783_730);
+        bitmap.insert(250_070_690_272_783_732);
+        bitmap.insert(188_740_018_811_086);
+        assert!(!bitmap.is_empty());
 
         bitmap.clear();
-        assert_eq!(bitmap.is_empty(), true);
+        assert!(bitmap.is_empty());
     }
 
     #[test]
@@ -283,6 +480,74 @@ mod tests {
         assert_eq!(values, input);
     }
 
+    #[test]
+    fn stats() {
+        // Three chunks in the first super-chunk, one in the second.
+        let input = [1, 2 << 16, 3 << 16, 1 << 32];
+        let bitmap = input.iter().copied().collect::<Bitmap>();
+
+        let stats = bitmap.stats();
+        assert_eq!(stats.nb_super_chunks, 2);
+        assert_eq!(stats.max_containers_per_super_chunk, 3);
+        assert_eq!(stats.nb_containers, 4);
+        assert_eq!(stats.nb_array_containers, 4);
+        assert_eq!(stats.nb_values, 4);
+    }
+
+    #[test]
+    fn generic_usage() {
+        /// Round-trips values through any 64-bit bitmap type.
+        fn round_trip<B>(values: &[u64]) -> Vec<u64>
+        where
+            B: FromIterator<u64> + Extend<u64>,
+            for<'a> &'a B: IntoIterator<Item = u64>,
+        {
+            let mut bitmap = values[..1].iter().copied().collect::<B>();
+            bitmap.extend(values[1..].iter().copied());
+            (&bitmap).into_iter().collect()
+        }
+
+        let input = [1, 42, 1 << 40];
+        assert_eq!(round_trip::<Bitmap>(&input), input);
+        assert_eq!(round_trip::<crate::RoaringTreeMap>(&input), input);
+        assert_eq!(round_trip::<crate::RoaringTwoLevels>(&input), input);
+    }
+
+    #[test]
+    fn double_ended() {
+        /// Reverse-scans any 64-bit bitmap, owned or borrowed.
+        fn reversed<B>(bitmap: B) -> Vec<u64>
+        where
+            B: IntoIterator<Item = u64>,
+            B::IntoIter: DoubleEndedIterator,
+        {
+            bitmap.into_iter().rev().collect()
+        }
+
+        let input = (0..10_000)
+            .step_by(3)
+            .chain([1 << 32, (1 << 40) + 7, u64::MAX])
+            .collect::<Vec<_>>();
+        let expected = input.iter().rev().copied().collect::<Vec<_>>();
+        let bitmap = input.iter().copied().collect::<Bitmap>();
+        assert_eq!(reversed(&bitmap), expected);
+        assert_eq!(reversed(bitmap), expected);
+        let bitmap = input.iter().copied().collect::<crate::RoaringTreeMap>();
+        assert_eq!(reversed(&bitmap), expected);
+        assert_eq!(reversed(bitmap), expected);
+        let bitmap = input.iter().copied().collect::<crate::RoaringTwoLevels>();
+        assert_eq!(reversed(&bitmap), expected);
+        assert_eq!(reversed(bitmap), expected);
+
+        // Both ends meet without missing or repeating a value.
+        let mut iter = input.iter().copied().collect::<Bitmap>().into_iter();
+        assert_eq!(iter.size_hint().0, input.len());
+        assert_eq!(iter.next_back(), Some(u64::MAX));
+        assert_eq!(iter.next(), Some(0));
+        assert_eq!(iter.size_hint().0, input.len() - 2);
+        assert!(iter.eq(input[1..input.len() - 1].iter().copied()));
+    }
+
     #[test]
     fn mem_size() {
         let bitmap = (0..10_000).step_by(2).collect::<Bitmap>();
@@ -1,9 +1,17 @@
 use super::{superchunk, SuperChunk};
+use crate::{RoaringIntoIter, RoaringIter};
+use alloc::vec::{self, Vec};
 
-type SuperChunkFlatIter<'a> = std::iter::FlatMap<
-    std::slice::Iter<'a, SuperChunk>,
-    superchunk::Iter<'a>,
-    fn(&'a SuperChunk) -> superchunk::Iter<'a>,
+type SuperChunkFlatIter<'a> = core::iter::FlatMap<
+    core::slice::Iter<'a, SuperChunk>,
+    superchunk::Iter<RoaringIter<'a>>,
+    fn(&'a SuperChunk) -> superchunk::Iter<RoaringIter<'a>>,
+>;
+
+type SuperChunkFlatIntoIter = core::iter::FlatMap<
+    vec::IntoIter<SuperChunk>,
+    superchunk::Iter<RoaringIntoIter>,
+    fn(SuperChunk) -> superchunk::Iter<RoaringIntoIter>,
 >;
 
 /// Immutable Lazy Roaring bitmap iterator.
@@ -15,7 +23,7 @@ pub struct Iter<'a> {
 }
 
 impl<'a> Iter<'a> {
-    pub(super) fn new(chunks: std::slice::Iter<'a, SuperChunk>) -> Self {
+    pub(super) fn new(chunks: core::slice::Iter<'a, SuperChunk>) -> Self {
         Self {
             inner: chunks.clone().flat_map(SuperChunk::iter),
             size: chunks.fold(0, |acc, chunk| acc + chunk.cardinality()),
@@ -23,7 +31,7 @@ impl<'a> Iter<'a> {
     }
 }
 
-impl<'a> Iterator for Iter<'a> {
+impl Iterator for Iter<'_> {
     type Item = u64;
 
     fn next(&mut self) -> Option<u64> {
@@ -35,3 +43,49 @@ impl<'a> Iterator for Iter<'a> {
         (self.size, Some(self.size))
     }
 }
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<u64> {
+        self.size = self.size.saturating_sub(1);
+        self.inner.next_back()
+    }
+}
+
+/// Owning Lazy Roaring bitmap iterator.
+///
+/// This struct is created by the `into_iter` method on Lazy Roaring bitmap.
+pub struct IntoIter {
+    inner: SuperChunkFlatIntoIter,
+    size: usize,
+}
+
+impl IntoIter {
+    pub(super) fn new(chunks: Vec<SuperChunk>) -> Self {
+        Self {
+            size: chunks
+                .iter()
+                .fold(0, |acc, chunk| acc + chunk.cardinality()),
+            inner: chunks.into_iter().flat_map(IntoIterator::into_iter),
+        }
+    }
+}
+
+impl Iterator for IntoIter {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        self.size = self.size.saturating_sub(1);
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.size, Some(self.size))
+    }
+}
+
+impl DoubleEndedIterator for IntoIter {
+    fn next_back(&mut self) -> Option<u64> {
+        self.size = self.size.saturating_sub(1);
+        self.inner.next_back()
+    }
+}
@@ -3,7 +3,8 @@ mod iter;
 mod superchunk;
 
 pub use bitmap::Bitmap as RoaringLazy;
+pub use iter::{IntoIter as RoaringLazyIntoIter, Iter as RoaringLazyIter};
 
 use crate::roaring_tree_map::Entry;
-use iter::Iter;
+use iter::{IntoIter, Iter};
 use superchunk::SuperChunk;
@@ -1,23 +1,28 @@
 use super::Entry;
-use crate::{
-    roaring::{ChunkIter, Entry as ChunkEntry, Header},
-    Chunk, Container, Stats,
-};
-use std::mem;
+use crate::{Roaring, RoaringIntoIter, RoaringIter, Stats};
+use core::ops::RangeInclusive;
 
 pub(super) struct SuperChunk {
     key: u32,
-    chunks: Vec<Chunk<Header>>,
+    bitmap: Roaring,
 }
 
 impl SuperChunk {
     pub(super) fn new(entry: &Entry) -> Self {
-        let chunk_entry = ChunkEntry::from(entry.lo);
-        let header = Header::new(chunk_entry.hi);
+        let mut bitmap = Roaring::new();
+        bitmap.insert(entry.lo);
 
         Self {
             key: entry.hi,
-            chunks: vec![Chunk::new(header, chunk_entry.lo)],
+            bitmap,
+        }
+    }
+
+    /// Initializes a new chunk holding every value of a non-empty range.
+    pub(super) fn from_range(key: u32, range: RangeInclusive<u32>) -> Self {
+        Self {
+            key,
+            bitmap: Roaring::from_range(range),
         }
     }
 
@@ -26,47 +31,34 @@ impl SuperChunk {
     /// If the chunk did not have this value present, true is returned.
     /// If the chunk did have this value present, false is returned.
     pub(super) fn insert(&mut self, value: u32) -> bool {
-        let entry = ChunkEntry::from(value);
-
-        match self.chunks.binary_search_by_key(&entry.hi, Chunk::key) {
-            Ok(index) => self.chunks[index].insert(entry.lo),
-            Err(index) => {
-                let header = Header::new(entry.hi);
-                self.chunks.insert(index, Chunk::new(header, entry.lo));
-                true
-            },
-        }
+        self.bitmap.insert(value)
     }
 
     /// Removes a value from the chunk.
     ///
     /// Returns whether the value was present or not.
     pub(super) fn remove(&mut self, value: u32) -> bool {
-        let entry = ChunkEntry::from(value);
-
-        self.chunks
-            .binary_search_by_key(&entry.hi, Chunk::key)
-            .map(|index| {
-                let old_cardinality = self.chunks[index].cardinality();
-                let removed = self.chunks[index].remove(entry.lo);
-
-                // Chunk is now empty (last element removed), delete it.
-                if old_cardinality == 1 && removed {
-                    self.chunks.remove(index);
-                }
-                removed
-            })
-            .unwrap_or(false)
+        self.bitmap.remove(value)
+    }
+
+    /// Adds every value of a range to the chunk.
+    pub(super) fn insert_range(&mut self, range: RangeInclusive<u32>) {
+        self.bitmap = &self.bitmap | Roaring::from_range(range);
+    }
+
+    /// Removes every value of a range from the chunk.
+    pub(super) fn remove_range(&mut self, range: RangeInclusive<u32>) {
+        self.bitmap = &self.bitmap - Roaring::from_range(range);
+    }
+
+    /// Flips every value of a range in the chunk.
+    pub(super) fn flip_range(&mut self, range: RangeInclusive<u32>) {
+        self.bitmap = &self.bitmap ^ Roaring::from_range(range);
     }
 
     /// Returns true if the chunk contains the value.
     pub(super) fn contains(&self, value: u32) -> bool {
-        let entry = ChunkEntry::from(value);
-
-        self.chunks
-            .binary_search_by_key(&entry.hi, Chunk::key)
-            .map(|index| self.chunks[index].contains(entry.lo))
-            .unwrap_or(false)
+        self.bitmap.contains(value)
     }
 
     /// Returns the chunk key.
@@ -76,111 +68,75 @@ impl SuperChunk {
 
     /// Computes the chunk cardinality.
     pub(super) fn cardinality(&self) -> usize {
-        self.chunks
-            .iter()
-            .fold(0, |acc, chunk| acc + chunk.cardinality())
+        self.bitmap.cardinality()
+    }
+
+    /// Returns true if the chunk contains no value.
+    pub(super) fn is_empty(&self) -> bool {
+        self.bitmap.is_empty()
+    }
+
+    /// Returns the number of values in the chunk lower than or equal to
+    /// `value`.
+    pub(super) fn rank(&self, value: u32) -> usize {
+        self.bitmap.rank(value)
+    }
+
+    /// Returns the `n`-th smallest value in the chunk, if any.
+    pub(super) fn select(&self, n: usize) -> Option<u32> {
+        self.bitmap.select(n)
     }
 
     /// Finds the smallest value in the chunk.
     pub(super) fn min(&self) -> Option<u32> {
-        self.chunks
-            .iter()
-            .filter_map(|chunk| {
-                chunk
-                    .min()
-                    .map(|min| ChunkEntry::from_parts(chunk.key(), min).into())
-            })
-            .min()
+        self.bitmap.min()
     }
 
     /// Finds the largest value in the chunk.
     pub(super) fn max(&self) -> Option<u32> {
-        self.chunks
-            .iter()
-            .filter_map(|chunk| {
-                chunk
-                    .max()
-                    .map(|max| ChunkEntry::from_parts(chunk.key(), max).into())
-            })
-            .max()
+        self.bitmap.max()
     }
 
     /// Gets an iterator that visits the values in the superchunk in ascending
     /// order.
-    pub(super) fn iter(&self) -> Iter<'_> {
-        Iter::new(self)
+    pub(super) fn iter(&self) -> Iter<RoaringIter<'_>> {
+        Iter {
+            key: self.key,
+            inner: self.bitmap.iter(),
+        }
     }
 
     /// Returns the approximate in-memory size of the bitmap, in bytes.
     pub(super) fn mem_size(&self) -> usize {
-        mem::size_of_val(self)
-            + self
-                .chunks
-                .iter()
-                .fold(0, |acc, chunk| acc + chunk.mem_size())
+        // The inner bitmap accounts for its own inline size.
+        size_of_val(self) - size_of_val(&self.bitmap) + self.bitmap.mem_size()
     }
 
     /// Returns detailed statistics about the composition of the superchunk.
     pub(super) fn stats(&self) -> Stats<u32> {
-        let stats = Stats {
-            nb_containers: self.chunks.len(),
-            nb_array_containers: 0,
-            nb_bitmap_containers: 0,
-
-            nb_values: self.cardinality(),
-            nb_values_array_containers: 0,
-            nb_values_bitmap_containers: 0,
-
-            nb_bytes: self.mem_size(),
-            nb_bytes_array_containers: 0,
-            nb_bytes_bitmap_containers: 0,
-
-            min_value: None, // Unused.
-            max_value: None, // Unused.
-        };
-
-        self.chunks.iter().fold(stats, |mut acc, chunk| {
-            acc.nb_containers += 1;
-            match *chunk.container() {
-                Container::Array(_) => {
-                    acc.nb_array_containers += 1;
-                    acc.nb_values_array_containers += chunk.cardinality();
-                    acc.nb_bytes_array_containers += chunk.mem_size();
-                },
-                Container::Bitmap(_) => {
-                    acc.nb_bitmap_containers += 1;
-                    acc.nb_values_bitmap_containers += chunk.cardinality();
-                    acc.nb_bytes_bitmap_containers += chunk.mem_size();
-                },
-            }
-
-            acc
-        })
+        self.bitmap.stats()
     }
 }
 
-type ChunkFlatIter<'a> = std::iter::FlatMap<
-    std::slice::Iter<'a, Chunk<Header>>,
-    ChunkIter<'a>,
-    fn(&'a Chunk<Header>) -> ChunkIter<'a>,
->;
-
-/// Super-chunk iterator wrapper, containing the associated key as well.
-pub(super) struct Iter<'a> {
-    key: u32,
-    inner: ChunkFlatIter<'a>,
-}
+impl IntoIterator for SuperChunk {
+    type Item = u64;
+    type IntoIter = Iter<RoaringIntoIter>;
 
-impl<'a> Iter<'a> {
-    fn new(chunk: &'a SuperChunk) -> Self {
-        Self {
-            key: chunk.key,
-            inner: chunk.chunks.iter().flat_map(Into::into),
+    fn into_iter(self) -> Self::IntoIter {
+        Iter {
+            key: self.key,
+            inner: self.bitmap.into_iter(),
         }
     }
 }
 
-impl<'a> Iterator for Iter<'a> {
+/// Super-chunk iterator wrapper, containing the associated key as well.
+pub(super) struct Iter<I> {
+    key: u32,
+    inner: I,
+}
+
+impl<I: Iterator<Item = u32>> Iterator for Iter<I> {
     type Item = u64;
 
     fn next(&mut self) -> Option<u64> {
@@ -190,45 +146,53 @@ impl<'a> Iterator for Iter<'a> {
     }
 }
 
+impl<I: DoubleEndedIterator<Item = u32>> DoubleEndedIterator for Iter<I> {
+    fn next_back(&mut self) -> Option<u64> {
+        self.inner
+            .next_back()
+            .map(|value| Entry::from_parts(self.key, value).into())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn insertion_deletion() {
-        let entry = 1538809352.into();
+        let entry = 1_538_809_352.into();
         let mut chunk = SuperChunk::new(&entry);
         assert_eq!(chunk.cardinality(), 1);
-        assert_eq!(chunk.chunks.len(), 1);
-        assert_eq!(chunk.min(), Some(1538809352));
-        assert_eq!(chunk.max(), Some(1538809352));
+        assert_eq!(chunk.bitmap.chunks().count(), 1);
+        assert_eq!(chunk.min(), Some(1_538_809_352));
+        assert_eq!(chunk.max(), Some(1_538_809_352));
 
         // Chunks are created as needed.
-        chunk.insert(370099062);
+        chunk.insert(370_099_062);
         assert_eq!(chunk.cardinality(), 2);
-        assert_eq!(chunk.chunks.len(), 2);
+        assert_eq!(chunk.bitmap.chunks().count(), 2);
 
         // Operation works accross chunks.
-        assert_eq!(chunk.min(), Some(370099062));
-        assert_eq!(chunk.max(), Some(1538809352));
+        assert_eq!(chunk.min(), Some(370_099_062));
+        assert_eq!(chunk.max(), Some(1_538_809_352));
 
         // Chunks are deleted when empty.
-        chunk.remove(370099062);
+        chunk.remove(370_099_062);
         assert_eq!(chunk.cardinality(), 1);
-        assert_eq!(chunk.chunks.len(), 1);
+        assert_eq!(chunk.bitmap.chunks().count(), 1);
     }
 
     #[test]
     fn contains() {
         let entry = 0.into();
         let mut chunk = SuperChunk::new(&entry);
-        assert_eq!(chunk.contains(42), false);
+        assert!(!chunk.contains(42));
 
         chunk.insert(42);
-        assert_eq!(chunk.contains(42), true);
+        assert!(chunk.contains(42));
 
         chunk.remove(42);
-        assert_eq!(chunk.contains(42), false);
+        assert!(!chunk.contains(42));
     }
 
     #[test]
@@ -236,8 +200,8 @@ mod tests {
         let entry = 0.into();
         let mut chunk = SuperChunk::new(&entry);
 
-        assert_eq!(chunk.insert(42), true, "new entry");
-        assert_eq!(chunk.insert(42), false, "already exists");
+        assert!(chunk.insert(42), "new entry");
+        assert!(!chunk.insert(42), "already exists");
     }
 
     #[test]
@@ -247,8 +211,8 @@ mod tests {
 
         chunk.insert(11);
 
-        assert_eq!(chunk.remove(11), true, "found");
-        assert_eq!(chunk.remove(11), false, "missing entry");
+        assert!(chunk.remove(11), "found");
+        assert!(!chunk.remove(11), "missing entry");
     }
 
     #[test]
@@ -256,9 +220,10 @@ mod tests {
         let entry = 0.into();
         let chunk = SuperChunk::new(&entry);
         let chunks_size = chunk
-            .chunks
-            .iter()
-            .fold(0, |acc, chunk| acc + chunk.mem_size());
+            .bitmap
+            .chunks()
+            .map(|info| info.mem_size)
+            .sum::<usize>();
 
         // Ensure we don't forget to account for the Vec overhead.
         assert!(chunk.mem_size() > chunks_size);
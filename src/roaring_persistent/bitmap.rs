@@ -0,0 +1,254 @@
+use super::{Entry, Header, Iter};
+use crate::{Chunk, Roaring};
+use alloc::{sync::Arc, vec::Vec};
+
+/// Persistent compressed bitmap for 32-bit integers.
+///
+/// Chunks are immutable and shared between versions: cloning the bitmap is
+/// cheap and yields a snapshot unaffected by later updates, a chunk being
+/// copied only when it is updated while shared.
+#[derive(Clone, Default)]
+pub struct Bitmap {
+    /// Bitmap chunks, indexed by the 16 most significant bits of the integer.
+    chunks: Vec<Arc<Chunk<Header>>>,
+    /// Number of values in the bitmap, kept up to date by every update.
+    cardinality: usize,
+}
+
+impl Bitmap {
+    /// Create an empty bitmap.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a value to the bitmap.
+    ///
+    /// If the bitmap did not have this value present, true is returned.
+    /// If the bitmap did have this value present, false is returned.
+    pub fn insert(&mut self, value: u32) -> bool {
+        let entry = Entry::from(value);
+
+        let added = match self.search(entry.hi) {
+            // Don't copy a shared chunk for nothing.
+            Ok(index) if self.chunks[index].contains(entry.lo) => false,
+            Ok(index) => {
+                Arc::make_mut(&mut self.chunks[index]).insert(entry.lo)
+            },
+            Err(index) => {
+                let chunk = Chunk::new(Header::new(entry.hi), entry.lo);
+                self.chunks.insert(index, Arc::new(chunk));
+                true
+            },
+        };
+        self.cardinality += usize::from(added);
+        added
+    }
+
+    /// Removes a value from the bitmap.
+    ///
+    /// Returns whether the value was present or not.
+    pub fn remove(&mut self, value: u32) -> bool {
+        let entry = Entry::from(value);
+
+        let Ok(index) = self.search(entry.hi) else {
+            return false;
+        };
+        if !self.chunks[index].contains(entry.lo) {
+            return false;
+        }
+
+        // Chunk is about to be empty (last element removed), delete it.
+        if self.chunks[index].cardinality() == 1 {
+            self.chunks.remove(index);
+        } else {
+            Arc::make_mut(&mut self.chunks[index]).remove(entry.lo);
+        }
+        self.cardinality -= 1;
+        true
+    }
+
+    /// Returns true if the bitmap contains the value.
+    pub fn contains(&self, value: u32) -> bool {
+        let entry = Entry::from(value);
+
+        self.search(entry.hi)
+            .is_ok_and(|index| self.chunks[index].contains(entry.lo))
+    }
+
+    /// Returns the bitmap cardinality.
+    ///
+    /// The cardinality is cached, this is a constant-time operation.
+    pub fn cardinality(&self) -> usize {
+        self.cardinality
+    }
+
+    /// Finds the smallest value in the bitmap.
+    ///
+    /// Chunks are sorted and never empty: only the first one is looked at.
+    pub fn min(&self) -> Option<u32> {
+        self.chunks.first().and_then(|chunk| {
+            chunk
+                .min()
+                .map(|min| Entry::from_parts(chunk.key(), min).into())
+        })
+    }
+
+    /// Finds the largest value in the bitmap.
+    ///
+    /// Chunks are sorted and never empty: only the last one is looked at.
+    pub fn max(&self) -> Option<u32> {
+        self.chunks.last().and_then(|chunk| {
+            chunk
+                .max()
+                .map(|max| Entry::from_parts(chunk.key(), max).into())
+        })
+    }
+
+    /// Clears the bitmap, removing all values.
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+        self.cardinality = 0;
+    }
+
+    /// Returns true if the bitmap contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Gets an iterator that visits the values in the bitmap in ascending
+    /// order.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter::new(self.chunks.iter(), self.cardinality)
+    }
+
+    /// Returns the approximate in-memory size of the bitmap, in bytes.
+    ///
+    /// Chunks shared with other versions are accounted for in full.
+    pub fn mem_size(&self) -> usize {
+        size_of_val(self)
+            + self.chunks.capacity() * size_of::<Arc<Chunk<Header>>>()
+            + self.chunks.iter().fold(0, |acc, chunk| {
+                // Account for the reference counts as well.
+                acc + 2 * size_of::<usize>() + chunk.mem_size()
+            })
+    }
+
+    /// Returns the number of chunks shared with `other`.
+    pub fn shared_chunks(&self, other: &Self) -> usize {
+        let mut other = other.chunks.iter().peekable();
+
+        self.chunks
+            .iter()
+            .filter(|&chunk| {
+                while other.next_if(|rhs| rhs.key() < chunk.key()).is_some() {}
+                other.peek().is_some_and(|&rhs| Arc::ptr_eq(chunk, rhs))
+            })
+            .count()
+    }
+
+    /// Searches for the chunk `key`, with the same semantic than
+    /// `slice::binary_search`.
+    fn search(&self, key: u16) -> Result<usize, usize> {
+        self.chunks.binary_search_by_key(&key, |chunk| chunk.key())
+    }
+}
+
+impl From<&Roaring> for Bitmap {
+    fn from(bitmap: &Roaring) -> Self {
+        bitmap.iter().collect()
+    }
+}
+
+impl Extend<u32> for Bitmap {
+    fn extend<I: IntoIterator<Item = u32>>(&mut self, iterator: I) {
+        for value in iterator {
+            self.insert(value);
+        }
+    }
+}
+
+impl FromIterator<u32> for Bitmap {
+    fn from_iter<I: IntoIterator<Item = u32>>(iterator: I) -> Self {
+        let mut bitmap = Self::new();
+        bitmap.extend(iterator);
+        bitmap
+    }
+}
+
+impl<'a> IntoIterator for &'a Bitmap {
+    type Item = u32;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insertion_deletion() {
+        let mut bitmap = Bitmap::new();
+        assert_eq!(bitmap.cardinality(), 0);
+        assert_eq!(bitmap.min(), None);
+        assert_eq!(bitmap.max(), None);
+
+        // Chunks are created as needed.
+        bitmap.insert(1_538_809_352);
+        bitmap.insert(1_538_809_350);
+        assert_eq!(bitmap.cardinality(), 2);
+        assert_eq!(bitmap.chunks.len(), 1);
+        bitmap.insert(370_099_062);
+        assert_eq!(bitmap.cardinality(), 3);
+        assert_eq!(bitmap.chunks.len(), 2);
+
+        // Operation works accross chunks.
+        assert_eq!(bitmap.min(), Some(370_099_062));
+        assert_eq!(bitmap.max(), Some(1_538_809_352));
+
+        // Chunks are deleted when empty.
+        assert!(bitmap.remove(370_099_062));
+        assert!(!bitmap.remove(370_099_062), "missing entry");
+        assert_eq!(bitmap.cardinality(), 2);
+        assert_eq!(bitmap.chunks.len(), 1);
+
+        assert!(!bitmap.insert(1_538_809_350), "already exists");
+        bitmap.clear();
+        assert!(bitmap.is_empty());
+    }
+
+    #[test]
+    fn versions() {
+        let mut bitmap = (0..300_000).step_by(3).collect::<Bitmap>();
+        let snapshot = bitmap.clone();
+        assert_eq!(bitmap.shared_chunks(&snapshot), 5);
+
+        // Only the updated chunks are copied.
+        assert!(bitmap.insert(1));
+        assert!(bitmap.remove(200_001));
+        assert!(!bitmap.remove(200_002), "missing entry");
+        assert!(bitmap.insert(1 << 20));
+        assert_eq!(bitmap.shared_chunks(&snapshot), 3);
+        assert_eq!(snapshot.shared_chunks(&bitmap), 3);
+
+        // The snapshot is unaffected.
+        assert!(bitmap.contains(1) && !snapshot.contains(1));
+        assert!(!bitmap.contains(200_001) && snapshot.contains(200_001));
+        assert!(snapshot.iter().eq((0..300_000).step_by(3)));
+        assert_eq!(snapshot.cardinality(), 100_000);
+        assert_eq!(bitmap.cardinality(), 100_001);
+        assert_eq!(bitmap.iter().count(), 100_001);
+    }
+
+    #[test]
+    fn from_roaring() {
+        let roaring = (0..100_000).step_by(7).collect::<Roaring>();
+        let bitmap = Bitmap::from(&roaring);
+
+        assert!(bitmap.iter().eq(roaring.iter()));
+        assert_eq!((&bitmap).into_iter().size_hint().0, roaring.cardinality());
+        assert!(bitmap.mem_size() > 0);
+    }
+}
@@ -1,30 +1,34 @@
 use super::{Entry, Header};
 use crate::{chunk, Chunk};
+use alloc::sync::Arc;
 
-type ChunkFlatIter<'a> = std::iter::FlatMap<
-    std::slice::Iter<'a, Chunk<Header>>,
+type ChunkFlatIter<'a> = core::iter::FlatMap<
+    core::slice::Iter<'a, Arc<Chunk<Header>>>,
     ChunkIter<'a>,
-    fn(&'a Chunk<Header>) -> ChunkIter<'a>,
+    fn(&'a Arc<Chunk<Header>>) -> ChunkIter<'a>,
 >;
 
-/// Immutable Roaring bitmap iterator.
+/// Immutable persistent Roaring bitmap iterator.
 ///
-/// This struct is created by the `iter` method on Roaring bitmap.
+/// This struct is created by the `iter` method on persistent Roaring bitmap.
 pub struct Iter<'a> {
     inner: ChunkFlatIter<'a>,
     size: usize,
 }
 
 impl<'a> Iter<'a> {
-    pub(super) fn new(chunks: std::slice::Iter<'a, Chunk<Header>>) -> Self {
+    pub(super) fn new(
+        chunks: core::slice::Iter<'a, Arc<Chunk<Header>>>,
+        size: usize,
+    ) -> Self {
         Self {
-            inner: chunks.clone().flat_map(Into::into),
-            size: chunks.fold(0, |acc, chunk| acc + chunk.cardinality()),
+            inner: chunks.flat_map(|chunk| ChunkIter::from(&**chunk)),
+            size,
         }
     }
 }
 
-impl<'a> Iterator for Iter<'a> {
+impl Iterator for Iter<'_> {
     type Item = u32;
 
     fn next(&mut self) -> Option<u32> {
@@ -38,7 +42,7 @@ impl<'a> Iterator for Iter<'a> {
 }
 
 /// Chunk iterator wrapper, containing the associated key as well.
-pub(crate) struct ChunkIter<'a> {
+struct ChunkIter<'a> {
     key: u16,
     inner: chunk::Iter<'a>,
 }
@@ -52,7 +56,7 @@ impl<'a> From<&'a Chunk<Header>> for ChunkIter<'a> {
     }
 }
 
-impl<'a> Iterator for ChunkIter<'a> {
+impl Iterator for ChunkIter<'_> {
     type Item = u32;
 
     fn next(&mut self) -> Option<u32> {
@@ -0,0 +1,8 @@
+mod bitmap;
+mod iter;
+
+pub use bitmap::Bitmap as PersistentRoaring;
+pub use iter::Iter as PersistentRoaringIter;
+
+use crate::roaring::{Entry, Header};
+use iter::Iter;
@@ -1,6 +1,8 @@
-use super::{Entry, Iter};
-use crate::{Roaring, Stats};
-use std::{collections::BTreeMap, mem};
+use super::{Entry, IntoIter, Iter};
+use crate::{Roaring, Stats, TryFromBitmapError};
+use alloc::collections::{BTreeMap, BTreeSet};
+#[cfg(feature = "std")]
+use std::collections::HashSet;
 
 /// Compressed bitmap for 64-bit integers.
 ///
@@ -26,10 +28,7 @@ impl Bitmap {
     pub fn insert(&mut self, value: u64) -> bool {
         let entry = Entry::from(value);
 
-        self.bitmaps
-            .entry(entry.hi)
-            .or_insert_with(Roaring::new)
-            .insert(entry.lo)
+        self.bitmaps.entry(entry.hi).or_default().insert(entry.lo)
     }
 
     /// Removes a value from the bitmap.
@@ -39,7 +38,7 @@ impl Bitmap {
         let entry = Entry::from(value);
 
         match self.bitmaps.entry(entry.hi) {
-            std::collections::btree_map::Entry::Occupied(mut slot) => {
+            alloc::collections::btree_map::Entry::Occupied(mut slot) => {
                 let removed = slot.get_mut().remove(entry.lo);
 
                 // Remove unused bitmap.
@@ -48,7 +47,7 @@ impl Bitmap {
                 }
                 removed
             },
-            std::collections::btree_map::Entry::Vacant(_) => false,
+            alloc::collections::btree_map::Entry::Vacant(_) => false,
         }
     }
 
@@ -58,7 +57,7 @@ impl Bitmap {
 
         self.bitmaps
             .get(&entry.hi)
-            .map_or(false, |bitmap| bitmap.contains(entry.lo))
+            .is_some_and(|bitmap| bitmap.contains(entry.lo))
     }
 
     /// Computes the bitmap cardinality.
@@ -70,16 +69,14 @@ impl Bitmap {
 
     /// Finds the smallest value in the bitmap.
     pub fn min(&self) -> Option<u64> {
-        // TODO: use `first_key_value` when stable.
-        self.bitmaps.iter().next().and_then(|(key, bitmap)| {
+        self.bitmaps.first_key_value().and_then(|(key, bitmap)| {
             bitmap.min().map(|min| Entry::from_parts(*key, min).into())
         })
     }
 
     /// Finds the largest value in the bitmap.
     pub fn max(&self) -> Option<u64> {
-        // TODO: use `last_key_value` when stable.
-        self.bitmaps.iter().last().and_then(|(key, bitmap)| {
+        self.bitmaps.last_key_value().and_then(|(key, bitmap)| {
             bitmap.max().map(|max| Entry::from_parts(*key, max).into())
         })
     }
@@ -96,49 +93,78 @@ impl Bitmap {
 
     /// Gets an iterator that visits the values in the bitmap in ascending
     /// order.
-    pub(super) fn iter(&self) -> Iter<'_> {
+    pub fn iter(&self) -> Iter<'_> {
         Iter::new(self.bitmaps.iter())
     }
 
+    /// Returns the underlying 32-bit bitmap holding the values whose 32 most
+    /// significant bits are `key`, if any.
+    pub fn get_bitmap(&self, key: u32) -> Option<&Roaring> {
+        self.bitmaps.get(&key)
+    }
+
+    /// Gets an iterator over the underlying 32-bit bitmaps, with their key,
+    /// in ascending key order.
+    pub fn bitmaps(&self) -> impl Iterator<Item = (u32, &Roaring)> + '_ {
+        self.bitmaps.iter().map(|(&key, bitmap)| (key, bitmap))
+    }
+
+    /// Gets an iterator over the underlying 32-bit bitmaps, with their key,
+    /// in ascending key order.
+    ///
+    /// Alias of [`bitmaps`](Self::bitmaps).
+    pub fn shards(&self) -> impl Iterator<Item = (u32, &Roaring)> + '_ {
+        self.bitmaps()
+    }
+
+    /// Returns the number of underlying 32-bit bitmaps.
+    pub fn shard_count(&self) -> usize {
+        self.bitmaps.len()
+    }
+
+    /// Gets an iterator over the keys of the underlying 32-bit bitmaps (i.e.
+    /// the 32 most significant bits of their values), in ascending order.
+    pub fn keys(&self) -> impl Iterator<Item = u32> + '_ {
+        self.bitmaps.keys().copied()
+    }
+
+    /// Attaches a pre-built 32-bit bitmap as the values whose 32 most
+    /// significant bits are `key`.
+    ///
+    /// Returns the bitmap previously stored under that key, if any.
+    pub fn insert_bitmap(
+        &mut self,
+        key: u32,
+        bitmap: Roaring,
+    ) -> Option<Roaring> {
+        // Empty bitmaps are never stored.
+        if bitmap.is_empty() {
+            return self.bitmaps.remove(&key);
+        }
+        self.bitmaps.insert(key, bitmap)
+    }
+
     /// Returns the approximate in-memory size of the bitmap, in bytes.
     pub fn mem_size(&self) -> usize {
-        mem::size_of_val(self)
+        size_of_val(self)
             + self.bitmaps.iter().fold(0, |acc, (key, bitmap)| {
-                acc + mem::size_of_val(key) + bitmap.mem_size()
+                acc + size_of_val(key) + bitmap.mem_size()
             })
     }
 
     /// Returns detailed statistics about the composition of the bitmap.
     pub fn stats(&self) -> Stats<u64> {
-        let stats = Stats {
-            nb_containers: self.bitmaps.len(),
-            nb_array_containers: 0,
-            nb_bitmap_containers: 0,
-
-            nb_values: self.cardinality(),
-            nb_values_array_containers: 0,
-            nb_values_bitmap_containers: 0,
-
-            nb_bytes: self.mem_size(),
-            nb_bytes_array_containers: 0,
-            nb_bytes_bitmap_containers: 0,
-
-            min_value: self.min(),
-            max_value: self.max(),
-        };
-
-        self.bitmaps.values().fold(stats, |mut acc, bitmap| {
-            let sub = bitmap.stats();
-
-            acc.nb_array_containers += sub.nb_array_containers;
-            acc.nb_bitmap_containers += sub.nb_bitmap_containers;
-            acc.nb_values_array_containers += sub.nb_values_array_containers;
-            acc.nb_values_bitmap_containers += sub.nb_values_bitmap_containers;
-            acc.nb_bytes_array_containers += sub.nb_bytes_array_containers;
-            acc.nb_bytes_bitmap_containers += sub.nb_bytes_bitmap_containers;
-
-            acc
-        })
+        let mut stats = Stats::new(
+            self.cardinality(),
+            self.mem_size(),
+            self.min(),
+            self.max(),
+        );
+        for bitmap in self.bitmaps.values() {
+            stats.add_super_chunk(&bitmap.stats());
+        }
+
+        stats
     }
 }
 
@@ -158,6 +184,50 @@ impl FromIterator<u64> for Bitmap {
     }
 }
 
+impl From<Roaring> for Bitmap {
+    /// Widens a 32-bit bitmap, reusing it as the first underlying bitmap.
+    fn from(bitmap: Roaring) -> Self {
+        let mut bitmaps = BTreeMap::new();
+        if !bitmap.is_empty() {
+            bitmaps.insert(0, bitmap);
+        }
+
+        Self { bitmaps }
+    }
+}
+
+impl TryFrom<Bitmap> for Roaring {
+    type Error = TryFromBitmapError;
+
+    /// Narrows a 64-bit bitmap, failing if some values don't fit in `u32`.
+    fn try_from(mut bitmap: Bitmap) -> Result<Self, Self::Error> {
+        match bitmap.bitmaps.keys().max() {
+            None => Ok(Roaring::new()),
+            Some(&0) => Ok(bitmap.bitmaps.remove(&0).unwrap_or_default()),
+            Some(_) => Err(TryFromBitmapError::new()),
+        }
+    }
+}
+
+impl From<&BTreeSet<u64>> for Bitmap {
+    fn from(set: &BTreeSet<u64>) -> Self {
+        set.iter().copied().collect()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S> From<&HashSet<u64, S>> for Bitmap {
+    fn from(set: &HashSet<u64, S>) -> Self {
+        set.iter().copied().collect()
+    }
+}
+
+impl From<&Bitmap> for BTreeSet<u64> {
+    fn from(bitmap: &Bitmap) -> Self {
+        bitmap.iter().collect()
+    }
+}
+
 impl<'a> IntoIterator for &'a Bitmap {
     type Item = u64;
     type IntoIter = Iter<'a>;
@@ -167,6 +237,15 @@ impl<'a> IntoIterator for &'a Bitmap {
     }
 }
 
+impl IntoIterator for Bitmap {
+    type Item = u64;
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter::new(self.bitmaps)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -181,20 +260,20 @@ mod tests {
         assert_eq!(bitmap.bitmaps.len(), 0);
 
         // Bitmaps are created as needed.
-        bitmap.insert(250070690272783730);
-        bitmap.insert(250070690272783732);
+        bitmap.insert(250_070_690_272_783_730);
+        bitmap.insert(250_070_690_272_783_732);
         assert_eq!(bitmap.cardinality(), 2);
         assert_eq!(bitmap.bitmaps.len(), 1);
-        bitmap.insert(188740018811086);
+        bitmap.insert(188_740_018_811_086);
         assert_eq!(bitmap.cardinality(), 3);
         assert_eq!(bitmap.bitmaps.len(), 2);
 
         // Operation works accross bitmaps.
-        assert_eq!(bitmap.min(), Some(188740018811086));
-        assert_eq!(bitmap.max(), Some(250070690272783732));
+        assert_eq!(bitmap.min(), Some(188_740_018_811_086));
+        assert_eq!(bitmap.max(), Some(250_070_690_272_783_732));
 
         // Bitmaps are deleted when empty.
-        bitmap.remove(188740018811086);
+        bitmap.remove(188_740_018_811_086);
         assert_eq!(bitmap.cardinality(), 2);
         assert_eq!(bitmap.bitmaps.len(), 1);
     }
@@ -202,21 +281,21 @@ mod tests {
     #[test]
     fn contains() {
         let mut bitmap = Bitmap::new();
-        assert_eq!(bitmap.contains(42), false);
+        assert!(!bitmap.contains(42));
 
         bitmap.insert(42);
-        assert_eq!(bitmap.contains(42), true);
+        assert!(bitmap.contains(42));
 
         bitmap.remove(42);
-        assert_eq!(bitmap.contains(42), false);
+        assert!(!bitmap.contains(42));
     }
 
     #[test]
     fn already_exists() {
         let mut bitmap = Bitmap::new();
 
-        assert_eq!(bitmap.insert(42), true, "new entry");
-        assert_eq!(bitmap.insert(42), false, "already exists");
+        assert!(bitmap.insert(42), "new entry");
+        assert!(!bitmap.insert(42), "already exists");
     }
 
     #[test]
@@ -225,22 +304,22 @@ mod tests {
 
         bitmap.insert(11);
 
-        assert_eq!(bitmap.remove(11), true, "found");
-        assert_eq!(bitmap.remove(11), false, "missing entry");
+        assert!(bitmap.remove(11), "found");
+        assert!(!bitmap.remove(11), "missing entry");
     }
 
     #[test]
     fn is_empty() {
         let mut bitmap = Bitmap::new();
-        assert_eq!(bitmap.is_empty(), true);
+        assert!(bitmap.is_empty());
 
-        bitmap.insert(250070690292783730);
-        bitmap.insert(250070690272783732);
-        bitmap.insert(188740018811086);
-        assert_eq!(bitmap.is_empty(), false);
+        bitmap.insert(250_070_690_292_783_730);
+        bitmap.insert(250_070_690_272_783_732);
+        bitmap.insert(188_740_018_811_086);
+        assert!(!bitmap.is_empty());
 
         bitmap.clear();
-        assert_eq!(bitmap.is_empty(), true);
+        assert!(bitmap.is_empty());
     }
 
     #[test]
@@ -267,12 +346,122 @@ mod tests {
         assert_eq!(values, input);
     }
 
+    #[test]
+    fn double_ended() {
+        let input = (0..10_000)
+            .step_by(3)
+            .chain([1 << 32, (1 << 40) + 7, u64::MAX])
+            .collect::<Vec<_>>();
+        let bitmap = input.iter().copied().collect::<Bitmap>();
+
+        assert!(bitmap.iter().rev().eq(input.iter().rev().copied()));
+        let mut iter = bitmap.iter();
+        assert_eq!(iter.next_back(), Some(u64::MAX));
+        assert_eq!(iter.next(), Some(0));
+        assert_eq!(iter.size_hint().0, input.len() - 2);
+        assert!(iter.eq(input[1..input.len() - 1].iter().copied()));
+
+        let mut iter = bitmap.into_iter();
+        assert_eq!(iter.size_hint().0, input.len());
+        assert_eq!(iter.next_back(), Some(u64::MAX));
+        assert!(iter.eq(input[..input.len() - 1].iter().copied()));
+    }
+
+    #[test]
+    fn stats() {
+        // Three chunks in the first super-chunk, one in the second.
+        let input = [1, 2 << 16, 3 << 16, 1 << 32];
+        let bitmap = input.iter().copied().collect::<Bitmap>();
+
+        let stats = bitmap.stats();
+        assert_eq!(stats.nb_super_chunks, 2);
+        assert_eq!(stats.max_containers_per_super_chunk, 3);
+        assert_eq!(stats.nb_containers, 4);
+        assert_eq!(stats.nb_array_containers, 4);
+        assert_eq!(stats.nb_values, 4);
+    }
+
+    #[test]
+    fn conversions() {
+        let roaring = [1, 42, u32::MAX].iter().copied().collect::<Roaring>();
+
+        let bitmap = Bitmap::from(roaring);
+        assert_eq!(
+            bitmap.iter().collect::<Vec<_>>(),
+            vec![1, 42, u64::from(u32::MAX)]
+        );
+
+        let roaring = Roaring::try_from(bitmap).expect("values fit in u32");
+        assert_eq!(roaring.iter().collect::<Vec<_>>(), vec![1, 42, u32::MAX]);
+
+        let bitmap = Bitmap::from(Roaring::new());
+        assert!(bitmap.is_empty());
+        assert!(Roaring::try_from(bitmap).expect("empty").is_empty());
+
+        let bitmap = [1, 1 << 32].iter().copied().collect::<Bitmap>();
+        assert_eq!(
+            Roaring::try_from(bitmap).err(),
+            Some(TryFromBitmapError::new())
+        );
+    }
+
+    #[test]
+    fn shards() {
+        let mut bitmap = Bitmap::new();
+        let day1 = (0..1_000).collect::<Roaring>();
+        let day2 = (500..2_000).collect::<Roaring>();
+
+        assert!(bitmap.insert_bitmap(1, day1).is_none());
+        assert!(bitmap.insert_bitmap(2, day2).is_none());
+        assert_eq!(bitmap.cardinality(), 2_500);
+        assert_eq!(bitmap.min(), Some(1 << 32));
+        assert!(bitmap.contains((2 << 32) + 1_999));
+        assert_eq!(bitmap.get_bitmap(2).map(Roaring::cardinality), Some(1_500));
+        assert!(bitmap.get_bitmap(3).is_none());
+
+        let keys = bitmap.bitmaps().map(|(key, _)| key).collect::<Vec<_>>();
+        assert_eq!(keys, vec![1, 2]);
+        assert!(bitmap.shards().map(|(key, _)| key).eq(keys));
+        assert_eq!(bitmap.shard_count(), 2);
+
+        // Replacing a shard returns the previous one.
+        let old = bitmap.insert_bitmap(1, (0..10).collect());
+        assert_eq!(old.map(|old| old.cardinality()), Some(1_000));
+        assert_eq!(bitmap.cardinality(), 1_510);
+
+        // Attaching an empty shard detaches the previous one.
+        assert!(bitmap.insert_bitmap(2, Roaring::new()).is_some());
+        assert!(bitmap.get_bitmap(2).is_none());
+        assert_eq!(bitmap.bitmaps().count(), 1);
+        assert_eq!(bitmap.shard_count(), 1);
+        assert!(bitmap.keys().eq([1]));
+    }
+
+    #[test]
+    fn std_collections() {
+        use std::collections::BTreeSet;
+
+        let values = [1, 42, 1 << 40, u64::MAX];
+        let btree_set = values.into_iter().collect::<BTreeSet<_>>();
+
+        let bitmap = Bitmap::from(&btree_set);
+        assert!(bitmap.iter().eq(values));
+        assert_eq!(BTreeSet::from(&bitmap), btree_set);
+
+        #[cfg(feature = "std")]
+        {
+            let hash_set = values.into_iter().collect::<HashSet<_>>();
+            let bitmap = Bitmap::from(&hash_set);
+            assert!(bitmap.iter().eq(values));
+        }
+    }
+
     #[test]
     fn mem_size() {
         let bitmap = (0..10_000).step_by(2).collect::<Bitmap>();
         let bitmaps_size =
             bitmap.bitmaps.iter().fold(0, |acc, (key, bitmap)| {
-                acc + mem::size_of_val(key) + bitmap.mem_size()
+                acc + size_of_val(key) + bitmap.mem_size()
             });
 
         // Ensure we don't forget to account for the BTreeMap overhead.
@@ -1,11 +1,17 @@
 use super::Entry;
-use crate::{roaring, Roaring};
-use std::collections::btree_map;
+use crate::{Roaring, RoaringIntoIter, RoaringIter};
+use alloc::collections::{btree_map, BTreeMap};
 
-type RoaringFlatIter<'a> = std::iter::FlatMap<
+type RoaringFlatIter<'a> = core::iter::FlatMap<
     btree_map::Iter<'a, u32, Roaring>,
-    BitmapIter<'a>,
-    fn((&'a u32, &'a Roaring)) -> BitmapIter<'a>,
+    BitmapIter<RoaringIter<'a>>,
+    fn((&'a u32, &'a Roaring)) -> BitmapIter<RoaringIter<'a>>,
+>;
+
+type RoaringFlatIntoIter = core::iter::FlatMap<
+    btree_map::IntoIter<u32, Roaring>,
+    BitmapIter<RoaringIntoIter>,
+    fn((u32, Roaring)) -> BitmapIter<RoaringIntoIter>,
 >;
 
 /// Immutable Roaring Tree-Map bitmap iterator.
@@ -25,7 +31,7 @@ impl<'a> Iter<'a> {
     }
 }
 
-impl<'a> Iterator for Iter<'a> {
+impl Iterator for Iter<'_> {
     type Item = u64;
 
     fn next(&mut self) -> Option<u64> {
@@ -38,13 +44,60 @@ impl<'a> Iterator for Iter<'a> {
     }
 }
 
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<u64> {
+        self.size = self.size.saturating_sub(1);
+        self.inner.next_back()
+    }
+}
+
+/// Owning Roaring Tree-Map bitmap iterator.
+///
+/// This struct is created by the `into_iter` method on Roaring Tree-Map
+/// bitmap.
+pub struct IntoIter {
+    inner: RoaringFlatIntoIter,
+    size: usize,
+}
+
+impl IntoIter {
+    pub(super) fn new(bitmaps: BTreeMap<u32, Roaring>) -> Self {
+        Self {
+            size: bitmaps
+                .values()
+                .fold(0, |acc, bitmap| acc + bitmap.cardinality()),
+            inner: bitmaps.into_iter().flat_map(Into::into),
+        }
+    }
+}
+
+impl Iterator for IntoIter {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        self.size = self.size.saturating_sub(1);
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.size, Some(self.size))
+    }
+}
+
+impl DoubleEndedIterator for IntoIter {
+    fn next_back(&mut self) -> Option<u64> {
+        self.size = self.size.saturating_sub(1);
+        self.inner.next_back()
+    }
+}
+
 /// Roaring bitmap iterator wrapper, containing the associated key as well.
-struct BitmapIter<'a> {
+struct BitmapIter<I> {
     key: u32,
-    inner: roaring::Iter<'a>,
+    inner: I,
 }
 
-impl<'a> From<(&'a u32, &'a Roaring)> for BitmapIter<'a> {
+impl<'a> From<(&'a u32, &'a Roaring)> for BitmapIter<RoaringIter<'a>> {
     fn from(entry: (&'a u32, &'a Roaring)) -> Self {
         Self {
             key: *entry.0,
@@ -53,7 +106,16 @@ impl<'a> From<(&'a u32, &'a Roaring)> for BitmapIter<'a> {
     }
 }
 
-impl<'a> Iterator for BitmapIter<'a> {
+impl From<(u32, Roaring)> for BitmapIter<RoaringIntoIter> {
+    fn from(entry: (u32, Roaring)) -> Self {
+        Self {
+            key: entry.0,
+            inner: entry.1.into_iter(),
+        }
+    }
+}
+
+impl<I: Iterator<Item = u32>> Iterator for BitmapIter<I> {
     type Item = u64;
 
     fn next(&mut self) -> Option<u64> {
@@ -62,3 +124,11 @@ impl<'a> Iterator for BitmapIter<'a> {
             .map(|value| Entry::from_parts(self.key, value).into())
     }
 }
+
+impl<I: DoubleEndedIterator<Item = u32>> DoubleEndedIterator for BitmapIter<I> {
+    fn next_back(&mut self) -> Option<u64> {
+        self.inner
+            .next_back()
+            .map(|value| Entry::from_parts(self.key, value).into())
+    }
+}
@@ -3,7 +3,10 @@ mod entry;
 mod iter;
 
 pub use bitmap::Bitmap as RoaringTreeMap;
+pub use iter::{
+    IntoIter as RoaringTreeMapIntoIter, Iter as RoaringTreeMapIter,
+};
 
 pub(super) use entry::Entry;
 
-use iter::Iter;
+use iter::{IntoIter, Iter};
@@ -1,14 +1,14 @@
 /// `RoaringTwoLevels` bitmap entry.
-pub(super) struct Entry {
+pub(crate) struct Entry {
     /// Most significant bits (48).
-    pub(super) hi: u64,
+    pub(crate) hi: u64,
     /// Least significant bits (16).
-    pub(super) lo: u16,
+    pub(crate) lo: u16,
 }
 
 impl Entry {
     /// Initialize a new entry from its lower and higher parts.
-    pub(super) fn from_parts(hi: u64, lo: u16) -> Self {
+    pub(crate) fn from_parts(hi: u64, lo: u16) -> Self {
         Self { hi, lo }
     }
 }
@@ -16,7 +16,7 @@ impl Entry {
 impl From<u64> for Entry {
     #[allow(clippy::cast_possible_truncation)] // We truncate on purpose here.
     fn from(value: u64) -> Self {
-        Self::from_parts((value >> 16) as u64, (value & 0xFFFF) as u16)
+        Self::from_parts(value >> 16, (value & 0xFFFF) as u16)
     }
 }
 
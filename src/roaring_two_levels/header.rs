@@ -1,7 +1,8 @@
 use crate::chunk;
 
 /// Chunk header.
-pub(super) struct Header {
+#[derive(Clone)]
+pub struct Header {
     /// Header's data.
     ///
     /// Contains both the chunk's key (in the upper 48 bits) and the chunk's
@@ -16,7 +17,7 @@ pub(super) struct Header {
 
 impl Header {
     /// Initializes a new Chunk's header.
-    pub(super) fn new(key: u64) -> Self {
+    pub(crate) fn new(key: u64) -> Self {
         Self { data: key << 16 }
     }
 
@@ -53,6 +54,12 @@ impl chunk::Header for Header {
         let cardinality = self.unpack_cardinality().saturating_sub(1);
         self.pack_cardinality(cardinality);
     }
+
+    fn set_cardinality(&mut self, cardinality: usize) {
+        let cardinality = u16::try_from(cardinality - 1)
+            .expect("chunk cardinality fits on 16-bit");
+        self.pack_cardinality(cardinality);
+    }
 }
 
 #[cfg(test)]
@@ -75,4 +82,17 @@ mod tests {
         assert_eq!(header.key(), 0xFEED_DEAD_BEEF);
         assert_eq!(header.unpack_cardinality(), 0);
     }
+
+    #[test]
+    fn full_cardinality() {
+        let mut header = Header::new(0xFFFF_FFFF_FFFF);
+
+        header.set_cardinality(65_535);
+        header.increase_cardinality();
+        assert_eq!(header.cardinality(), 65_536);
+        assert_eq!(header.key(), 0xFFFF_FFFF_FFFF, "key is preserved");
+
+        header.decrease_cardinality();
+        assert_eq!(header.cardinality(), 65_535);
+    }
 }
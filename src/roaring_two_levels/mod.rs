@@ -1,10 +1,19 @@
 mod bitmap;
 mod entry;
 mod header;
-mod iter;
 
 pub use bitmap::Bitmap as RoaringTwoLevels;
 
-use entry::Entry;
-use header::Header;
-use iter::Iter;
+pub(crate) use entry::Entry;
+pub(crate) use header::Header;
+
+/// Immutable Roaring Two-Levels bitmap iterator.
+///
+/// This struct is created by the `iter` method on Roaring Two-Levels bitmap.
+pub type RoaringTwoLevelsIter<'a> = crate::roaring_core::Iter<'a, u64>;
+
+/// Owning Roaring Two-Levels bitmap iterator.
+///
+/// This struct is created by the `into_iter` method on Roaring Two-Levels
+/// bitmap.
+pub type RoaringTwoLevelsIntoIter = crate::roaring_core::IntoIter<u64>;
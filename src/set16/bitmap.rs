@@ -0,0 +1,268 @@
+use super::{Header, Iter};
+use crate::{Chunk, Container, Tuning};
+
+/// Set operation between two containers.
+type ContainerOp = fn(&Container, &Container) -> Option<(Container, usize)>;
+
+/// Compressed set for 16-bit integers.
+///
+/// Uses a single chunk, without the key indirection of the 32-bit bitmaps.
+#[derive(Clone, Default)]
+pub struct Bitmap {
+    /// The set's chunk, if not empty.
+    chunk: Option<Chunk<Header>>,
+}
+
+impl Bitmap {
+    /// Create an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a value to the set.
+    ///
+    /// If the set did not have this value present, true is returned.
+    /// If the set did have this value present, false is returned.
+    pub fn insert(&mut self, value: u16) -> bool {
+        if let Some(ref mut chunk) = self.chunk {
+            return chunk.insert(value);
+        }
+        self.chunk = Some(Chunk::new(Header::new(), value));
+
+        true
+    }
+
+    /// Removes a value from the set.
+    ///
+    /// Returns whether the value was present or not.
+    pub fn remove(&mut self, value: u16) -> bool {
+        let Some(ref mut chunk) = self.chunk else {
+            return false;
+        };
+        let old_cardinality = chunk.cardinality();
+        let removed = chunk.remove(value);
+
+        // Chunk is now empty (last element removed), delete it.
+        if old_cardinality == 1 && removed {
+            self.chunk = None;
+        }
+        removed
+    }
+
+    /// Returns true if the set contains the value.
+    pub fn contains(&self, value: u16) -> bool {
+        self.chunk
+            .as_ref()
+            .is_some_and(|chunk| chunk.contains(value))
+    }
+
+    /// Computes the set cardinality.
+    pub fn cardinality(&self) -> usize {
+        self.chunk.as_ref().map_or(0, Chunk::cardinality)
+    }
+
+    /// Finds the smallest value in the set.
+    pub fn min(&self) -> Option<u16> {
+        self.chunk.as_ref().and_then(Chunk::min)
+    }
+
+    /// Finds the largest value in the set.
+    pub fn max(&self) -> Option<u16> {
+        self.chunk.as_ref().and_then(Chunk::max)
+    }
+
+    /// Clears the set, removing all values.
+    pub fn clear(&mut self) {
+        self.chunk = None;
+    }
+
+    /// Returns true if the set contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.chunk.is_none()
+    }
+
+    /// Gets an iterator that visits the values in the set in ascending order.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter::new(self.chunk.as_ref())
+    }
+
+    /// Returns the union of two sets, as a new set.
+    #[must_use]
+    pub fn union(&self, other: &Self) -> Self {
+        self.combine(other, Container::or, true, true)
+    }
+
+    /// Returns the intersection of two sets, as a new set.
+    #[must_use]
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.combine(other, Container::and, false, false)
+    }
+
+    /// Returns the values in `self` but not in `other`, as a new set.
+    #[must_use]
+    pub fn difference(&self, other: &Self) -> Self {
+        self.combine(other, Container::and_not, true, false)
+    }
+
+    /// Returns the values in `self` or in `other` but not in both, as a new
+    /// set.
+    #[must_use]
+    pub fn symmetric_difference(&self, other: &Self) -> Self {
+        self.combine(other, Container::xor, true, true)
+    }
+
+    /// Converts the container to its most compact representation, including
+    /// run containers.
+    ///
+    /// Returns true if the set uses a run container.
+    pub fn run_optimize(&mut self) -> bool {
+        self.chunk.as_mut().is_some_and(Chunk::run_optimize)
+    }
+
+    /// Converts a run container back to an array or bitmap container.
+    ///
+    /// Returns true if the container was converted.
+    pub fn remove_run_compression(&mut self) -> bool {
+        self.chunk.as_mut().is_some_and(|chunk| {
+            chunk.remove_run_compression(&Tuning::default())
+        })
+    }
+
+    /// Returns the approximate in-memory size of the set, in bytes.
+    pub fn mem_size(&self) -> usize {
+        size_of_val(self) + self.chunk.as_ref().map_or(0, Chunk::mem_size)
+    }
+}
+
+// Private API.
+impl Bitmap {
+    /// Combines the chunks of two sets.
+    ///
+    /// Chunks present in both sets are combined using `op`, a chunk present
+    /// in only one of them is either copied or dropped.
+    fn combine(
+        &self,
+        other: &Self,
+        op: ContainerOp,
+        keep_lhs: bool,
+        keep_rhs: bool,
+    ) -> Self {
+        let chunk = match (self.chunk.as_ref(), other.chunk.as_ref()) {
+            (Some(lhs), Some(rhs)) => {
+                op(lhs.container(), rhs.container()).map(
+                    |(container, cardinality)| {
+                        Chunk::from_container(
+                            Header::new(),
+                            container,
+                            cardinality,
+                        )
+                    },
+                )
+            },
+            (Some(lhs), None) => keep_lhs.then(|| lhs.clone()),
+            (None, Some(rhs)) => keep_rhs.then(|| rhs.clone()),
+            (None, None) => None,
+        };
+
+        Self { chunk }
+    }
+}
+
+impl Extend<u16> for Bitmap {
+    fn extend<I: IntoIterator<Item = u16>>(&mut self, iterator: I) {
+        for value in iterator {
+            self.insert(value);
+        }
+    }
+}
+
+impl FromIterator<u16> for Bitmap {
+    fn from_iter<I: IntoIterator<Item = u16>>(iterator: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iterator);
+        set
+    }
+}
+
+impl<'a> IntoIterator for &'a Bitmap {
+    type Item = u16;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insertion_deletion() {
+        let mut set = Bitmap::new();
+        assert!(set.is_empty());
+
+        assert!(set.insert(42));
+        assert!(!set.insert(42), "already exists");
+        assert!(set.contains(42));
+        assert_eq!(set.cardinality(), 1);
+
+        assert!(set.remove(42));
+        assert!(!set.remove(42), "missing entry");
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn boundaries() {
+        let mut set = (0..=u16::MAX).collect::<Bitmap>();
+        assert_eq!(set.cardinality(), 65_536);
+        assert_eq!(set.min(), Some(u16::MIN));
+        assert_eq!(set.max(), Some(u16::MAX));
+        assert_eq!(set.iter().size_hint(), (65_536, Some(65_536)));
+
+        set.remove(u16::MAX);
+        assert_eq!(set.max(), Some(u16::MAX - 1));
+        assert_eq!(set.cardinality(), 65_535);
+    }
+
+    #[test]
+    fn iterator() {
+        let input = [3u16, 11, 42, 77, 100, 1_000, 65_000];
+        let set = input.iter().copied().collect::<Bitmap>();
+
+        assert_eq!(set.iter().collect::<Vec<_>>(), input);
+        assert_eq!((&set).into_iter().count(), input.len());
+        assert_eq!(Bitmap::new().iter().next(), None);
+    }
+
+    #[test]
+    fn set_operations() {
+        let lhs = [1u16, 2, 3, 10].iter().copied().collect::<Bitmap>();
+        let rhs = [3u16, 4, 10, 20].iter().copied().collect::<Bitmap>();
+        let empty = Bitmap::new();
+
+        let values = |set: &Bitmap| set.iter().collect::<Vec<_>>();
+        assert_eq!(values(&lhs.union(&rhs)), vec![1, 2, 3, 4, 10, 20]);
+        assert_eq!(values(&lhs.intersection(&rhs)), vec![3, 10]);
+        assert_eq!(values(&lhs.difference(&rhs)), vec![1, 2]);
+        assert_eq!(values(&lhs.symmetric_difference(&rhs)), vec![1, 2, 4, 20]);
+
+        assert_eq!(values(&lhs.union(&empty)), values(&lhs));
+        assert!(lhs.intersection(&empty).is_empty());
+        assert!(lhs.difference(&lhs).is_empty());
+    }
+
+    #[test]
+    fn run_compression() {
+        let mut set = (100..200).collect::<Bitmap>();
+        let size = set.mem_size();
+
+        assert!(set.run_optimize());
+        assert!(set.mem_size() < size);
+        assert!(set.remove_run_compression());
+        assert_eq!(
+            set.iter().collect::<Vec<_>>(),
+            (100..200).collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,41 @@
+use crate::chunk;
+
+/// Chunk header, without key as the set holds a single chunk.
+#[derive(Clone)]
+pub(super) struct Header {
+    /// Chunk's cardinality minus one.
+    ///
+    /// -1 allows to count up to 65536 while staying on 16-bit, and it's
+    /// safe because the minimum size is 1 (empty chunks are deallocated).
+    cardinality: u16,
+}
+
+impl Header {
+    /// Initializes a new Chunk's header.
+    pub(super) fn new() -> Self {
+        Self { cardinality: 0 }
+    }
+}
+
+impl chunk::Header for Header {
+    type Key = ();
+
+    fn key(&self) -> Self::Key {}
+
+    fn cardinality(&self) -> usize {
+        usize::from(self.cardinality) + 1
+    }
+
+    fn increase_cardinality(&mut self) {
+        self.cardinality += 1;
+    }
+
+    fn decrease_cardinality(&mut self) {
+        self.cardinality = self.cardinality.saturating_sub(1);
+    }
+
+    fn set_cardinality(&mut self, cardinality: usize) {
+        self.cardinality = u16::try_from(cardinality - 1)
+            .expect("chunk cardinality fits on 16-bit");
+    }
+}
@@ -0,0 +1,37 @@
+use super::Header;
+use crate::{chunk, Chunk};
+
+/// Immutable 16-bit set iterator.
+///
+/// This struct is created by the `iter` method on 16-bit set.
+pub struct Iter<'a> {
+    inner: Option<chunk::Iter<'a>>,
+    size: usize,
+}
+
+impl<'a> Iter<'a> {
+    pub(super) fn new(chunk: Option<&'a Chunk<Header>>) -> Self {
+        Self {
+            inner: chunk.map(Chunk::iter),
+            size: chunk.map_or(0, Chunk::cardinality),
+        }
+    }
+}
+
+impl Iterator for Iter<'_> {
+    type Item = u16;
+
+    fn next(&mut self) -> Option<u16> {
+        self.size = self.size.saturating_sub(1);
+        self.inner.as_mut().and_then(Iterator::next)
+    }
+
+    fn nth(&mut self, n: usize) -> Option<u16> {
+        self.size = self.size.saturating_sub(n + 1);
+        self.inner.as_mut().and_then(|inner| inner.nth(n))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.size, Some(self.size))
+    }
+}
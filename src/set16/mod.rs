@@ -0,0 +1,9 @@
+mod bitmap;
+mod header;
+mod iter;
+
+pub use bitmap::Bitmap as Set16;
+pub use iter::Iter as Set16Iter;
+
+use header::Header;
+use iter::Iter;
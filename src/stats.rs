@@ -1,5 +1,12 @@
+use crate::{chunk::Header, Chunk, Container};
+use core::fmt;
+
+/// Number of buckets in the container cardinality histogram.
+const HISTOGRAM_SIZE: usize = 17;
+
 /// Bitmap statistics.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Stats<T> {
     /// Total number of containers.
     pub nb_containers: usize,
@@ -7,6 +14,16 @@ pub struct Stats<T> {
     pub nb_array_containers: usize,
     /// Number of bitmap containers.
     pub nb_bitmap_containers: usize,
+    /// Number of run containers.
+    pub nb_run_containers: usize,
+    /// Number of full containers.
+    pub nb_full_containers: usize,
+
+    /// Number of super-chunks, for bitmaps using a two-level index (zero
+    /// otherwise).
+    pub nb_super_chunks: usize,
+    /// Largest number of containers held by a single super-chunk.
+    pub max_containers_per_super_chunk: usize,
 
     /// Total number of values stored (cardinality).
     pub nb_values: usize,
@@ -14,6 +31,10 @@ pub struct Stats<T> {
     pub nb_values_array_containers: usize,
     /// Number of values in bitmap containers.
     pub nb_values_bitmap_containers: usize,
+    /// Number of values in run containers.
+    pub nb_values_run_containers: usize,
+    /// Number of values in full containers.
+    pub nb_values_full_containers: usize,
 
     /// Total number of allocated bytes (approximated).
     pub nb_bytes: usize,
@@ -21,9 +42,333 @@ pub struct Stats<T> {
     pub nb_bytes_array_containers: usize,
     /// Number of allocated bytes (approximated) in bitmap containers.
     pub nb_bytes_bitmap_containers: usize,
+    /// Number of allocated bytes (approximated) in run containers.
+    pub nb_bytes_run_containers: usize,
+    /// Number of allocated bytes (approximated) in full containers.
+    pub nb_bytes_full_containers: usize,
+
+    /// Number of runs in run containers.
+    pub nb_runs: usize,
+    /// Histogram of the container cardinalities.
+    ///
+    /// Bucket `i` counts the containers holding between 2ⁱ and 2ⁱ⁺¹ - 1
+    /// values.
+    pub cardinality_histogram: [usize; HISTOGRAM_SIZE],
+    /// Cardinality of the smallest container, `None` if there is none.
+    pub min_container_cardinality: Option<usize>,
+    /// Cardinality of the largest container, `None` if there is none.
+    pub max_container_cardinality: Option<usize>,
 
     /// The minimal value, `None` if cardinality is zero.
     pub min_value: Option<T>,
     /// The maximal value, `None` if cardinality is zero.
     pub max_value: Option<T>,
 }
+
+impl<T> Stats<T> {
+    /// Initializes the statistics of a bitmap, without any container.
+    pub(crate) fn new(
+        nb_values: usize,
+        nb_bytes: usize,
+        min_value: Option<T>,
+        max_value: Option<T>,
+    ) -> Self {
+        Self {
+            nb_containers: 0,
+            nb_array_containers: 0,
+            nb_bitmap_containers: 0,
+            nb_run_containers: 0,
+            nb_full_containers: 0,
+
+            nb_super_chunks: 0,
+            max_containers_per_super_chunk: 0,
+
+            nb_values,
+            nb_values_array_containers: 0,
+            nb_values_bitmap_containers: 0,
+            nb_values_run_containers: 0,
+            nb_values_full_containers: 0,
+
+            nb_bytes,
+            nb_bytes_array_containers: 0,
+            nb_bytes_bitmap_containers: 0,
+            nb_bytes_run_containers: 0,
+            nb_bytes_full_containers: 0,
+
+            nb_runs: 0,
+            cardinality_histogram: [0; HISTOGRAM_SIZE],
+            min_container_cardinality: None,
+            max_container_cardinality: None,
+
+            min_value,
+            max_value,
+        }
+    }
+
+    /// Accounts for the container of a chunk.
+    pub(crate) fn add_chunk<H: Header>(&mut self, chunk: &Chunk<H>) {
+        let cardinality = chunk.cardinality();
+        let mem_size = chunk.mem_size();
+
+        self.nb_containers += 1;
+        match *chunk.container() {
+            Container::Array(_) => {
+                self.nb_array_containers += 1;
+                self.nb_values_array_containers += cardinality;
+                self.nb_bytes_array_containers += mem_size;
+            },
+            Container::Bitmap(_) => {
+                self.nb_bitmap_containers += 1;
+                self.nb_values_bitmap_containers += cardinality;
+                self.nb_bytes_bitmap_containers += mem_size;
+            },
+            Container::Run(_) => {
+                self.nb_run_containers += 1;
+                self.nb_values_run_containers += cardinality;
+                self.nb_bytes_run_containers += mem_size;
+                self.nb_runs += chunk.container().count_runs();
+            },
+            Container::Full => {
+                self.nb_full_containers += 1;
+                self.nb_values_full_containers += cardinality;
+                self.nb_bytes_full_containers += mem_size;
+            },
+        }
+
+        // Chunks are never empty.
+        self.cardinality_histogram[cardinality.ilog2() as usize] += 1;
+        self.update_container_cardinality(Some(cardinality), Some(cardinality));
+    }
+
+    /// Accounts for the containers of a super-chunk (i.e. a first-level
+    /// index entry), given its statistics.
+    pub(crate) fn add_super_chunk<U>(&mut self, stats: &Stats<U>) {
+        self.nb_super_chunks += 1;
+        self.max_containers_per_super_chunk =
+            self.max_containers_per_super_chunk.max(stats.nb_containers);
+
+        self.nb_containers += stats.nb_containers;
+        self.nb_array_containers += stats.nb_array_containers;
+        self.nb_bitmap_containers += stats.nb_bitmap_containers;
+        self.nb_run_containers += stats.nb_run_containers;
+        self.nb_full_containers += stats.nb_full_containers;
+        self.nb_values_array_containers += stats.nb_values_array_containers;
+        self.nb_values_bitmap_containers += stats.nb_values_bitmap_containers;
+        self.nb_values_run_containers += stats.nb_values_run_containers;
+        self.nb_values_full_containers += stats.nb_values_full_containers;
+        self.nb_bytes_array_containers += stats.nb_bytes_array_containers;
+        self.nb_bytes_bitmap_containers += stats.nb_bytes_bitmap_containers;
+        self.nb_bytes_run_containers += stats.nb_bytes_run_containers;
+        self.nb_bytes_full_containers += stats.nb_bytes_full_containers;
+
+        self.nb_runs += stats.nb_runs;
+        for (count, sub_count) in self
+            .cardinality_histogram
+            .iter_mut()
+            .zip(stats.cardinality_histogram.iter())
+        {
+            *count += sub_count;
+        }
+        self.update_container_cardinality(
+            stats.min_container_cardinality,
+            stats.max_container_cardinality,
+        );
+    }
+
+    /// Updates the smallest and largest container cardinalities.
+    fn update_container_cardinality(
+        &mut self,
+        min: Option<usize>,
+        max: Option<usize>,
+    ) {
+        self.min_container_cardinality =
+            match (self.min_container_cardinality, min) {
+                (Some(lhs), Some(rhs)) => Some(lhs.min(rhs)),
+                (lhs, rhs) => lhs.or(rhs),
+            };
+        self.max_container_cardinality =
+            self.max_container_cardinality.max(max);
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Stats<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Values:     {}", self.nb_values)?;
+        if let (Some(min), Some(max)) =
+            (self.min_value.as_ref(), self.max_value.as_ref())
+        {
+            write!(f, " (min: {min}, max: {max})")?;
+        }
+        writeln!(f)?;
+        writeln!(f, "Memory:     {}", ByteSize(self.nb_bytes))?;
+        write!(f, "Containers: {}", self.nb_containers)?;
+        if self.nb_super_chunks != 0 {
+            write!(
+                f,
+                " (in {} super-chunks, at most {} per super-chunk)",
+                self.nb_super_chunks, self.max_containers_per_super_chunk
+            )?;
+        }
+        writeln!(f)?;
+
+        let kinds = [
+            (
+                "array",
+                self.nb_array_containers,
+                self.nb_values_array_containers,
+                self.nb_bytes_array_containers,
+            ),
+            (
+                "bitmap",
+                self.nb_bitmap_containers,
+                self.nb_values_bitmap_containers,
+                self.nb_bytes_bitmap_containers,
+            ),
+            (
+                "run",
+                self.nb_run_containers,
+                self.nb_values_run_containers,
+                self.nb_bytes_run_containers,
+            ),
+            (
+                "full",
+                self.nb_full_containers,
+                self.nb_values_full_containers,
+                self.nb_bytes_full_containers,
+            ),
+        ];
+        for (name, nb_containers, nb_values, nb_bytes) in kinds {
+            writeln!(
+                f,
+                "  {name:<6}    {nb_containers} ({:.1}%), {nb_values} values \
+                 ({:.1}%), {} ({:.1}%)",
+                percent(nb_containers, self.nb_containers),
+                percent(nb_values, self.nb_values),
+                ByteSize(nb_bytes),
+                percent(nb_bytes, self.nb_bytes),
+            )?;
+        }
+        writeln!(f, "Runs:       {}", self.nb_runs)?;
+
+        if let (Some(min), Some(max)) = (
+            self.min_container_cardinality,
+            self.max_container_cardinality,
+        ) {
+            writeln!(f, "Container cardinality: min {min}, max {max}")?;
+            for (bucket, &count) in
+                self.cardinality_histogram.iter().enumerate()
+            {
+                if count != 0 {
+                    writeln!(
+                        f,
+                        "  [{}, {}]: {count}",
+                        1_usize << bucket,
+                        (1_usize << (bucket + 1)) - 1
+                    )?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the percentage represented by `part` in `total`.
+// Precision loss only matters for astronomical counts.
+#[allow(clippy::cast_precision_loss)]
+fn percent(part: usize, total: usize) -> f64 {
+    if total == 0 {
+        return 0.;
+    }
+    part as f64 * 100. / total as f64
+}
+
+/// Size in bytes, displayed with a human-scaled binary unit.
+struct ByteSize(usize);
+
+impl fmt::Display for ByteSize {
+    // Precision loss only matters for astronomical sizes.
+    #[allow(clippy::cast_precision_loss)]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+        if self.0 < 1024 {
+            return write!(f, "{} B", self.0);
+        }
+        let mut size = self.0 as f64 / 1024.;
+        let mut unit = 0;
+        while size >= 1024. && unit + 1 < UNITS.len() {
+            size /= 1024.;
+            unit += 1;
+        }
+        write!(f, "{size:.1} {}", UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Roaring;
+
+    #[test]
+    fn histogram() {
+        let bitmap = (0..3)
+            .chain(1 << 16..(1 << 16) + 100)
+            .chain((2 << 16..3 << 16).step_by(2))
+            .chain((5 << 16..6 << 16).filter(|value| value % 100 < 50))
+            .collect::<Roaring>();
+
+        let stats = bitmap.stats();
+        assert_eq!(stats.cardinality_histogram[1], 1, "3 values");
+        assert_eq!(stats.cardinality_histogram[6], 1, "100 values");
+        assert_eq!(stats.cardinality_histogram[14], 1, "32766 values");
+        assert_eq!(stats.cardinality_histogram[15], 1, "32768 values");
+        assert_eq!(stats.cardinality_histogram.iter().sum::<usize>(), 4);
+        assert_eq!(stats.min_container_cardinality, Some(3));
+        assert_eq!(stats.max_container_cardinality, Some(32_768));
+        assert_eq!(stats.nb_run_containers, 1);
+        assert_eq!(stats.nb_runs, 656);
+
+        let stats = Roaring::new().stats();
+        assert_eq!(stats.min_container_cardinality, None);
+        assert_eq!(stats.max_container_cardinality, None);
+    }
+
+    #[test]
+    fn byte_size() {
+        assert_eq!(ByteSize(0).to_string(), "0 B");
+        assert_eq!(ByteSize(1023).to_string(), "1023 B");
+        assert_eq!(ByteSize(1536).to_string(), "1.5 KiB");
+        assert_eq!(ByteSize(3 << 20).to_string(), "3.0 MiB");
+    }
+
+    #[test]
+    fn display() {
+        let bitmap = (0..10)
+            .chain((1 << 16..2 << 16).step_by(2))
+            .collect::<Roaring>();
+        let report = bitmap.stats().to_string();
+
+        assert!(report.starts_with("Values:     32778 (min: 0, max: 131070)\n"));
+        assert!(report.contains("Containers: 2\n"));
+        assert!(report.contains("  array     1 (50.0%), 10 values (0.0%)"));
+        assert!(report.contains("  [8, 15]: 1\n"));
+        assert!(report.contains("  [32768, 65535]: 1\n"));
+
+        let report = Roaring::new().stats().to_string();
+        assert!(report.starts_with("Values:     0\n"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialize() {
+        let bitmap = (0..10).collect::<Roaring>();
+        let json = serde_json::to_value(bitmap.stats()).expect("serializable");
+
+        assert_eq!(json["nb_values"], 10);
+        assert_eq!(json["nb_array_containers"], 1);
+        assert_eq!(json["min_value"], 0);
+        assert_eq!(json["max_value"], 9);
+        assert_eq!(json["cardinality_histogram"][3], 1);
+    }
+}